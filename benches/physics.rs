@@ -0,0 +1,124 @@
+//! Timings of the physics update in a few typical situations, run on the
+//! library `World` with no window and measured with criterion. Run with
+//! `cargo bench`, or `cargo bench -- NAME` for the benchmarks whose names
+//! contain `NAME`.
+//!
+//! Each benchmark builds its world and runs it for a while untimed so it is
+//! in the state being measured, then times ticks on it one after another.
+//! After `TICKS` of them the world is built again, untimed, so the state
+//! does not drift far from that.
+
+use std::time::{Duration, Instant};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use sandfall::{Drain, InputState, Material, World};
+
+const WIDTH: usize = 1024;
+const HEIGHT: usize = 768;
+/// Ticks timed on one world in a row before it is built again.
+const TICKS: u64 = 50;
+/// Samples criterion takes of each benchmark; its default of 100 would take
+/// minutes, as every world is slow to build.
+const SAMPLES: usize = 10;
+
+/// Builds a benchmark's world, and gives the tick to time on it.
+type Setup = fn() -> (World, fn(&mut World));
+
+/// A world with a wall floor and a layer of sand `depth` cells deep on it,
+/// run until the sand has settled.
+fn pile(depth: usize) -> World {
+    let mut world = World::new(WIDTH, HEIGHT, 42);
+    world.rect((0, HEIGHT - 1), (WIDTH - 1, HEIGHT - 1), Material::Wall, false);
+    world.rect((0, HEIGHT - 1 - depth), (WIDTH - 1, HEIGHT - 2), Material::Sand, false);
+    for _ in 0..200 {
+        world.step_parallel(&InputState::NONE);
+    }
+    world
+}
+
+/// A deep pile that has come to rest: the cost of a world where nothing
+/// moves.
+fn settled_pile() -> (World, fn(&mut World)) {
+    (pile(HEIGHT / 2), |world| world.step_parallel(&InputState::NONE))
+}
+
+/// Sand pouring from one spout onto a floor, so only a few chunks are busy.
+fn raining_column() -> (World, fn(&mut World)) {
+    let mut world = World::new(WIDTH, HEIGHT, 42);
+    world.rect((0, HEIGHT - 1), (WIDTH - 1, HEIGHT - 1), Material::Wall, false);
+    fn step(world: &mut World) {
+        world.spawn(WIDTH as isize / 2, 20, 8, 40, Material::Sand);
+        world.step_parallel(&InputState::NONE);
+    }
+    for _ in 0..200 {
+        step(&mut world);
+    }
+    (world, step)
+}
+
+/// A curtain of water the width of the world falling onto a drain as wide,
+/// so every chunk is busy.
+fn waterfall() -> (World, fn(&mut World)) {
+    let mut world = World::new(WIDTH, HEIGHT, 42);
+    world.add_drain(Drain { x: WIDTH / 2, y: HEIGHT - 1, half_width: WIDTH / 2 });
+    fn step(world: &mut World) {
+        for x in (0..WIDTH).step_by(2) {
+            world.place(x, 0, Material::Water);
+        }
+        world.step_parallel(&InputState::NONE);
+        world.drain();
+    }
+    for _ in 0..300 {
+        step(&mut world);
+    }
+    (world, step)
+}
+
+/// A settled pile the width of the world with a drain under all of it
+/// opened, so the whole pile collapses into it.
+fn drain_collapse() -> (World, fn(&mut World)) {
+    let mut world = pile(HEIGHT / 2);
+    world.add_drain(Drain { x: WIDTH / 2, y: HEIGHT - 2, half_width: WIDTH / 2 });
+    fn step(world: &mut World) {
+        world.step_parallel(&InputState::NONE);
+        world.drain();
+    }
+    (world, step)
+}
+
+/// How long `ticks` ticks take on worlds from `setup`, not counting the
+/// time spent building them.
+fn time(setup: Setup, ticks: u64) -> Duration {
+    let mut elapsed = Duration::ZERO;
+    let mut done = 0;
+    while done < ticks {
+        let (mut world, step) = setup();
+        let run = TICKS.min(ticks - done);
+        let start = Instant::now();
+        for _ in 0..run {
+            step(&mut world);
+        }
+        elapsed += start.elapsed();
+        done += run;
+    }
+    elapsed
+}
+
+fn physics(c: &mut Criterion) {
+    let benches: [(&str, Setup); 4] = [
+        ("settled_pile", settled_pile),
+        ("raining_column", raining_column),
+        ("waterfall", waterfall),
+        ("drain_collapse", drain_collapse),
+    ];
+    for (name, setup) in benches {
+        c.bench_function(name, |b| b.iter_custom(|ticks| time(setup, ticks)));
+    }
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(SAMPLES);
+    targets = physics
+}
+criterion_main!(benches);
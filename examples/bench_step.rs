@@ -0,0 +1,45 @@
+//! Times the serial and parallel physics updates on a large world.
+//! Run with `cargo run -r --example bench_step`.
+
+use std::time::{Duration, Instant};
+
+use sandfall::{InputState, Material, World};
+
+const WIDTH: usize = 2048;
+const HEIGHT: usize = 1024;
+const TICKS: u32 = 200;
+
+/// A world with sand pouring in from a row of spouts over water, so most
+/// chunks stay busy for the whole run.
+fn busy_world() -> World {
+    let mut world = World::new(WIDTH, HEIGHT, 42);
+    for x in (32..WIDTH as isize).step_by(64) {
+        world.spawn(x, HEIGHT as isize - 120, 30, 2500, Material::Water);
+    }
+    world
+}
+
+fn time(name: &str, step: fn(&mut World)) -> Duration {
+    let mut world = busy_world();
+    let start = Instant::now();
+    for _ in 0..TICKS {
+        for x in (32..WIDTH as isize).step_by(64) {
+            world.spawn(x, 40, 12, 30, Material::Sand);
+        }
+        step(&mut world);
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{name:>8}: {:>7.2} ms/tick, {} grains",
+        elapsed.as_secs_f64() * 1000.0 / TICKS as f64,
+        world.grains().len()
+    );
+    elapsed
+}
+
+fn main() {
+    println!("{TICKS} ticks of a {WIDTH}x{HEIGHT} world on {} threads", rayon::current_num_threads());
+    let serial = time("serial", |world| world.step(&InputState::NONE));
+    let parallel = time("parallel", |world| world.step_parallel(&InputState::NONE));
+    println!(" speedup: {:.2}x", serial.as_secs_f64() / parallel.as_secs_f64());
+}
@@ -0,0 +1,50 @@
+//! Times the sand-only compute shader against the same rule on the CPU, on
+//! a world of a few million cells, and checks the two end up the same.
+//! Run with `cargo run -r --features compute --example compute_sand`.
+
+use std::time::Instant;
+
+use sandfall::compute::{Sand, SAND, WALL};
+
+const WIDTH: usize = 2048;
+const HEIGHT: usize = 1536;
+const TICKS: u32 = 600;
+
+/// A grid with a slab of sand over a row of ledges.
+fn fill(sand: &mut Sand) {
+    for y in 0..HEIGHT / 3 {
+        for x in WIDTH / 4..3 * WIDTH / 4 {
+            sand.set(x, y, SAND);
+        }
+    }
+    for x in (0..WIDTH).filter(|x| x % 256 < 160) {
+        sand.set(x, 2 * HEIGHT / 3, WALL);
+    }
+}
+
+/// Run `TICKS` ticks and return the cells and how long they took.
+fn time(name: &str, mut sand: Sand) -> Vec<u32> {
+    fill(&mut sand);
+    let start = Instant::now();
+    sand.step(TICKS);
+    let cells = sand.cells().as_slice().to_vec();
+    let elapsed = start.elapsed();
+    println!(
+        "{name:>4}: {:>7.2} ms/tick over {} cells",
+        elapsed.as_secs_f64() * 1000.0 / TICKS as f64,
+        WIDTH * HEIGHT
+    );
+    cells
+}
+
+fn main() {
+    let gpu = Sand::new(WIDTH, HEIGHT);
+    if !gpu.is_gpu() {
+        println!("no GPU that runs compute shaders; both runs are on the CPU");
+    }
+    let gpu = time("gpu", gpu);
+    let cpu = time("cpu", Sand::cpu(WIDTH, HEIGHT));
+    let grains = |cells: &[u32]| cells.iter().filter(|&&c| c == SAND).count();
+    println!("grains: {} and {}", grains(&gpu), grains(&cpu));
+    assert!(gpu == cpu, "the GPU and CPU disagree");
+}
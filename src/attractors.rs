@@ -0,0 +1,43 @@
+// Attractors: points that pull every grain near them, or push it away, with
+// a force falling off with the square of the distance. The pull is added to
+// each grain's velocity before the grains move, so a grain that is already
+// moving curves round an attractor instead of dropping straight onto it,
+// and sand poured past one swirls about it.
+
+use crate::world::{World, MAX_SPEED};
+
+/// Pulls weaker than this, in cells per tick per tick, are left out, which
+/// bounds how far an attractor reaches.
+const MIN_PULL: f32 = 0.01;
+/// The pull is no stronger than at this distance, so grains at the point
+/// itself are not flung off at full speed.
+const MIN_DISTANCE: f32 = 2.0;
+
+/// Add the pull of every attractor to the velocity of each grain in reach,
+/// and wake the grains pulled.
+pub(crate) fn pull(world: &mut World) {
+    if world.attractors.is_empty() {
+        return;
+    }
+    for i in 0..world.attractors.len() {
+        let a = world.attractors[i];
+        let reach = (a.strength.abs() / MIN_PULL).sqrt();
+        for g in &mut world.grains {
+            let dx = a.x as f32 - g.x as f32;
+            let dy = a.y as f32 - g.y as f32;
+            if dx.abs() > reach || dy.abs() > reach {
+                continue;
+            }
+            let d2 = dx * dx + dy * dy;
+            if d2 > reach * reach || d2 == 0.0 {
+                continue;
+            }
+            let d = d2.sqrt();
+            let pull = a.strength / d2.max(MIN_DISTANCE * MIN_DISTANCE);
+            g.vx = (g.vx + pull * dx / d).clamp(-MAX_SPEED, MAX_SPEED);
+            g.vy = (g.vy + pull * dy / d).clamp(-MAX_SPEED, MAX_SPEED);
+            g.idle = 0;
+            world.chunks.wake(g.x, g.y);
+        }
+    }
+}
@@ -0,0 +1,185 @@
+// Sound effects for the `audio` feature: a soft patter of grains that grows
+// with how many landed, and a whoosh while the drains are open. The world's
+// events bump a counter and the frontend says when the drains open; cpal's
+// audio thread turns them into noise, straight into the system's default
+// sound device, which also paces it to real time.
+
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample, Stream, StreamConfig};
+
+use sandfall::{Event, World};
+
+/// Sample rate the easing and filter rates below are given at; they are
+/// adjusted to the sound device's own.
+const TUNED_RATE: f32 = 22_050.0;
+/// Blocks of samples a second, made between looks at the counters.
+const BLOCKS_PER_SECOND: u32 = 60;
+/// Grains landing per block that make the patter as loud as it gets.
+const FULL_PATTER: f32 = 400.0;
+/// Loudness of the patter at its loudest, out of 1.
+const PATTER_VOLUME: f32 = 0.35;
+/// Gain on the whoosh's noise, most of which its low-pass filter takes out.
+const WHOOSH_VOLUME: f32 = 0.8;
+
+/// What the world and the frontend tell the audio thread.
+#[derive(Default)]
+struct Counters {
+    /// Grains landed since the audio thread last looked.
+    landed: AtomicU32,
+    /// Grains drained since the audio thread last looked.
+    drained: AtomicU32,
+    /// The drains are open.
+    open: AtomicBool,
+}
+
+/// Sound playing on the default output device. Stops when dropped.
+pub struct Sound {
+    // Kept only to keep playing.
+    _stream: Stream,
+    counters: Arc<Counters>,
+}
+
+impl Sound {
+    /// Open the default sound device and listen to `world`'s events.
+    pub fn start(world: &mut World) -> Result<Self, String> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or_else(|| "no sound device".to_string())?;
+        let supported = device.default_output_config().map_err(|e| e.to_string())?;
+        let config = supported.config();
+
+        let counters = Arc::new(Counters::default());
+        let heard = counters.clone();
+        world.on_event(move |event| match event {
+            Event::GrainSettled { .. } => {
+                heard.landed.fetch_add(1, Ordering::Relaxed);
+            }
+            Event::GrainDrained { .. } => {
+                heard.drained.fetch_add(1, Ordering::Relaxed);
+            }
+            _ => {}
+        });
+
+        let stream = match supported.sample_format() {
+            SampleFormat::F32 => stream::<f32>(&device, &config, counters.clone()),
+            SampleFormat::I16 => stream::<i16>(&device, &config, counters.clone()),
+            SampleFormat::U16 => stream::<u16>(&device, &config, counters.clone()),
+            format => return Err(format!("unsupported sample format {format}")),
+        }?;
+        stream.play().map_err(|e| e.to_string())?;
+        Ok(Self { _stream: stream, counters })
+    }
+
+    /// Say whether the drains are open this frame, which brings the whoosh
+    /// in or out.
+    pub fn set_drain(&self, open: bool) {
+        self.counters.open.store(open, Ordering::Relaxed);
+    }
+}
+
+/// An output stream on `device` playing the noise made from `counters` as
+/// samples of type `T`, the same on every channel.
+fn stream<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    counters: Arc<Counters>,
+) -> Result<Stream, String>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let channels = config.channels as usize;
+    let mut noise = Noise::new(config.sample_rate as f32, counters);
+    device
+        .build_output_stream(
+            config,
+            move |data: &mut [T], _| {
+                for frame in data.chunks_mut(channels) {
+                    frame.fill(T::from_sample(noise.next()));
+                }
+            },
+            |e| eprintln!("sandfall: sound: {e}"),
+            None,
+        )
+        .map_err(|e| e.to_string())
+}
+
+/// The patter and whoosh, one sample at a time.
+struct Noise {
+    counters: Arc<Counters>,
+    rng: u32,
+    /// Samples per block, and how many are left of this one.
+    block: u32,
+    left: u32,
+    /// Chance per sample of a grain's click: busier ticks patter faster.
+    click_chance: f32,
+    /// Loudness, eased towards its target so the sound does not jump from
+    /// block to block.
+    patter: f32,
+    whoosh: f32,
+    patter_target: f32,
+    whoosh_target: f32,
+    /// One-pole low-pass filter states: the patter is soft clicks, the
+    /// whoosh deep rushing noise.
+    click: f32,
+    rush: f32,
+    /// Per-sample easing and filter rates at the device's sample rate.
+    rates: [f32; 4],
+}
+
+impl Noise {
+    fn new(sample_rate: f32, counters: Arc<Counters>) -> Self {
+        // A rate of `r` per sample at `TUNED_RATE` closes the same part of
+        // the gap each second at `sample_rate`.
+        let rate = |r: f32| 1.0 - (1.0 - r).powf(TUNED_RATE / sample_rate);
+        Self {
+            counters,
+            rng: 0x9e37_79b9,
+            block: (sample_rate as u32 / BLOCKS_PER_SECOND).max(1),
+            left: 0,
+            click_chance: 0.0,
+            patter: 0.0,
+            whoosh: 0.0,
+            patter_target: 0.0,
+            whoosh_target: 0.0,
+            click: 0.0,
+            rush: 0.0,
+            rates: [rate(0.002), rate(0.0005), rate(0.3), rate(0.04)],
+        }
+    }
+
+    /// White noise from -1 to 1.
+    fn white(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        self.rng as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+
+    /// The next sample, from -1 to 1.
+    fn next(&mut self) -> f32 {
+        if self.left == 0 {
+            self.left = self.block;
+            let landed = self.counters.landed.swap(0, Ordering::Relaxed) as f32;
+            let drained = self.counters.drained.swap(0, Ordering::Relaxed);
+            let open = self.counters.open.load(Ordering::Relaxed);
+            self.patter_target = (landed / FULL_PATTER).sqrt().min(1.0);
+            self.whoosh_target = if open || drained > 0 { 1.0 } else { 0.0 };
+            self.click_chance = (landed / self.block as f32).min(0.5);
+        }
+        self.left -= 1;
+
+        let [patter_rate, whoosh_rate, click_rate, rush_rate] = self.rates;
+        self.patter += (self.patter_target - self.patter) * patter_rate;
+        self.whoosh += (self.whoosh_target - self.whoosh) * whoosh_rate;
+        let impulse = if self.white().abs() < self.click_chance { self.white() } else { 0.0 };
+        self.click += (impulse - self.click) * click_rate;
+        let rush = self.white();
+        self.rush += (rush - self.rush) * rush_rate;
+        let sample =
+            self.click * self.patter * PATTER_VOLUME + self.rush * self.whoosh * WHOOSH_VOLUME;
+        sample.clamp(-1.0, 1.0)
+    }
+}
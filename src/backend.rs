@@ -0,0 +1,256 @@
+// The window the frontends draw into and read input from, behind a trait so
+// the presentation layer can be swapped without touching the frame loops.
+// Every backend takes the same flat 0xAARRGGBB frame the renderer draws, and
+// reports input with minifb's `Key` and `MouseButton` as the vocabulary.
+// minifb is used unless the `softbuffer` feature swaps in a winit window
+// drawn with softbuffer, for desktops minifb does not get along with, or
+// the `wgpu` feature one drawn with wgpu, in gpu.rs, which wins if both are
+// on.
+
+#[cfg(all(feature = "softbuffer", not(feature = "wgpu")))]
+use std::num::NonZeroU32;
+#[cfg(all(feature = "softbuffer", not(feature = "wgpu")))]
+use std::sync::Arc;
+
+pub use minifb::{Key, MouseButton};
+#[cfg(not(any(feature = "softbuffer", feature = "wgpu")))]
+use minifb::{KeyRepeat, MouseMode, ScaleMode, Window, WindowOptions};
+
+use sandfall::grid::Grid;
+
+#[cfg(all(feature = "softbuffer", not(feature = "wgpu")))]
+use crate::winit_window::WinitWindow;
+
+/// A window showing frames and taking keyboard and mouse input.
+pub trait Backend {
+    /// False once the window has been closed.
+    fn is_open(&self) -> bool;
+    fn is_key_down(&self, key: Key) -> bool;
+    /// Keys that went down since the last frame, with the keyboard's repeat
+    /// while they are held, for typing.
+    fn keys_pressed(&self) -> Vec<Key>;
+    fn is_mouse_down(&self, button: MouseButton) -> bool;
+    /// Mouse position in window pixels, `None` while it is outside the
+    /// window.
+    fn mouse_pos(&self) -> Option<(f32, f32)>;
+    /// How far the wheel turned since the last frame, `None` if it did not.
+    fn scroll_wheel(&self) -> Option<(f32, f32)>;
+    /// Size of the area frames are shown in, which changes when the window
+    /// is resized.
+    fn size(&self) -> (usize, usize);
+    fn set_title(&mut self, title: &str);
+    /// Swap to a borderless window of `size` at the top-left of the desktop,
+    /// or back to the window as it was.
+    fn set_fullscreen(&mut self, fullscreen: bool, size: (usize, usize));
+    /// Show `frame`, scaled to fit the window with bars either side if it
+    /// is not the window's shape, and take in new input.
+    fn present(&mut self, frame: &Grid<u32>);
+}
+
+/// Open a `width` x `height` window titled `title`, which the user can
+/// resize if `resizable`.
+pub fn open(title: &str, width: usize, height: usize, resizable: bool) -> Box<dyn Backend> {
+    #[cfg(feature = "wgpu")]
+    return Box::new(crate::gpu::Gpu::new(title, width, height, resizable));
+    #[cfg(all(feature = "softbuffer", not(feature = "wgpu")))]
+    return Box::new(Softbuffer::new(title, width, height, resizable));
+    #[cfg(not(any(feature = "softbuffer", feature = "wgpu")))]
+    Box::new(Minifb::new(title, width, height, resizable))
+}
+
+/// A backend on a minifb window.
+#[cfg(not(any(feature = "softbuffer", feature = "wgpu")))]
+struct Minifb {
+    window: Window,
+    title: String,
+    resizable: bool,
+    /// Size and position of the window before it went fullscreen.
+    windowed: (usize, usize, isize, isize),
+}
+
+#[cfg(not(any(feature = "softbuffer", feature = "wgpu")))]
+impl Minifb {
+    fn new(title: &str, width: usize, height: usize, resizable: bool) -> Self {
+        Self {
+            window: Self::window(title, width, height, resizable, false),
+            title: title.to_string(),
+            resizable,
+            windowed: (width, height, 0, 0),
+        }
+    }
+
+    /// Open the window, either with a frame or, for fullscreen, borderless
+    /// over everything else at the top-left of the desktop.
+    fn window(title: &str, width: usize, height: usize, resize: bool, fullscreen: bool) -> Window {
+        let options = WindowOptions {
+            resize: resize && !fullscreen,
+            borderless: fullscreen,
+            topmost: fullscreen,
+            scale_mode: ScaleMode::AspectRatioStretch,
+            ..WindowOptions::default()
+        };
+        let mut window =
+            Window::new(title, width, height, options).expect("Unable to create window");
+        if fullscreen {
+            window.set_position(0, 0);
+        }
+        window
+    }
+}
+
+#[cfg(not(any(feature = "softbuffer", feature = "wgpu")))]
+impl Backend for Minifb {
+    fn is_open(&self) -> bool {
+        self.window.is_open()
+    }
+
+    fn is_key_down(&self, key: Key) -> bool {
+        self.window.is_key_down(key)
+    }
+
+    fn keys_pressed(&self) -> Vec<Key> {
+        self.window.get_keys_pressed(KeyRepeat::Yes)
+    }
+
+    fn is_mouse_down(&self, button: MouseButton) -> bool {
+        self.window.get_mouse_down(button)
+    }
+
+    fn mouse_pos(&self) -> Option<(f32, f32)> {
+        self.window.get_mouse_pos(MouseMode::Discard)
+    }
+
+    fn scroll_wheel(&self) -> Option<(f32, f32)> {
+        self.window.get_scroll_wheel()
+    }
+
+    fn size(&self) -> (usize, usize) {
+        self.window.get_size()
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.title = title.to_string();
+        self.window.set_title(title);
+    }
+
+    fn set_fullscreen(&mut self, fullscreen: bool, size: (usize, usize)) {
+        // minifb cannot change a window's style, so a new one replaces it.
+        let (w, h, x, y) = if fullscreen {
+            let (w, h) = self.window.get_size();
+            let (x, y) = self.window.get_position();
+            self.windowed = (w, h, x, y);
+            (size.0, size.1, 0, 0)
+        } else {
+            self.windowed
+        };
+        self.window = Self::window(&self.title, w, h, self.resizable, fullscreen);
+        if !fullscreen {
+            self.window.set_position(x, y);
+        }
+    }
+
+    fn present(&mut self, frame: &Grid<u32>) {
+        self.window
+            .update_with_buffer(frame.as_slice(), frame.width(), frame.height())
+            .expect("Failed to update window");
+    }
+}
+
+/// A backend on a winit window, drawn into on the CPU with softbuffer.
+#[cfg(all(feature = "softbuffer", not(feature = "wgpu")))]
+struct Softbuffer {
+    window: WinitWindow,
+    surface: softbuffer::Surface<Arc<winit::window::Window>, Arc<winit::window::Window>>,
+    /// For each column of the window, the column of the frame shown there,
+    /// or `None` in a bar; kept between frames as it rarely changes.
+    columns: Vec<Option<usize>>,
+    /// The window and frame sizes `columns` was worked out for.
+    fitted: ((usize, usize), (usize, usize)),
+}
+
+#[cfg(all(feature = "softbuffer", not(feature = "wgpu")))]
+impl Softbuffer {
+    fn new(title: &str, width: usize, height: usize, resizable: bool) -> Self {
+        let window = WinitWindow::open(title, width, height, resizable);
+        let context = softbuffer::Context::new(window.window()).expect("Unable to create window");
+        let surface =
+            softbuffer::Surface::new(&context, window.window()).expect("Unable to create window");
+        Self { window, surface, columns: Vec::new(), fitted: ((0, 0), (0, 0)) }
+    }
+}
+
+#[cfg(all(feature = "softbuffer", not(feature = "wgpu")))]
+impl Backend for Softbuffer {
+    fn is_open(&self) -> bool {
+        self.window.is_open()
+    }
+
+    fn is_key_down(&self, key: Key) -> bool {
+        self.window.is_key_down(key)
+    }
+
+    fn keys_pressed(&self) -> Vec<Key> {
+        self.window.keys_pressed()
+    }
+
+    fn is_mouse_down(&self, button: MouseButton) -> bool {
+        self.window.is_mouse_down(button)
+    }
+
+    fn mouse_pos(&self) -> Option<(f32, f32)> {
+        self.window.mouse_pos()
+    }
+
+    fn scroll_wheel(&self) -> Option<(f32, f32)> {
+        self.window.scroll_wheel()
+    }
+
+    fn size(&self) -> (usize, usize) {
+        self.window.size()
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.window.set_title(title);
+    }
+
+    fn set_fullscreen(&mut self, fullscreen: bool, _: (usize, usize)) {
+        self.window.set_fullscreen(fullscreen);
+    }
+
+    fn present(&mut self, frame: &Grid<u32>) {
+        let (ww, wh) = self.window.size();
+        let (fw, fh) = (frame.width(), frame.height());
+        if let (Some(w), Some(h)) = (NonZeroU32::new(ww as u32), NonZeroU32::new(wh as u32)) {
+            self.surface.resize(w, h).expect("Failed to update window");
+            // Scale to fit keeping the frame's shape, as minifb's
+            // `AspectRatioStretch` does, nearest cell first.
+            let scale = (ww as f32 / fw as f32).min(wh as f32 / fh as f32);
+            let left = (ww as f32 - fw as f32 * scale) / 2.0;
+            let top = (wh as f32 - fh as f32 * scale) / 2.0;
+            if self.fitted != ((ww, wh), (fw, fh)) {
+                self.fitted = ((ww, wh), (fw, fh));
+                self.columns = (0..ww)
+                    .map(|x| {
+                        let fx = (x as f32 + 0.5 - left) / scale;
+                        (fx >= 0.0 && fx < fw as f32).then_some(fx as usize)
+                    })
+                    .collect();
+            }
+            let mut buffer = self.surface.buffer_mut().expect("Failed to update window");
+            for (y, row) in buffer.chunks_exact_mut(ww).enumerate() {
+                let fy = (y as f32 + 0.5 - top) / scale;
+                if fy < 0.0 || fy >= fh as f32 {
+                    row.fill(0);
+                    continue;
+                }
+                let cells = &frame.as_slice()[fy as usize * fw..][..fw];
+                for (px, column) in row.iter_mut().zip(&self.columns) {
+                    // softbuffer wants the top byte clear.
+                    *px = column.map_or(0, |fx| cells[fx] & 0x00ff_ffff);
+                }
+            }
+            buffer.present().expect("Failed to update window");
+        }
+        self.window.update();
+    }
+}
@@ -0,0 +1,132 @@
+// The shape the brush tool draws with. Every shape is applied through
+// `Brush::apply`, so a new one only needs a `Shape` variant and an arm there;
+// `Brush::stroke` repeats it along the cursor's path.
+
+use crate::material::Material;
+use crate::world::World;
+
+/// What the brush puts down under the cursor each frame the button is held.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shape {
+    /// Scatters grains at random points inside the circle, `rate` a second
+    /// however fast the frames come, so holding it builds up a pile.
+    Spray,
+    /// Fills every empty cell inside the circle.
+    Disc,
+    /// Fills every empty cell inside the square the circle fits in.
+    Square,
+    /// Places a single cell, whatever the radius.
+    Pencil,
+}
+
+impl Shape {
+    /// Every shape, in the order `cycle_shape` steps through them.
+    pub const ALL: [Shape; 4] = [Shape::Spray, Shape::Disc, Shape::Square, Shape::Pencil];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Shape::Spray => "spray",
+            Shape::Disc => "disc",
+            Shape::Square => "square",
+            Shape::Pencil => "pencil",
+        }
+    }
+}
+
+/// A brush shape and size.
+pub struct Brush {
+    pub shape: Shape,
+    /// Radius of the circle, or half the side of the square, in cells.
+    pub radius: usize,
+    /// Grains the spray tries to place per second.
+    pub rate: usize,
+    /// Grains the spray places this frame, set by `advance`.
+    tries: usize,
+    /// The part of a grain owed from earlier frames.
+    owed: f32,
+}
+
+impl Brush {
+    pub fn new(radius: usize, rate: usize) -> Self {
+        Self { shape: Shape::Spray, radius, rate, tries: 0, owed: 0.0 }
+    }
+
+    /// Work out how many grains the spray places this frame, `dt` seconds
+    /// after the last. Fractions of a grain are carried over, so a slow
+    /// rate or a fast frame rate still adds up to `rate` a second.
+    pub fn advance(&mut self, dt: f32) {
+        self.owed += self.rate as f32 * dt;
+        self.tries = self.owed as usize;
+        self.owed -= self.tries as f32;
+    }
+
+    /// Switch to the next shape.
+    pub fn cycle_shape(&mut self) {
+        self.shape = match self.shape {
+            Shape::Spray => Shape::Disc,
+            Shape::Disc => Shape::Square,
+            Shape::Square => Shape::Pencil,
+            Shape::Pencil => Shape::Spray,
+        };
+    }
+
+    /// Put `material` down along the line from cell `from` to cell `to`,
+    /// where the cursor was last frame and is now, so a fast stroke leaves
+    /// an unbroken trail rather than a row of blobs. The brush is put down
+    /// at steps half its radius apart, not counting `from`, which was drawn
+    /// last frame, and the spray's grains for the frame are shared between
+    /// them.
+    pub fn stroke(
+        &self,
+        world: &mut World,
+        from: (usize, usize),
+        to: (usize, usize),
+        material: Material,
+    ) {
+        let spacing = match self.shape {
+            Shape::Pencil => 1.0,
+            _ => (self.radius as f32 / 2.0).max(1.0),
+        };
+        let (dx, dy) = (to.0 as f32 - from.0 as f32, to.1 as f32 - from.1 as f32);
+        let steps = (dx.abs().max(dy.abs()) / spacing).ceil().max(1.0) as usize;
+        for i in 1..=steps {
+            let t = i as f32 / steps as f32;
+            let x = (from.0 as f32 + dx * t).round() as usize;
+            let y = (from.1 as f32 + dy * t).round() as usize;
+            let tries = self.tries / steps + usize::from(i <= self.tries % steps);
+            self.apply(world, (x, y), material, tries);
+        }
+    }
+
+    /// Put `material` down around cell `pos`, spraying `tries` grains.
+    /// Static materials are never sprayed, as scattered specks of wall are
+    /// no use; the spray paints them solid instead. Movable materials are
+    /// placed a grain at a time, as `World::paint` only makes static cells.
+    fn apply(&self, world: &mut World, pos: (usize, usize), material: Material, tries: usize) {
+        let (x, y) = pos;
+        let r = self.radius;
+        match self.shape {
+            Shape::Spray if material.is_movable() => {
+                world.spawn(x as isize, y as isize, r, tries, material);
+            }
+            Shape::Disc if material.is_movable() => {
+                let r = r as isize;
+                for dy in -r..=r {
+                    for dx in -r..=r {
+                        let (cx, cy) = (x as isize + dx, y as isize + dy);
+                        if dx * dx + dy * dy <= r * r && world.cells().in_bounds(cx, cy) {
+                            world.place(cx as usize, cy as usize, material);
+                        }
+                    }
+                }
+            }
+            Shape::Spray | Shape::Disc => world.paint(x as isize, y as isize, r, material),
+            Shape::Square => {
+                let from = (x.saturating_sub(r), y.saturating_sub(r));
+                let to = ((x + r).min(world.width() - 1), (y + r).min(world.height() - 1));
+                world.rect(from, to, material, false);
+            }
+            Shape::Pencil => world.place(x, y, material),
+        }
+    }
+}
@@ -0,0 +1,77 @@
+// The `--mode btw` frontend: an abelian sandpile in place of the falling
+// sand world. Grains pour onto the middle of the window, or wherever the
+// left mouse button is held, and the pile is drawn by how many grains each
+// cell holds.
+
+use sandfall::grid::Grid;
+use sandfall::sandpile::{Sandpile, TOPPLE_AT};
+
+use crate::backend::{self, Key, MouseButton};
+use crate::draw_hud;
+
+/// Grains dropped per frame.
+const GRAINS_PER_FRAME: u32 = 64;
+/// Most cells toppled per frame, so a big avalanche plays out over several
+/// frames instead of freezing the window.
+const TOPPLES_PER_FRAME: usize = 200_000;
+
+/// Run the sandpile in a `width` x `height` window until it is closed.
+pub fn run(width: usize, height: usize) {
+    let mut window = backend::open("Sandpile", width, height, false);
+    let mut pile = Sandpile::new(width, height);
+    let mut frame = Grid::new(width, height, Sandpile::color(0).to_u32());
+    let palette: Vec<u32> = (0..=TOPPLE_AT)
+        .map(|count| Sandpile::color(count).to_u32())
+        .collect();
+
+    let mut paused = false;
+    let mut last_p_state = false;
+    let mut show_hud = false;
+    let mut last_f1_state = false;
+    let mut dropped: u64 = 0;
+
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        let p_state = window.is_key_down(Key::P);
+        if p_state && !last_p_state {
+            paused = !paused;
+        }
+        last_p_state = p_state;
+
+        let f1_state = window.is_key_down(Key::F1);
+        if f1_state && !last_f1_state {
+            show_hud = !show_hud;
+        }
+        last_f1_state = f1_state;
+
+        if window.is_key_down(Key::C) {
+            pile.clear();
+            dropped = 0;
+        }
+
+        if !paused {
+            let (x, y) = match window.mouse_pos() {
+                Some((mx, my)) if window.is_mouse_down(MouseButton::Left) => {
+                    (mx as usize, my as usize)
+                }
+                _ => (width / 2, height / 2),
+            };
+            pile.add(x, y, GRAINS_PER_FRAME);
+            dropped += GRAINS_PER_FRAME as u64;
+            pile.relax(TOPPLES_PER_FRAME);
+        }
+
+        for (px, &count) in frame.as_mut_slice().iter_mut().zip(pile.counts().as_slice()) {
+            *px = palette[count.min(TOPPLE_AT) as usize];
+        }
+        if show_hud {
+            let lines = [
+                format!("DROPPED {dropped}"),
+                format!("TOPPLES {}", pile.topples()),
+                if pile.is_stable() { "STABLE".to_string() } else { "TOPPLING".to_string() },
+            ];
+            draw_hud(&mut frame, &lines);
+        }
+
+        window.present(&frame);
+    }
+}
@@ -0,0 +1,157 @@
+// Maps between world cells and screen pixels so the frontend can zoom into
+// and pan around a world that may be larger than the window, and from the
+// window to the frame drawn in it when the two are not the same size.
+
+/// Most screen pixels a single cell can be stretched across.
+pub const MAX_ZOOM: f32 = 16.0;
+
+/// A view onto the world: which cell sits at the top-left of the screen and
+/// how many screen pixels each cell covers.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Camera {
+    /// World position of the top-left corner of the screen, in cells.
+    pub x: f32,
+    pub y: f32,
+    /// Screen pixels per cell.
+    pub zoom: f32,
+    view_w: usize,
+    view_h: usize,
+    world_w: usize,
+    world_h: usize,
+}
+
+impl Camera {
+    /// A camera for a `view_w` x `view_h` screen over a `world_w` x
+    /// `world_h` world, showing a cell per pixel, or closer if the world is
+    /// too small to fill the screen that way. A larger world is shown from
+    /// the middle of its floor, where grains end up.
+    pub fn new(view_w: usize, view_h: usize, world_w: usize, world_h: usize) -> Self {
+        let mut camera = Self { x: 0.0, y: 0.0, zoom: 1.0, view_w, view_h, world_w, world_h };
+        camera.zoom = camera.min_zoom().max(1.0);
+        camera.x = (world_w as f32 - view_w as f32 / camera.zoom) / 2.0;
+        camera.y = world_h as f32;
+        camera.clamp();
+        camera
+    }
+
+    pub fn view_size(&self) -> (usize, usize) {
+        (self.view_w, self.view_h)
+    }
+
+    /// Change the screen size to `view_w` x `view_h`, e.g. when the window
+    /// is resized, keeping the world point at the centre of the screen where
+    /// it is. The zoom goes up if the world would no longer fill the screen.
+    pub fn resize(&mut self, view_w: usize, view_h: usize) {
+        let (cx, cy) = self.screen_to_world(self.view_w as f32 / 2.0, self.view_h as f32 / 2.0);
+        self.view_w = view_w;
+        self.view_h = view_h;
+        self.clamp();
+        self.x = cx - view_w as f32 / 2.0 / self.zoom;
+        self.y = cy - view_h as f32 / 2.0 / self.zoom;
+        self.clamp();
+    }
+
+    /// The smallest zoom at which the world still fills the screen.
+    pub fn min_zoom(&self) -> f32 {
+        (self.view_w as f32 / self.world_w as f32).max(self.view_h as f32 / self.world_h as f32)
+    }
+
+    /// Keep the view inside the world and the zoom within range.
+    fn clamp(&mut self) {
+        self.zoom = self.zoom.clamp(self.min_zoom(), MAX_ZOOM.max(self.min_zoom()));
+        let max_x = (self.world_w as f32 - self.view_w as f32 / self.zoom).max(0.0);
+        let max_y = (self.world_h as f32 - self.view_h as f32 / self.zoom).max(0.0);
+        self.x = self.x.clamp(0.0, max_x);
+        self.y = self.y.clamp(0.0, max_y);
+    }
+
+    /// Convert a screen position in pixels to a world position in cells.
+    pub fn screen_to_world(&self, sx: f32, sy: f32) -> (f32, f32) {
+        (self.x + sx / self.zoom, self.y + sy / self.zoom)
+    }
+
+    /// Convert a world position in cells to a screen position in pixels.
+    pub fn world_to_screen(&self, wx: f32, wy: f32) -> (f32, f32) {
+        ((wx - self.x) * self.zoom, (wy - self.y) * self.zoom)
+    }
+
+    /// The cell under screen position `(sx, sy)`, which may lie between
+    /// pixels when the frame is scaled to fit the window.
+    pub fn cell_under(&self, sx: f32, sy: f32) -> (usize, usize) {
+        let (wx, wy) = self.screen_to_world(sx, sy);
+        (
+            (wx.max(0.0) as usize).min(self.world_w - 1),
+            (wy.max(0.0) as usize).min(self.world_h - 1),
+        )
+    }
+
+    /// The cell shown at screen pixel `(sx, sy)`.
+    #[inline]
+    pub fn cell_at(&self, sx: usize, sy: usize) -> (usize, usize) {
+        let (wx, wy) = self.screen_to_world(sx as f32 + 0.5, sy as f32 + 0.5);
+        (
+            (wx as usize).min(self.world_w - 1),
+            (wy as usize).min(self.world_h - 1),
+        )
+    }
+
+    /// Multiply the zoom by `factor`, keeping the world point under screen
+    /// position `(sx, sy)` where it is.
+    pub fn zoom_at(&mut self, sx: f32, sy: f32, factor: f32) {
+        let (wx, wy) = self.screen_to_world(sx, sy);
+        self.zoom *= factor;
+        self.clamp();
+        self.x = wx - sx / self.zoom;
+        self.y = wy - sy / self.zoom;
+        self.clamp();
+    }
+
+    /// Move the view so world position `(wx, wy)` is in the middle of the
+    /// screen, or as near as it can be.
+    pub fn look_at(&mut self, wx: f32, wy: f32) {
+        self.x = wx - self.view_w as f32 / 2.0 / self.zoom;
+        self.y = wy - self.view_h as f32 / 2.0 / self.zoom;
+        self.clamp();
+    }
+
+    /// Move the view by `(dx, dy)` screen pixels.
+    pub fn pan(&mut self, dx: f32, dy: f32) {
+        self.x += dx / self.zoom;
+        self.y += dy / self.zoom;
+        self.clamp();
+    }
+
+    /// The screen rectangle `(x, y, width, height)` covering the world cells
+    /// `(x, y, width, height)`, clipped to the screen. `None` if it is off
+    /// screen.
+    pub fn world_rect_to_screen(
+        &self,
+        rect: (usize, usize, usize, usize),
+    ) -> Option<(usize, usize, usize, usize)> {
+        let (x, y, w, h) = rect;
+        let (sx0, sy0) = self.world_to_screen(x as f32, y as f32);
+        let (sx1, sy1) = self.world_to_screen((x + w) as f32, (y + h) as f32);
+        let x0 = sx0.floor().max(0.0) as usize;
+        let y0 = sy0.floor().max(0.0) as usize;
+        let x1 = (sx1.ceil().max(0.0) as usize).min(self.view_w);
+        let y1 = (sy1.ceil().max(0.0) as usize).min(self.view_h);
+        (x0 < x1 && y0 < y1).then(|| (x0, y0, x1 - x0, y1 - y0))
+    }
+}
+
+/// Where window position `(wx, wy)` falls on a `frame` sized frame shown in
+/// a `window` sized window, scaled to fit while keeping its shape and
+/// centred between bars. `None` over a bar.
+pub fn window_to_frame(
+    window: (usize, usize),
+    frame: (usize, usize),
+    wx: f32,
+    wy: f32,
+) -> Option<(f32, f32)> {
+    let (ww, wh) = (window.0 as f32, window.1 as f32);
+    let (fw, fh) = (frame.0 as f32, frame.1 as f32);
+    let scale = (ww / fw).min(wh / fh);
+    let (left, top) = ((ww - fw * scale) / 2.0, (wh - fh * scale) / 2.0);
+    let (fx, fy) = ((wx - left) / scale, (wy - top) / scale);
+    (fx >= 0.0 && fy >= 0.0 && fx < fw && fy < fh).then_some((fx, fy))
+}
@@ -0,0 +1,158 @@
+// Splits the world into fixed-size chunks with their own activity flags so
+// the physics and the renderer can skip regions where nothing is happening.
+
+/// Width and height of a chunk in cells.
+pub const CHUNK_SIZE: usize = 64;
+
+/// A rectangle of cells as `(x, y, width, height)`.
+pub type Rect = (usize, usize, usize, usize);
+
+/// Inclusive `(min_x, min_y, max_x, max_y)` bounds of the cells of a chunk
+/// that changed since the last redraw.
+type Bounds = (usize, usize, usize, usize);
+
+/// Activity flags for every chunk of a `width` x `height` world.
+///
+/// A chunk woken during a tick is simulated on the next tick. The cells
+/// that changed are tracked per chunk as a bounding box, so the frontend
+/// only repaints those pixels.
+pub struct Chunks {
+    width: usize,
+    height: usize,
+    cols: usize,
+    rows: usize,
+    active: Vec<bool>,
+    next: Vec<bool>,
+    redraw: Vec<Option<Bounds>>,
+}
+
+impl Chunks {
+    /// Create the chunk grid. Every chunk starts flagged for redraw so the
+    /// first frame is drawn in full.
+    pub fn new(width: usize, height: usize) -> Self {
+        let cols = width.div_ceil(CHUNK_SIZE);
+        let rows = height.div_ceil(CHUNK_SIZE);
+        let mut chunks = Self {
+            width,
+            height,
+            cols,
+            rows,
+            active: vec![false; cols * rows],
+            next: vec![false; cols * rows],
+            redraw: vec![None; cols * rows],
+        };
+        chunks.redraw_all();
+        chunks
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// True if the chunk holding cell `(x, y)` is simulated this tick.
+    pub fn is_active(&self, x: usize, y: usize) -> bool {
+        self.active[(y / CHUNK_SIZE) * self.cols + x / CHUNK_SIZE]
+    }
+
+    /// Record a change at cell `(x, y)`. Wakes its chunk, and the chunks next
+    /// to it when the cell is on a chunk border, since the cells around it
+    /// may now be free to move, and marks the cell for redraw.
+    pub fn wake(&mut self, x: usize, y: usize) {
+        let cx0 = x.saturating_sub(1) / CHUNK_SIZE;
+        let cx1 = ((x + 1).min(self.width - 1)) / CHUNK_SIZE;
+        let cy0 = y.saturating_sub(1) / CHUNK_SIZE;
+        let cy1 = ((y + 1).min(self.height - 1)) / CHUNK_SIZE;
+        for cy in cy0..=cy1 {
+            for cx in cx0..=cx1 {
+                self.next[cy * self.cols + cx] = true;
+            }
+        }
+
+        let bounds = &mut self.redraw[(y / CHUNK_SIZE) * self.cols + x / CHUNK_SIZE];
+        *bounds = Some(match *bounds {
+            Some((x0, y0, x1, y1)) => (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+            None => (x, y, x, y),
+        });
+    }
+
+    /// Record a change to every cell from `(x0, y0)` to `(x1, y1)`
+    /// inclusive, like calling `wake` on each but touching every chunk only
+    /// once.
+    pub fn wake_rect(&mut self, x0: usize, y0: usize, x1: usize, y1: usize) {
+        let cx0 = x0.saturating_sub(1) / CHUNK_SIZE;
+        let cx1 = ((x1 + 1).min(self.width - 1)) / CHUNK_SIZE;
+        let cy0 = y0.saturating_sub(1) / CHUNK_SIZE;
+        let cy1 = ((y1 + 1).min(self.height - 1)) / CHUNK_SIZE;
+        for cy in cy0..=cy1 {
+            for cx in cx0..=cx1 {
+                self.next[cy * self.cols + cx] = true;
+            }
+        }
+
+        for cy in y0 / CHUNK_SIZE..=y1 / CHUNK_SIZE {
+            for cx in x0 / CHUNK_SIZE..=x1 / CHUNK_SIZE {
+                let (rx, ry, rw, rh) = self.rect(cx, cy);
+                let (bx0, by0) = (x0.max(rx), y0.max(ry));
+                let (bx1, by1) = (x1.min(rx + rw - 1), y1.min(ry + rh - 1));
+                let bounds = &mut self.redraw[cy * self.cols + cx];
+                *bounds = Some(match *bounds {
+                    Some((ox0, oy0, ox1, oy1)) => {
+                        (ox0.min(bx0), oy0.min(by0), ox1.max(bx1), oy1.max(by1))
+                    }
+                    None => (bx0, by0, bx1, by1),
+                });
+            }
+        }
+    }
+
+    /// Start a new tick: chunks woken during the last one become active.
+    pub fn advance(&mut self) {
+        std::mem::swap(&mut self.active, &mut self.next);
+        self.next.fill(false);
+    }
+
+    /// Wake every chunk, e.g. after the whole grid was replaced.
+    pub fn wake_all(&mut self) {
+        self.next.fill(true);
+        self.redraw_all();
+    }
+
+    /// Flag every chunk for redraw, e.g. after the frame buffer was cleared.
+    pub fn redraw_all(&mut self) {
+        for i in 0..self.redraw.len() {
+            let (x, y, w, h) = self.rect(i % self.cols, i / self.cols);
+            self.redraw[i] = Some((x, y, x + w - 1, y + h - 1));
+        }
+    }
+
+    /// Forget the redraw flags once the frontend has drawn them.
+    pub fn clear_redraw(&mut self) {
+        self.redraw.fill(None);
+    }
+
+    /// The cell rectangle covered by chunk `(cx, cy)`, clipped to the world.
+    pub fn rect(&self, cx: usize, cy: usize) -> Rect {
+        let x = cx * CHUNK_SIZE;
+        let y = cy * CHUNK_SIZE;
+        (x, y, CHUNK_SIZE.min(self.width - x), CHUNK_SIZE.min(self.height - y))
+    }
+
+    /// Rectangles of the chunks simulated this tick.
+    pub fn active_rects(&self) -> impl Iterator<Item = Rect> + '_ {
+        self.active
+            .iter()
+            .enumerate()
+            .filter(|&(_, &flag)| flag)
+            .map(|(i, _)| self.rect(i % self.cols, i / self.cols))
+    }
+
+    /// For each chunk with changes since the last redraw, the smallest
+    /// rectangle holding every changed cell.
+    pub fn redraw_rects(&self) -> impl Iterator<Item = Rect> + '_ {
+        self.redraw.iter().flatten().map(|&(x0, y0, x1, y1)| (x0, y0, x1 - x0 + 1, y1 - y0 + 1))
+    }
+}
@@ -0,0 +1,226 @@
+// Hand-rolled command line parsing for the frontend.
+
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sandfall::puzzle;
+use sandfall::scenes::{self, Scene};
+use sandfall::weather::Precipitation;
+use sandfall::Wrap;
+
+pub const USAGE: &str = "usage: sandfall [--mode MODE] [--tui] [--seed N] [--scene NAME]
+                [--puzzle NAME] [--world WxH] [--load-image PATH] [--walls PATH]
+                [--stamp PATH] [--wrap | --wrap-all] [--weather rain|snow]
+                [--day-night] [--auto-flip] [--script PATH] [--serve PORT] [--demo]
+                [--pipe-frames] [--verify]
+
+options:
+  --mode MODE     sand (the default), or btw for an abelian sandpile
+  --tui           draw in the terminal instead of a window, driven from the keyboard
+  --seed N        seed the random number generator (0..=900000000)
+  --scene NAME    start from a built-in scene: hourglass, funnel or pachinko
+  --puzzle NAME   play a built-in puzzle, ramp or split: draw walls to steer
+                  enough sand into the goal drains; F5 starts it again
+  --world WxH     make the world W by H cells, e.g. 4000x3000, instead of the
+                  window's size; pan and zoom to see the rest of it
+  --load-image PATH
+                  start from a PNG: black walls, yellow sand, blue water, red fire
+  --walls PATH    draw walls from a text file of lines and filled shapes, see
+                  the README
+  --stamp PATH    load a small PNG, coloured like --load-image, for the stamp tool
+  --wrap          join the left and right edges, so grains leaving one side
+                  come back on the other
+  --wrap-all      join the top and bottom edges as well
+  --weather KIND  rain or snow falling from the top, at the rate and wind set
+                  in sandfall.toml
+  --day-night     warm and cool the air over a day, so water freezes at night
+                  and thaws by day; the day's length is set in sandfall.toml
+  --auto-flip     turn gravity over whenever the grains have all fallen into
+                  the lower half, for an endless --scene hourglass
+  --script PATH   run the console commands in a text file, one a line, before
+                  the first tick
+  --serve PORT    take JSON requests, one a line, on TCP port PORT of this
+                  machine; needs the server feature, see the README
+  --demo          after a while with no input, pour, drain and change
+                  material by itself until a key or the mouse is touched
+  --pipe-frames   write every frame to stdout as raw RGBA at 30 frames a second,
+                  for a video encoder such as ffmpeg to read
+  --verify        check after every tick that the grid and the grains agree, and
+                  stop with a list of what differs if not (slow)";
+
+/// Largest seed the Marsaglia generator accepts.
+const MAX_SEED: i32 = 900_000_000;
+
+/// Which simulation to run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// The falling sand world.
+    Sand,
+    /// The Bak-Tang-Wiesenfeld abelian sandpile.
+    Btw,
+}
+
+/// Options given on the command line.
+pub struct Args {
+    pub mode: Mode,
+    /// Draw in the terminal instead of a window.
+    pub tui: bool,
+    /// Seed for the world's random number generator, if one was given.
+    pub seed: Option<i32>,
+    /// Built-in scene to start from, if one was given.
+    pub scene: Option<&'static Scene>,
+    /// Built-in puzzle to play, if one was given.
+    pub puzzle: Option<&'static Scene>,
+    /// World size in cells, if it is not to be the window's.
+    pub world: Option<(usize, usize)>,
+    /// PNG to build the starting world from, if one was given.
+    pub image: Option<PathBuf>,
+    /// Text file of walls to draw, if one was given.
+    pub walls: Option<PathBuf>,
+    /// PNG to load as a stamp, if one was given.
+    pub stamp: Option<PathBuf>,
+    /// Which edges of the world join up.
+    pub wrap: Wrap,
+    /// Rain or snow to start with, if any.
+    pub weather: Option<Precipitation>,
+    /// Warm and cool the air over a day.
+    pub day_night: bool,
+    /// Turn gravity over once the upper half has emptied.
+    pub auto_flip: bool,
+    /// Text file of console commands to run at startup, if one was given.
+    pub script: Option<PathBuf>,
+    /// Local port to take remote requests on, if one was given.
+    pub serve: Option<u16>,
+    /// Run the world by itself while nobody is using it.
+    pub demo: bool,
+    /// Write frames to stdout for an external encoder.
+    pub pipe_frames: bool,
+    /// Check the world is consistent after every tick.
+    pub verify: bool,
+}
+
+impl Args {
+    /// Parse the program arguments, skipping the program name.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut parsed = Args {
+            mode: Mode::Sand,
+            tui: false,
+            seed: None,
+            scene: None,
+            puzzle: None,
+            world: None,
+            image: None,
+            walls: None,
+            stamp: None,
+            wrap: Wrap::default(),
+            weather: None,
+            day_night: false,
+            auto_flip: false,
+            script: None,
+            serve: None,
+            demo: false,
+            pipe_frames: false,
+            verify: false,
+        };
+        let mut args = args.into_iter().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--mode" => {
+                    let value = args.next().ok_or("--mode needs a value")?;
+                    parsed.mode = match value.as_str() {
+                        "sand" => Mode::Sand,
+                        "btw" => Mode::Btw,
+                        _ => return Err(format!("unknown mode '{value}', expected sand or btw")),
+                    };
+                }
+                "--tui" => parsed.tui = true,
+                "--seed" => {
+                    let value = args.next().ok_or("--seed needs a value")?;
+                    let seed = value
+                        .parse::<i32>()
+                        .ok()
+                        .filter(|s| (0..=MAX_SEED).contains(s))
+                        .ok_or_else(|| format!("invalid seed '{value}', expected 0..={MAX_SEED}"))?;
+                    parsed.seed = Some(seed);
+                }
+                "--scene" => {
+                    let name = args.next().ok_or("--scene needs a value")?;
+                    let scene =
+                        scenes::find(&name).ok_or_else(|| format!("unknown scene '{name}'"))?;
+                    parsed.scene = Some(scene);
+                }
+                "--puzzle" => {
+                    let name = args.next().ok_or("--puzzle needs a value")?;
+                    let puzzle =
+                        puzzle::find(&name).ok_or_else(|| format!("unknown puzzle '{name}'"))?;
+                    parsed.puzzle = Some(puzzle);
+                }
+                "--world" => {
+                    let value = args.next().ok_or("--world needs a size")?;
+                    let size = value
+                        .split_once('x')
+                        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                        .filter(|&(w, h)| w > 0 && h > 0)
+                        .ok_or_else(|| format!("invalid world size '{value}', expected WxH"))?;
+                    parsed.world = Some(size);
+                }
+                "--load-image" => {
+                    let path = args.next().ok_or("--load-image needs a path")?;
+                    parsed.image = Some(PathBuf::from(path));
+                }
+                "--walls" => {
+                    let path = args.next().ok_or("--walls needs a path")?;
+                    parsed.walls = Some(PathBuf::from(path));
+                }
+                "--stamp" => {
+                    let path = args.next().ok_or("--stamp needs a path")?;
+                    parsed.stamp = Some(PathBuf::from(path));
+                }
+                "--wrap" => parsed.wrap.horizontal = true,
+                "--wrap-all" => parsed.wrap = Wrap { horizontal: true, vertical: true },
+                "--weather" => {
+                    let name = args.next().ok_or("--weather needs a value")?;
+                    let kind = Precipitation::from_name(&name)
+                        .ok_or_else(|| format!("unknown weather '{name}', expected rain or snow"))?;
+                    parsed.weather = Some(kind);
+                }
+                "--day-night" => parsed.day_night = true,
+                "--auto-flip" => parsed.auto_flip = true,
+                "--script" => {
+                    let path = args.next().ok_or("--script needs a path")?;
+                    parsed.script = Some(PathBuf::from(path));
+                }
+                "--serve" => {
+                    let value = args.next().ok_or("--serve needs a port")?;
+                    let port = value
+                        .parse::<u16>()
+                        .map_err(|_| format!("invalid port '{value}', expected 0..=65535"))?;
+                    parsed.serve = Some(port);
+                }
+                "--demo" => parsed.demo = true,
+                "--pipe-frames" => parsed.pipe_frames = true,
+                "--verify" => parsed.verify = true,
+                "-h" | "--help" => return Err(String::new()),
+                other => return Err(format!("unknown argument '{other}'")),
+            }
+        }
+        // A puzzle lays out its own world and is played by hand.
+        if parsed.puzzle.is_some() && (parsed.scene.is_some() || parsed.demo) {
+            return Err("--puzzle cannot be used with --scene or --demo".to_string());
+        }
+        // Both want stdout to themselves.
+        if parsed.pipe_frames && parsed.tui {
+            return Err("--pipe-frames cannot be used with --tui".to_string());
+        }
+        Ok(parsed)
+    }
+}
+
+/// Pick a seed from the clock for runs without `--seed`.
+pub fn random_seed() -> i32 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    (nanos % (MAX_SEED as u128 + 1)) as i32
+}
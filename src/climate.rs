@@ -0,0 +1,108 @@
+// The day and night cycle: the temperature of the open air rises and falls
+// over a day, and water, ice and snow in contact with the air take it on
+// little by little. Water left out at night freezes from its surface down
+// into ice, and by day ice and snow melt again. Only cells touching air or
+// ice are affected, so the middle of a deep pool stays liquid. The world is
+// looked over every few ticks rather than every one, as freezing is slow.
+
+use crate::material::Material;
+use crate::reactions::transmute;
+use crate::world::World;
+
+/// Ticks between looks over the world.
+const EVERY: u64 = 16;
+/// Fraction of the gap to the air's temperature a cell touching it closes
+/// on each look.
+const EXPOSURE: f32 = 0.1;
+/// Temperature water freezes and ice and snow melt at.
+const FREEZE: f32 = 0.0;
+/// Chance on each look that a cell past freezing or melting changes, so a
+/// surface freezes over gradually.
+const CHANGE_CHANCE: f32 = 0.2;
+
+/// Air temperature swinging between `low` at midnight and `high` at noon
+/// over `day_ticks`.
+#[derive(Clone, Copy, Debug)]
+pub struct DayNight {
+    pub day_ticks: u64,
+    pub low: f32,
+    pub high: f32,
+}
+
+impl DayNight {
+    /// The air temperature `ticks` into the run, which starts at dawn.
+    pub fn ambient(&self, ticks: u64) -> f32 {
+        let phase = (ticks % self.day_ticks.max(1)) as f32 / self.day_ticks.max(1) as f32;
+        let mid = (self.low + self.high) / 2.0;
+        let swing = (self.high - self.low) / 2.0;
+        mid + swing * (phase * std::f32::consts::TAU).sin()
+    }
+}
+
+/// Bring the water, ice and snow touching the air towards its temperature,
+/// freezing and melting them, if the world has a day and night.
+pub(crate) fn run(world: &mut World) {
+    let Some(day_night) = world.day_night else {
+        return;
+    };
+    if !world.ticks().is_multiple_of(EVERY) {
+        return;
+    }
+    let ambient = day_night.ambient(world.ticks());
+    let (w, h) = (world.width, world.height);
+
+    let mut changes = Vec::new();
+    for y in 0..h {
+        for x in 0..w {
+            let m = world.grid.get(x, y);
+            if !matches!(m, Material::Water | Material::Ice | Material::Snow) {
+                continue;
+            }
+            // Water freezes onto ice as well as from the air, taking on the
+            // temperature of the coldest ice beside it.
+            let mut air = false;
+            let mut coldest_ice = None::<f32>;
+            for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                let (nx, ny) = (x as isize + dx, y as isize + dy);
+                if !world.grid.in_bounds(nx, ny) {
+                    continue;
+                }
+                let (nx, ny) = (nx as usize, ny as usize);
+                match world.grid.get(nx, ny) {
+                    Material::Empty => air = true,
+                    Material::Ice => {
+                        let t = world.heat.get(nx, ny);
+                        coldest_ice = Some(coldest_ice.map_or(t, |c| c.min(t)));
+                    }
+                    _ => {}
+                }
+            }
+            let target = match (air, coldest_ice) {
+                (true, _) => ambient,
+                (false, Some(t)) if m == Material::Water => t,
+                _ => continue,
+            };
+            let t = world.heat.get(x, y);
+            let t = t + EXPOSURE * (target - t);
+            world.heat.set(x, y, t);
+            let next = match m {
+                Material::Water if t < FREEZE => Material::Ice,
+                Material::Ice | Material::Snow if t > FREEZE => Material::Water,
+                _ => continue,
+            };
+            if world.rng.uni() < CHANGE_CHANCE {
+                changes.push((x, y, next));
+            }
+        }
+    }
+
+    let mut removed = false;
+    for (x, y, next) in changes {
+        removed |= transmute(world, x, y, next);
+        world.chunks.wake(x, y);
+    }
+    if removed {
+        world.grains.retain(|g| g.material != Material::Empty);
+        world.reindex();
+    }
+}
@@ -0,0 +1,281 @@
+// Text commands that change a world while it runs, such as `set gravity
+// 0.3` or `spawn water 100 200 50`. Each command is an entry in one table,
+// so the in-app console, scripts read from a file and anything else that
+// can produce lines of text all drive the world the same way.
+
+use crate::input::InputState;
+use crate::material::Material;
+use crate::weather::{Precipitation, Weather};
+use crate::world::{Gravity, World, Wrap};
+
+/// Largest seed the Marsaglia generator accepts.
+const MAX_SEED: i32 = 900_000_000;
+/// Most ticks one `step` command runs, so a typo cannot hang the caller.
+const MAX_STEPS: usize = 10_000;
+/// Drops per tick for weather started with `set weather`.
+const WEATHER_RATE: f32 = 2.0;
+
+/// What a command does to the world, given the words after its name. It
+/// returns a line to show on success, or what was wrong.
+type Run = fn(&mut World, &[&str]) -> Result<String, String>;
+
+/// A named command.
+pub struct Command {
+    pub name: &'static str,
+    /// The words the command takes, for `help` and error messages.
+    pub usage: &'static str,
+    run: Run,
+}
+
+/// Every command, in the order `help` lists them.
+pub const COMMANDS: [Command; 12] = [
+    Command { name: "help", usage: "help [COMMAND]", run: help },
+    Command { name: "set", usage: "set gravity|wrap|weather|wind VALUE", run: set },
+    Command { name: "spawn", usage: "spawn MATERIAL X Y RADIUS", run: spawn },
+    Command { name: "erase", usage: "erase X Y RADIUS", run: erase },
+    Command { name: "clear", usage: "clear", run: clear },
+    Command { name: "seed", usage: "seed N", run: seed },
+    Command { name: "drain", usage: "drain", run: drain },
+    Command { name: "flip", usage: "flip", run: flip },
+    Command { name: "shake", usage: "shake SPEED", run: shake },
+    Command { name: "step", usage: "step [N]", run: step },
+    Command { name: "stats", usage: "stats", run: stats },
+    Command { name: "materials", usage: "materials", run: materials },
+];
+
+/// The command called `name`, ignoring case.
+pub fn find(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|c| c.name.eq_ignore_ascii_case(name))
+}
+
+/// Run one line of text against `world`. Blank lines and lines starting
+/// with `#` do nothing.
+pub fn run(world: &mut World, line: &str) -> Result<String, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let Some((&name, args)) = words.split_first() else {
+        return Ok(String::new());
+    };
+    if name.starts_with('#') {
+        return Ok(String::new());
+    }
+    let command = find(name).ok_or_else(|| format!("unknown command '{name}', try help"))?;
+    (command.run)(world, args)
+}
+
+/// Run every line of `script` in turn, stopping at the first that fails
+/// with its line number.
+pub fn run_script(world: &mut World, script: &str) -> Result<(), String> {
+    for (i, line) in script.lines().enumerate() {
+        run(world, line).map_err(|e| format!("line {}: {e}", i + 1))?;
+    }
+    Ok(())
+}
+
+/// Parse `word` as a number, naming `what` it is if it is not one.
+fn number<T: std::str::FromStr>(word: &str, what: &str) -> Result<T, String> {
+    word.parse().map_err(|_| format!("invalid {what} '{word}'"))
+}
+
+/// Parse `word` as a finite number, as `nan` and `inf` parse too but would
+/// poison every speed they touch.
+fn finite(word: &str, what: &str) -> Result<f32, String> {
+    let value: f32 = number(word, what)?;
+    if value.is_finite() { Ok(value) } else { Err(format!("invalid {what} '{word}'")) }
+}
+
+/// Parse `word` as a cell coordinate, which may be off the world, but not
+/// so far that stepping round a brush there overflows.
+fn coordinate(word: &str, what: &str) -> Result<isize, String> {
+    number::<i32>(word, what).map(|v| v as isize)
+}
+
+/// Parse `word` as the brush radius for the command called `name`, which
+/// may be no bigger than `world` can use.
+fn brush_radius(world: &World, word: &str, name: &str) -> Result<usize, String> {
+    let radius: usize = number(word, "radius")?;
+    if radius > world.max_radius() {
+        return Err(format!("radius is at most {}, {}", world.max_radius(), usage(name)));
+    }
+    Ok(radius)
+}
+
+/// How the command called `name` is used, as an error.
+fn usage(name: &str) -> String {
+    find(name).map_or_else(String::new, |c| format!("usage: {}", c.usage))
+}
+
+fn help(_: &mut World, args: &[&str]) -> Result<String, String> {
+    match args {
+        [] => Ok(COMMANDS.iter().map(|c| c.name).collect::<Vec<_>>().join(" ")),
+        [name] => find(name)
+            .map(|c| c.usage.to_string())
+            .ok_or_else(|| format!("unknown command '{name}'")),
+        _ => Err(usage("help")),
+    }
+}
+
+fn set(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let &[what, value] = args else {
+        return Err(usage("set"));
+    };
+    match what.to_ascii_lowercase().as_str() {
+        "gravity" => {
+            let gravity = match value.to_ascii_lowercase().as_str() {
+                "down" => Gravity::Down,
+                "left" => Gravity::Left,
+                "up" => Gravity::Up,
+                "right" => Gravity::Right,
+                _ => {
+                    world.set_gravity_strength(finite(value, "gravity")?);
+                    return Ok(format!("gravity {}", world.gravity_strength()));
+                }
+            };
+            world.set_gravity(gravity);
+            Ok(format!("gravity {value}"))
+        }
+        "wrap" => {
+            let wrap = match value.to_ascii_lowercase().as_str() {
+                "none" => Wrap::default(),
+                "sides" => Wrap { horizontal: true, vertical: false },
+                "all" => Wrap { horizontal: true, vertical: true },
+                _ => return Err(format!("invalid wrap '{value}', expected none, sides or all")),
+            };
+            world.set_wrap(wrap);
+            Ok(format!("wrap {value}"))
+        }
+        "weather" => {
+            let weather = if value.eq_ignore_ascii_case("none") {
+                None
+            } else {
+                let precipitation = Precipitation::from_name(value).ok_or_else(|| {
+                    format!("invalid weather '{value}', expected rain, snow or none")
+                })?;
+                let wind = world.weather().map_or(0.0, |w| w.wind);
+                Some(Weather { precipitation, rate: WEATHER_RATE, wind })
+            };
+            world.set_weather(weather);
+            Ok(format!("weather {value}"))
+        }
+        "wind" => {
+            let wind = finite(value, "wind")?;
+            let Some(weather) = world.weather() else {
+                return Err("no weather to blow".to_string());
+            };
+            world.set_weather(Some(Weather { wind, ..weather }));
+            Ok(format!("wind {wind}"))
+        }
+        _ => Err(format!("cannot set '{what}', expected gravity, wrap, weather or wind")),
+    }
+}
+
+fn spawn(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let &[name, x, y, radius] = args else {
+        return Err(usage("spawn"));
+    };
+    let material = Material::from_name(name)
+        .filter(|&m| m != Material::Empty)
+        .ok_or_else(|| format!("unknown material '{name}'"))?;
+    let (x, y) = (coordinate(x, "x")?, coordinate(y, "y")?);
+    let radius = brush_radius(world, radius, "spawn")?;
+    if material.is_movable() {
+        let before = world.grains().len();
+        world.spawn(x, y, radius, InputState::box_tries(radius), material);
+        Ok(format!("spawned {} grains", world.grains().len() - before))
+    } else {
+        world.paint(x, y, radius, material);
+        Ok(format!("painted {}", material.props().name))
+    }
+}
+
+fn erase(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let &[x, y, radius] = args else {
+        return Err(usage("erase"));
+    };
+    let radius = brush_radius(world, radius, "erase")?;
+    world.erase(coordinate(x, "x")?, coordinate(y, "y")?, radius);
+    Ok("erased".to_string())
+}
+
+fn clear(world: &mut World, args: &[&str]) -> Result<String, String> {
+    if !args.is_empty() {
+        return Err(usage("clear"));
+    }
+    world.clear();
+    Ok("cleared".to_string())
+}
+
+fn seed(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let &[value] = args else {
+        return Err(usage("seed"));
+    };
+    let seed = value
+        .parse::<i32>()
+        .ok()
+        .filter(|s| (0..=MAX_SEED).contains(s))
+        .ok_or_else(|| format!("invalid seed '{value}', expected 0..={MAX_SEED}"))?;
+    world.reseed(seed);
+    Ok(format!("seed {seed}"))
+}
+
+fn drain(world: &mut World, args: &[&str]) -> Result<String, String> {
+    if !args.is_empty() {
+        return Err(usage("drain"));
+    }
+    let before = world.drained_total();
+    world.drain();
+    Ok(format!("drained {} grains", world.drained_total() - before))
+}
+
+fn flip(world: &mut World, args: &[&str]) -> Result<String, String> {
+    if !args.is_empty() {
+        return Err(usage("flip"));
+    }
+    world.set_gravity(world.gravity().opposite());
+    Ok(format!("gravity {:?}", world.gravity()).to_lowercase())
+}
+
+fn shake(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let &[speed] = args else {
+        return Err(usage("shake"));
+    };
+    world.shake(finite(speed, "speed")?);
+    Ok("shaken".to_string())
+}
+
+fn step(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let ticks = match args {
+        [] => 1,
+        [n] => number::<usize>(n, "tick count")?,
+        _ => return Err(usage("step")),
+    };
+    if ticks > MAX_STEPS {
+        return Err(format!("at most {MAX_STEPS} ticks at a time"));
+    }
+    for _ in 0..ticks {
+        world.step(&InputState::NONE);
+    }
+    Ok(format!("tick {}", world.ticks()))
+}
+
+fn stats(world: &mut World, args: &[&str]) -> Result<String, String> {
+    if !args.is_empty() {
+        return Err(usage("stats"));
+    }
+    Ok(format!(
+        "tick {} grains {} gravity {:?} {}",
+        world.ticks(),
+        world.grains().len(),
+        world.gravity(),
+        world.gravity_strength()
+    )
+    .to_lowercase())
+}
+
+fn materials(_: &mut World, args: &[&str]) -> Result<String, String> {
+    if !args.is_empty() {
+        return Err(usage("materials"));
+    }
+    let names: Vec<_> =
+        Material::ALL[1..].iter().filter(|m| m.in_use()).map(|m| m.props().name).collect();
+    Ok(names.join(" "))
+}
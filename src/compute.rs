@@ -0,0 +1,396 @@
+// An experiment in running falling sand as a wgpu compute shader, built with
+// the `compute` feature. It is sand only: every cell is empty, sand or wall,
+// and sand falls down. Each tick the grid is cut into two by two blocks,
+// shifted one cell down and right on odd ticks, and each block is updated
+// on its own: sand falls into an empty cell below it, or else slides into
+// an empty cell diagonally below. Blocks share no cells, so the GPU can
+// update every one at once without two grains landing in the same cell,
+// and the same rule run on the CPU, when there is no GPU to be had, gives
+// the same cells. Has nothing to do with `World` beyond `Sand::from_world`.
+
+use rayon::prelude::*;
+
+use crate::grid::Grid;
+use crate::material::Material;
+use crate::world::World;
+
+pub const EMPTY: u32 = 0;
+pub const SAND: u32 = 1;
+pub const WALL: u32 = 2;
+
+/// The block rule, written once more for the GPU. `hash` and `update` must
+/// do exactly what the Rust ones below do.
+const SHADER: &str = r"
+struct Params {
+    width: u32,
+    height: u32,
+    tick: u32,
+    pad: u32,
+}
+
+@group(0) @binding(0) var<storage, read_write> cells: array<u32>;
+@group(0) @binding(1) var<uniform> params: Params;
+
+const EMPTY: u32 = 0u;
+const SAND: u32 = 1u;
+const WALL: u32 = 2u;
+
+fn hash(x: u32) -> u32 {
+    let h = x * 747796405u + 2891336453u;
+    let w = ((h >> ((h >> 28u) + 4u)) ^ h) * 277803737u;
+    return (w >> 22u) ^ w;
+}
+
+fn load(x: i32, y: i32) -> u32 {
+    if x < 0 || y < 0 || x >= i32(params.width) || y >= i32(params.height) {
+        return WALL;
+    }
+    return cells[u32(y) * params.width + u32(x)];
+}
+
+fn store(x: i32, y: i32, cell: u32) {
+    if x >= 0 && y >= 0 && x < i32(params.width) && y < i32(params.height) {
+        cells[u32(y) * params.width + u32(x)] = cell;
+    }
+}
+
+@compute @workgroup_size(8, 8)
+fn step(@builtin(global_invocation_id) id: vec3<u32>) {
+    let shift = i32(params.tick & 1u);
+    let x = i32(id.x) * 2 - shift;
+    let y = i32(id.y) * 2 - shift;
+    if x >= i32(params.width) || y >= i32(params.height) {
+        return;
+    }
+    var a = load(x, y);
+    var b = load(x + 1, y);
+    var c = load(x, y + 1);
+    var d = load(x + 1, y + 1);
+    let left_first = (hash(id.x ^ hash(id.y ^ hash(params.tick))) & 1u) == 0u;
+
+    if a == SAND && c == EMPTY {
+        a = EMPTY;
+        c = SAND;
+    }
+    if b == SAND && d == EMPTY {
+        b = EMPTY;
+        d = SAND;
+    }
+    for (var turn = 0u; turn < 2u; turn++) {
+        if (turn == 0u) == left_first {
+            if a == SAND && d == EMPTY && b != WALL {
+                a = EMPTY;
+                d = SAND;
+            }
+        } else if b == SAND && c == EMPTY && a != WALL {
+            b = EMPTY;
+            c = SAND;
+        }
+    }
+
+    store(x, y, a);
+    store(x + 1, y, b);
+    store(x, y + 1, c);
+    store(x + 1, y + 1, d);
+}
+";
+
+/// Blocks along a row or column of `len` cells, enough for either shift.
+fn blocks(len: usize) -> usize {
+    len / 2 + 1
+}
+
+/// The PCG hash of `x`, as the shader has it.
+fn hash(x: u32) -> u32 {
+    let h = x.wrapping_mul(747_796_405).wrapping_add(2_891_336_453);
+    let w = ((h >> ((h >> 28) + 4)) ^ h).wrapping_mul(277_803_737);
+    (w >> 22) ^ w
+}
+
+/// The block `[a, b, c, d]`, top left, top right, bottom left and bottom
+/// right, a tick later. Which way sand tries to slide first is the coin
+/// `left_first`.
+fn update([mut a, mut b, mut c, mut d]: [u32; 4], left_first: bool) -> [u32; 4] {
+    if a == SAND && c == EMPTY {
+        (a, c) = (EMPTY, SAND);
+    }
+    if b == SAND && d == EMPTY {
+        (b, d) = (EMPTY, SAND);
+    }
+    // A grain that could not fall slides past its neighbour, unless that is
+    // a wall, so walls drawn on the slant do not leak.
+    for turn in 0..2 {
+        if (turn == 0) == left_first {
+            if a == SAND && d == EMPTY && b != WALL {
+                (a, d) = (EMPTY, SAND);
+            }
+        } else if b == SAND && c == EMPTY && a != WALL {
+            (b, c) = (EMPTY, SAND);
+        }
+    }
+    [a, b, c, d]
+}
+
+/// Update the row of blocks `by` in tick `tick`, whose top and bottom rows
+/// of cells are `top` and `bottom`; `None` is a row off the world.
+fn update_row(mut top: Option<&mut [u32]>, mut bottom: Option<&mut [u32]>, by: u32, tick: u32) {
+    let width = top.as_deref().or(bottom.as_deref()).map_or(0, <[u32]>::len) as isize;
+    let shift = (tick & 1) as isize;
+    let get = |row: &Option<&mut [u32]>, x: isize| match row {
+        Some(row) if (0..width).contains(&x) => row[x as usize],
+        _ => WALL,
+    };
+    let put = |row: &mut Option<&mut [u32]>, x: isize, cell: u32| {
+        if let Some(row) = row
+            && (0..width).contains(&x)
+        {
+            row[x as usize] = cell;
+        }
+    };
+    let seed = hash(by ^ hash(tick));
+    for bx in 0..blocks(width as usize) as u32 {
+        let x = 2 * bx as isize - shift;
+        if x >= width {
+            break;
+        }
+        let block = [get(&top, x), get(&top, x + 1), get(&bottom, x), get(&bottom, x + 1)];
+        let [a, b, c, d] = update(block, hash(bx ^ seed) & 1 == 0);
+        put(&mut top, x, a);
+        put(&mut top, x + 1, b);
+        put(&mut bottom, x, c);
+        put(&mut bottom, x + 1, d);
+    }
+}
+
+/// Run one tick of the block rule over `cells` on the CPU, a row of blocks
+/// to a thread.
+fn step_cpu(cells: &mut Grid<u32>, tick: u32) {
+    let width = cells.width();
+    let shift = (tick & 1) as usize;
+    // On odd ticks the first row of blocks hangs off the top, and holds
+    // only the world's first row.
+    let (first, rest) = cells.as_mut_slice().split_at_mut(shift * width);
+    if shift == 1 {
+        update_row(None, Some(first), 0, tick);
+    }
+    rest.par_chunks_mut(2 * width).enumerate().for_each(|(i, rows)| {
+        let (top, bottom) = rows.split_at_mut(width.min(rows.len()));
+        let bottom = (!bottom.is_empty()).then_some(bottom);
+        update_row(Some(top), bottom, (i + shift) as u32, tick);
+    });
+}
+
+/// The GPU's copy of the cells and what runs the shader over them.
+struct Gpu {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+    cells: wgpu::Buffer,
+    params: wgpu::Buffer,
+    /// Where cells are copied to be read back.
+    readback: wgpu::Buffer,
+}
+
+impl Gpu {
+    /// Set up the shader for a `width` x `height` grid, or `None` if there
+    /// is no GPU that runs compute shaders.
+    fn new(width: usize, height: usize) -> Option<Self> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::from_env_or_default());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        }))
+        .ok()?;
+        let downlevel = adapter.get_downlevel_capabilities();
+        if !downlevel.flags.contains(wgpu::DownlevelFlags::COMPUTE_SHADERS) {
+            return None;
+        }
+        let size = (width * height * size_of::<u32>()) as u64;
+        let limits = adapter.limits();
+        if size > limits.max_storage_buffer_binding_size as u64 {
+            return None;
+        }
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("sand"),
+            required_limits: limits,
+            ..Default::default()
+        }))
+        .ok()?;
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("sand"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("sand"),
+            layout: None,
+            module: &module,
+            entry_point: Some("step"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        let buffer = |label, size, usage| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage,
+                mapped_at_creation: false,
+            })
+        };
+        use wgpu::BufferUsages as U;
+        let cells = buffer("cells", size, U::STORAGE | U::COPY_DST | U::COPY_SRC);
+        let params = buffer("params", 16, U::UNIFORM | U::COPY_DST);
+        let readback = buffer("readback", size, U::MAP_READ | U::COPY_DST);
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("sand"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: cells.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: params.as_entire_binding() },
+            ],
+        });
+        Some(Self { device, queue, pipeline, bind_group, cells, params, readback })
+    }
+
+    fn upload(&self, cells: &Grid<u32>) {
+        self.queue.write_buffer(&self.cells, 0, bytemuck::cast_slice(cells.as_slice()));
+    }
+
+    /// Queue `ticks` ticks from tick `first`, each in its own pass so every
+    /// block sees the one before finished.
+    fn step(&self, width: usize, height: usize, first: u32, ticks: u32) {
+        for tick in first..first + ticks {
+            let params = [width as u32, height as u32, tick, 0];
+            self.queue.write_buffer(&self.params, 0, bytemuck::cast_slice(&params));
+            let mut encoder = self.device.create_command_encoder(&Default::default());
+            {
+                let mut pass = encoder.begin_compute_pass(&Default::default());
+                pass.set_pipeline(&self.pipeline);
+                pass.set_bind_group(0, &self.bind_group, &[]);
+                pass.dispatch_workgroups(
+                    blocks(width).div_ceil(8) as u32,
+                    blocks(height).div_ceil(8) as u32,
+                    1,
+                );
+            }
+            self.queue.submit(Some(encoder.finish()));
+        }
+    }
+
+    /// Wait for the GPU and copy its cells into `cells`.
+    fn download(&self, cells: &mut Grid<u32>) {
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        encoder.copy_buffer_to_buffer(&self.cells, 0, &self.readback, 0, self.cells.size());
+        self.queue.submit(Some(encoder.finish()));
+        let slice = self.readback.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| {
+            result.expect("Failed to read cells back from the GPU");
+        });
+        self.device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .expect("Failed to read cells back from the GPU");
+        cells.as_mut_slice().copy_from_slice(bytemuck::cast_slice(&slice.get_mapped_range()));
+        self.readback.unmap();
+    }
+}
+
+/// A grid of sand and walls, stepped on the GPU if there is one.
+pub struct Sand {
+    /// The cells as of the last `cells`, or as set since.
+    cells: Grid<u32>,
+    gpu: Option<Gpu>,
+    /// The GPU's cells are newer than `cells`.
+    stale: bool,
+    /// `cells` has been changed since the GPU last had them.
+    dirty: bool,
+    tick: u32,
+}
+
+impl Sand {
+    /// An empty `width` x `height` grid, stepped on the GPU if one can be
+    /// had, and on the CPU if not.
+    pub fn new(width: usize, height: usize) -> Self {
+        let gpu = Gpu::new(width, height);
+        Self { cells: Grid::new(width, height, EMPTY), gpu, stale: false, dirty: true, tick: 0 }
+    }
+
+    /// An empty `width` x `height` grid, stepped on the CPU, for comparing
+    /// against the GPU.
+    pub fn cpu(width: usize, height: usize) -> Self {
+        Self {
+            cells: Grid::new(width, height, EMPTY),
+            gpu: None,
+            stale: false,
+            dirty: false,
+            tick: 0,
+        }
+    }
+
+    /// A grid the size of `world`, with sand where it has sand and walls
+    /// where it has anything that does not move.
+    pub fn from_world(world: &World) -> Self {
+        let mut sand = Self::new(world.width(), world.height());
+        for (cell, &m) in sand.cells.as_mut_slice().iter_mut().zip(world.cells().as_slice()) {
+            *cell = match m {
+                Material::Sand => SAND,
+                m if m != Material::Empty && !m.is_movable() => WALL,
+                _ => EMPTY,
+            };
+        }
+        sand
+    }
+
+    /// True if the grid is stepped on the GPU.
+    pub fn is_gpu(&self) -> bool {
+        self.gpu.is_some()
+    }
+
+    pub fn width(&self) -> usize {
+        self.cells.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.cells.height()
+    }
+
+    /// Every cell, `EMPTY`, `SAND` or `WALL`. Reading them back from the
+    /// GPU waits for it to finish every tick asked of it so far.
+    pub fn cells(&mut self) -> &Grid<u32> {
+        if let Some(gpu) = &self.gpu
+            && self.stale
+        {
+            gpu.download(&mut self.cells);
+            self.stale = false;
+        }
+        &self.cells
+    }
+
+    /// Set cell `(x, y)` to `cell`.
+    pub fn set(&mut self, x: usize, y: usize, cell: u32) {
+        self.cells();
+        self.cells.set(x, y, cell);
+        self.dirty = self.gpu.is_some();
+    }
+
+    /// Run `ticks` ticks. On the GPU they are only queued, and run while
+    /// the caller gets on with something else.
+    pub fn step(&mut self, ticks: u32) {
+        match &self.gpu {
+            Some(gpu) => {
+                if self.dirty {
+                    gpu.upload(&self.cells);
+                    self.dirty = false;
+                }
+                gpu.step(self.width(), self.height(), self.tick, ticks);
+                self.stale = true;
+            }
+            None => {
+                for tick in self.tick..self.tick + ticks {
+                    step_cpu(&mut self.cells, tick);
+                }
+            }
+        }
+        self.tick = self.tick.wrapping_add(ticks);
+    }
+}
@@ -0,0 +1,104 @@
+// Settings read from an optional `sandfall.toml` at startup, so the window
+// size, brush and drain can be tuned without recompiling. Any key left out
+// keeps its default.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::Deserialize;
+
+/// Settings for the frontend.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Window size in pixels, and the world's in cells unless `--world` is
+    /// given.
+    pub width: usize,
+    pub height: usize,
+    /// Size of the borderless window `F11` switches to, as `[width, height]`.
+    /// minifb cannot ask for the desktop resolution, so set it to yours.
+    pub fullscreen_size: [usize; 2],
+    /// Window pixels per frame pixel. Above 1 the frame is drawn smaller and
+    /// stretched to fill the window, for bigger cells and less drawing.
+    pub pixel_scale: usize,
+    /// Base colour of sand as `[r, g, b]`.
+    pub sand_color: [u8; 3],
+    /// Starting brush radius in cells.
+    pub spawn_radius: usize,
+    /// Grains the spray brush tries to place per second, whatever the frame
+    /// rate.
+    pub spawn_rate: usize,
+    /// Column the starting drain is centred on; the middle if left out.
+    pub drain_x: Option<usize>,
+    /// Cells either side of the starting drain's centre.
+    pub drain_half_width: usize,
+    /// Seconds without input before `--demo` takes over.
+    pub demo_idle_secs: u64,
+    /// Drops per tick of `--weather`.
+    pub weather_rate: f32,
+    /// Sideways speed of `--weather`, in cells per tick; negative blows to
+    /// the left.
+    pub wind: f32,
+    /// Seconds from one dawn to the next with `--day-night`.
+    pub day_length_secs: f32,
+    /// Air temperatures at midnight and noon with `--day-night`.
+    pub night_temperature: f32,
+    pub day_temperature: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            width: 1200,
+            height: 800,
+            fullscreen_size: [1920, 1080],
+            pixel_scale: 1,
+            sand_color: [194, 178, 128],
+            spawn_radius: 16,
+            spawn_rate: 1500,
+            drain_x: None,
+            drain_half_width: 50,
+            demo_idle_secs: 30,
+            weather_rate: 2.0,
+            wind: 0.5,
+            day_length_secs: 120.0,
+            night_temperature: -15.0,
+            day_temperature: 25.0,
+        }
+    }
+}
+
+impl Config {
+    /// Read the config at `path`, or the defaults if there is no such file.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(format!("{}: {e}", path.display())),
+        };
+        let config: Self = toml::from_str(&text).map_err(|e| format!("{}: {e}", path.display()))?;
+        if config.width == 0 || config.height == 0 {
+            return Err(format!("{}: width and height must be positive", path.display()));
+        }
+        if !(1..=8).contains(&config.pixel_scale) {
+            return Err(format!("{}: pixel_scale must be 1 to 8", path.display()));
+        }
+        if !config.weather_rate.is_finite() || config.weather_rate < 0.0 {
+            return Err(format!("{}: weather_rate must not be negative", path.display()));
+        }
+        if !config.wind.is_finite() {
+            return Err(format!("{}: wind must be a number", path.display()));
+        }
+        if !config.day_length_secs.is_finite() || config.day_length_secs <= 0.0 {
+            return Err(format!("{}: day_length_secs must be positive", path.display()));
+        }
+        if !config.night_temperature.is_finite() || !config.day_temperature.is_finite() {
+            return Err(format!("{}: temperatures must be numbers", path.display()));
+        }
+        if config.fullscreen_size.contains(&0) {
+            return Err(format!("{}: fullscreen_size must be positive", path.display()));
+        }
+        Ok(config)
+    }
+}
@@ -0,0 +1,140 @@
+// The in-app console, opened with the backquote key: a line typed at the
+// bottom of the window is run through the library's command table when
+// Enter is pressed, and what it said is shown above. While the console is
+// open every key types into it instead of doing what it is bound to.
+
+use sandfall::{commands, World};
+
+use crate::backend::Key;
+
+/// Most lines of past commands and their results kept on screen.
+const SCROLLBACK: usize = 8;
+/// Longest line that can be typed.
+const MAX_INPUT: usize = 80;
+
+/// The console's state: whether it is open, the line being typed, and what
+/// was typed and said before.
+#[derive(Default)]
+pub struct Console {
+    open: bool,
+    input: String,
+    /// Past commands and what they said, oldest first.
+    output: Vec<String>,
+    /// Commands run so far, oldest first, for Up and Down to bring back.
+    history: Vec<String>,
+    /// How far back through `history` Up has gone, while it has.
+    recalled: Option<usize>,
+}
+
+/// Letter keys, from `a` to `z`.
+const LETTERS: [Key; 26] = [
+    Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I,
+    Key::J, Key::K, Key::L, Key::M, Key::N, Key::O, Key::P, Key::Q, Key::R,
+    Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z,
+];
+/// Number keys along the top and on the keypad, from `0` to `9`.
+const DIGITS: [[Key; 2]; 10] = [
+    [Key::Key0, Key::NumPad0], [Key::Key1, Key::NumPad1], [Key::Key2, Key::NumPad2],
+    [Key::Key3, Key::NumPad3], [Key::Key4, Key::NumPad4], [Key::Key5, Key::NumPad5],
+    [Key::Key6, Key::NumPad6], [Key::Key7, Key::NumPad7], [Key::Key8, Key::NumPad8],
+    [Key::Key9, Key::NumPad9],
+];
+
+/// The character `key` types, with Shift held or not.
+fn typed(key: Key, shift: bool) -> Option<char> {
+    if let Some(i) = LETTERS.iter().position(|&k| k == key) {
+        let c = (b'a' + i as u8) as char;
+        return Some(if shift { c.to_ascii_uppercase() } else { c });
+    }
+    if let Some(i) = DIGITS.iter().position(|keys| keys.contains(&key)) {
+        return Some((b'0' + i as u8) as char);
+    }
+    match key {
+        Key::Space => Some(' '),
+        Key::Minus if shift => Some('_'),
+        Key::Minus | Key::NumPadMinus => Some('-'),
+        Key::Period | Key::NumPadDot => Some('.'),
+        Key::Comma => Some(','),
+        Key::Slash => Some('/'),
+        Key::Equal => Some('='),
+        _ => None,
+    }
+}
+
+impl Console {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Open the console, or close it keeping the line typed so far.
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Type the keys pressed this frame. Enter runs the line against
+    /// `world`, Backspace deletes, Escape empties the line, and Up and Down
+    /// step through the commands run before.
+    pub fn type_keys(&mut self, keys: &[Key], shift: bool, world: &mut World) {
+        for &key in keys {
+            match key {
+                Key::Enter | Key::NumPadEnter => self.submit(world),
+                Key::Backspace => {
+                    self.input.pop();
+                }
+                Key::Escape => {
+                    self.input.clear();
+                    self.recalled = None;
+                }
+                Key::Up => self.recall(1),
+                Key::Down => self.recall(-1),
+                _ => {
+                    if let Some(c) = typed(key, shift)
+                        && self.input.len() < MAX_INPUT
+                    {
+                        self.input.push(c);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Bring back the command `step` further back in the history than the
+    /// one shown, or an empty line on stepping past the newest.
+    fn recall(&mut self, step: isize) {
+        let back = self.recalled.map_or(-1, |b| b as isize) + step;
+        if back < 0 {
+            self.recalled = None;
+            self.input.clear();
+        } else if (back as usize) < self.history.len() {
+            self.recalled = Some(back as usize);
+            self.input = self.history[self.history.len() - 1 - back as usize].clone();
+        }
+    }
+
+    /// Run the line typed and show what it said.
+    fn submit(&mut self, world: &mut World) {
+        let line = std::mem::take(&mut self.input);
+        self.recalled = None;
+        if line.trim().is_empty() {
+            return;
+        }
+        self.output.push(format!("] {line}"));
+        match commands::run(world, &line) {
+            Ok(said) if said.is_empty() => {}
+            Ok(said) => self.output.push(said),
+            Err(e) => self.output.push(format!("error: {e}")),
+        }
+        let excess = self.output.len().saturating_sub(SCROLLBACK);
+        self.output.drain(..excess);
+        if self.history.last() != Some(&line) {
+            self.history.push(line);
+        }
+    }
+
+    /// The lines to show: what was said, then the prompt with a cursor.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = self.output.clone();
+        lines.push(format!("] {}_", self.input));
+        lines
+    }
+}
@@ -0,0 +1,64 @@
+// Conveyor belts: static cells that carry whatever rests on them one cell
+// along every tick, for sorting machines and contraptions. "On" and "along"
+// follow gravity, so a belt still carries the grains lying on it when the
+// world is turned over, just the other way. Belts are only looked for in the
+// active chunks; a grain moved along wakes the chunk it lands in, so a belt
+// keeps its chunk awake for as long as it has something to carry.
+
+use crate::material::Material;
+use crate::physics::Cells;
+use crate::world::{World, NO_GRAIN};
+
+/// Carry every movable grain resting on a conveyor one cell along it, into
+/// the cell beside it if that is empty.
+pub(crate) fn run(world: &mut World) {
+    let gravity = world.gravity();
+    let (gx, gy) = gravity.vector();
+    let (ax, ay) = gravity.across();
+    let (w, h, wrap) = (world.width, world.height, world.wrap());
+
+    // Each grain rests on one cell at most, so is found once.
+    let mut moves = Vec::new();
+    for (x0, y0, cw, ch) in world.chunks.active_rects() {
+        for y in y0..y0 + ch {
+            for (x, &m) in world.grid.row(y)[x0..x0 + cw].iter().enumerate() {
+                let sign = match m {
+                    Material::ConveyorLeft => -1,
+                    Material::ConveyorRight => 1,
+                    _ => continue,
+                };
+                let (ox, oy) = wrap.join(w, h, (x0 + x) as isize - gx, y as isize - gy);
+                if !world.grid.in_bounds(ox, oy) {
+                    continue;
+                }
+                let idx = world.index.get(ox as usize, oy as usize);
+                if idx != NO_GRAIN {
+                    moves.push((idx as usize, sign * ax, sign * ay));
+                }
+            }
+        }
+    }
+    if moves.is_empty() {
+        return;
+    }
+
+    // The grain furthest along goes first, so a row of grains on a belt
+    // moves up as one instead of only the front one moving.
+    moves.sort_by_key(|&(idx, dx, dy)| {
+        let g = &world.grains[idx];
+        -(g.x as isize * dx + g.y as isize * dy)
+    });
+    for (idx, dx, dy) in moves {
+        let (x, y) = (world.grains[idx].x, world.grains[idx].y);
+        let (nx, ny) = wrap.join(w, h, x as isize + dx, y as isize + dy);
+        if !world.grid.in_bounds(nx, ny) {
+            continue;
+        }
+        let (nx, ny) = (nx as usize, ny as usize);
+        if world.grid.get(nx, ny) != Material::Empty {
+            continue;
+        }
+        world.move_grain(idx, nx, ny);
+        world.grains[idx].idle = 0;
+    }
+}
@@ -0,0 +1,78 @@
+// Attract mode for `--demo`: once nobody has touched the keyboard or mouse
+// for a while, the world runs itself. A few spouts sweep back and forth
+// along the top pouring grains, the material changes every so often and the
+// drains open now and then to make room, until the next key press or mouse
+// movement hands the world back.
+
+use std::time::{Duration, Instant};
+
+use sandfall::{Drain, Material, World};
+
+/// Materials the spouts take turns pouring.
+const MATERIALS: [Material; 5] =
+    [Material::Sand, Material::Water, Material::Sand, Material::Oil, Material::Mud];
+/// Ticks each material pours for: ten seconds at 120 ticks a second.
+const MATERIAL_TICKS: u64 = 1200;
+/// Ticks between the drains opening, and how long they stay open.
+const DRAIN_EVERY: u64 = 2400;
+const DRAIN_OPEN: u64 = 360;
+/// Each spout's sweep as (ticks per sweep, phase), so they drift in and out
+/// of step with each other.
+const SPOUTS: [(f32, f32); 3] = [(900.0, 0.0), (1300.0, 2.1), (1700.0, 4.2)];
+/// Grains each spout tries to place per tick.
+const SPOUT_TRIES: usize = 3;
+/// Spout radius as a fraction of the world's width.
+const SPOUT_RADIUS: f32 = 0.01;
+
+/// Watches for input and, after long enough without any, runs the world.
+pub struct Demo {
+    after: Duration,
+    last_input: Instant,
+    running: bool,
+    /// Ticks since the demo last started.
+    ticks: u64,
+}
+
+impl Demo {
+    /// A demo that starts after `after` without input.
+    pub fn new(after: Duration) -> Self {
+        Self { after, last_input: Instant::now(), running: false, ticks: 0 }
+    }
+
+    /// Note whether there was any input this frame, once a frame, starting
+    /// or stopping the demo. A world with no drain is given one at the
+    /// bottom when the demo starts, so it never fills up.
+    pub fn update(&mut self, input: bool, world: &mut World) {
+        if input {
+            self.last_input = Instant::now();
+            self.running = false;
+        } else if !self.running && self.last_input.elapsed() >= self.after {
+            self.running = true;
+            self.ticks = 0;
+            if world.drains().is_empty() {
+                let (w, h) = (world.width(), world.height());
+                world.add_drain(Drain { x: w / 2, y: h - 1, half_width: w / 8 });
+            }
+        }
+    }
+
+    /// Pour from the spouts for one tick, before the world is stepped.
+    /// Returns true if the drains should be open this tick.
+    pub fn tick(&mut self, world: &mut World) -> bool {
+        if !self.running {
+            return false;
+        }
+        let t = self.ticks;
+        self.ticks += 1;
+
+        let material = MATERIALS[(t / MATERIAL_TICKS) as usize % MATERIALS.len()];
+        let w = world.width() as f32;
+        let radius = (SPOUT_RADIUS * w).max(1.0) as usize;
+        for (period, phase) in SPOUTS {
+            let angle = t as f32 / period * std::f32::consts::TAU + phase;
+            let x = w * (0.5 + 0.4 * angle.sin());
+            world.spawn(x as isize, radius as isize, radius, SPOUT_TRIES, material);
+        }
+        t % DRAIN_EVERY >= DRAIN_EVERY - DRAIN_OPEN
+    }
+}
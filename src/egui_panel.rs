@@ -0,0 +1,292 @@
+// The F2 settings panel drawn with egui, for the `egui` feature, in place of
+// the built-in widgets in panel.rs and with the same calls. egui lays the
+// widgets out and turns them into triangles; they are rasterized here into
+// the flat frame like everything else drawn on it, so the panel works with
+// every backend and needs no GPU.
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use egui::epaint::{ClippedPrimitive, ImageDelta, ImageData, Primitive, Vertex};
+use egui::{
+    Color32, Context, Event, Id, Modifiers, PointerButton, Pos2, RawInput, Rect as Area, Sense,
+    Stroke, StrokeKind, TextureId, UiBuilder, Vec2,
+};
+use sandfall::chunks::Rect;
+use sandfall::grid::Grid;
+use sandfall::render::Pixel;
+
+/// Width of the panel in pixels.
+pub const WIDTH: usize = 220;
+/// Space around the widgets.
+const MARGIN: f32 = 6.0;
+/// Side of a material swatch.
+const SWATCH: f32 = 20.0;
+
+const HIGHLIGHT: Color32 = Color32::from_rgb(255, 200, 60);
+
+/// The screen area the panel covers in a `view_w` x `view_h` window, which
+/// the world should not take mouse input from while it is shown.
+pub fn area(view_w: usize, view_h: usize) -> Rect {
+    let x = view_w.saturating_sub(WIDTH);
+    (x, 0, view_w - x, view_h)
+}
+
+/// True if screen position `(x, y)` is on the panel.
+pub fn contains(view_w: usize, view_h: usize, x: f32, y: f32) -> bool {
+    let (ax, ay, aw, ah) = area(view_w, view_h);
+    x >= ax as f32 && x < (ax + aw) as f32 && y >= ay as f32 && y < (ay + ah) as f32
+}
+
+/// What egui keeps between frames: its context, with the state of the
+/// widgets and the fonts, and the textures it has handed over.
+pub struct Panel {
+    ctx: Context,
+    textures: HashMap<TextureId, Texture>,
+    start: Instant,
+    /// Where the mouse was last seen, and whether the left button was held.
+    mouse: Option<Pos2>,
+    down: bool,
+}
+
+/// An RGBA image egui draws from, such as its font atlas.
+struct Texture {
+    width: usize,
+    height: usize,
+    /// Premultiplied, row by row from the top.
+    pixels: Vec<Color32>,
+}
+
+impl Panel {
+    pub fn new() -> Self {
+        Self {
+            ctx: Context::default(),
+            textures: HashMap::new(),
+            start: Instant::now(),
+            mouse: None,
+            down: false,
+        }
+    }
+
+    /// Start this frame's panel in `frame`. `mouse` is the mouse position
+    /// in pixels, `down` whether the left button is held and `clicked`
+    /// whether it went down this frame. The panel is only drawn once
+    /// `Ui::end` is called.
+    pub fn begin<'a>(
+        &'a mut self,
+        frame: &'a mut Grid<u32>,
+        mouse: Option<(f32, f32)>,
+        down: bool,
+        clicked: bool,
+    ) -> Ui<'a> {
+        let (width, height) = (frame.width() as f32, frame.height() as f32);
+        let mut input = RawInput {
+            screen_rect: Some(Area::from_min_size(Pos2::ZERO, Vec2::new(width, height))),
+            time: Some(self.start.elapsed().as_secs_f64()),
+            ..RawInput::default()
+        };
+        let pos = mouse.map(|(x, y)| Pos2::new(x, y));
+        input.events.push(pos.map_or(Event::PointerGone, Event::PointerMoved));
+        self.mouse = pos.or(self.mouse);
+        let button = |pressed| Event::PointerButton {
+            pos: self.mouse.unwrap_or_default(),
+            button: PointerButton::Primary,
+            pressed,
+            modifiers: Modifiers::NONE,
+        };
+        if clicked || (down && !self.down) {
+            input.events.push(button(true));
+        } else if !down && self.down {
+            input.events.push(button(false));
+        }
+        self.down = down;
+        self.ctx.begin_pass(input);
+
+        let (ax, ay, aw, ah) = area(frame.width(), frame.height());
+        let panel = Area::from_min_size(
+            Pos2::new(ax as f32, ay as f32),
+            Vec2::new(aw as f32, ah as f32),
+        );
+        let ui = egui::Ui::new(
+            self.ctx.clone(),
+            Id::new("panel"),
+            UiBuilder::new().max_rect(panel.shrink(MARGIN)),
+        );
+        let visuals = ui.visuals().clone();
+        ui.painter().rect_filled(panel, 0.0, visuals.panel_fill);
+        ui.painter().vline(panel.left(), panel.y_range(), visuals.window_stroke);
+        Ui { panel: self, frame, ui }
+    }
+}
+
+/// One frame's worth of panel, laid out by egui and drawn into `frame` by
+/// `end`.
+pub struct Ui<'a> {
+    panel: &'a mut Panel,
+    frame: &'a mut Grid<u32>,
+    ui: egui::Ui,
+}
+
+impl Ui<'_> {
+    /// A line of text.
+    pub fn label(&mut self, text: &str) {
+        self.ui.label(text);
+    }
+
+    /// A slider setting `value` between `min` and `max` inclusive. Returns
+    /// true if it changed.
+    pub fn slider(&mut self, label: &str, value: &mut usize, min: usize, max: usize) -> bool {
+        self.ui.add(egui::Slider::new(value, min..=max).text(label)).changed()
+    }
+
+    /// A row of buttons, one per option, with `selected` highlighted; a
+    /// click selects another. Returns true if the selection changed.
+    pub fn choice(&mut self, options: &[&str], selected: &mut usize) -> bool {
+        let old = *selected;
+        self.ui.horizontal_wrapped(|ui| {
+            for (i, option) in options.iter().enumerate() {
+                ui.selectable_value(selected, i, *option);
+            }
+        });
+        *selected != old
+    }
+
+    /// Swatches of `colors`, wrapped into rows, with `selected` outlined; a
+    /// click picks another. Returns true if the selection changed.
+    pub fn palette(&mut self, colors: &[Pixel], selected: &mut usize) -> bool {
+        let old = *selected;
+        self.ui.horizontal_wrapped(|ui| {
+            for (i, color) in colors.iter().enumerate() {
+                let (rect, response) = ui.allocate_exact_size(Vec2::splat(SWATCH), Sense::click());
+                ui.painter().rect_filled(rect, 2.0, Color32::from_rgb(color.r, color.g, color.b));
+                if *selected == i {
+                    let stroke = Stroke::new(2.0, HIGHLIGHT);
+                    ui.painter().rect_stroke(rect, 2.0, stroke, StrokeKind::Outside);
+                }
+                if response.clicked() {
+                    *selected = i;
+                }
+            }
+        });
+        *selected != old
+    }
+
+    /// Finish the panel and draw it into the frame, returning the area it
+    /// covers, to be repainted once it is hidden.
+    pub fn end(self) -> Rect {
+        let Ui { panel, frame, ui } = self;
+        drop(ui);
+        let output = panel.ctx.end_pass();
+        for (id, delta) in output.textures_delta.set {
+            panel.update_texture(id, delta);
+        }
+        for primitive in panel.ctx.tessellate(output.shapes, output.pixels_per_point) {
+            panel.draw(frame, primitive);
+        }
+        for id in output.textures_delta.free {
+            panel.textures.remove(&id);
+        }
+        area(frame.width(), frame.height())
+    }
+}
+
+impl Panel {
+    /// Make or patch texture `id` as egui asks.
+    fn update_texture(&mut self, id: TextureId, delta: ImageDelta) {
+        let ImageData::Color(image) = delta.image;
+        let [w, h] = image.size;
+        match delta.pos {
+            Some([x0, y0]) => {
+                let Some(texture) = self.textures.get_mut(&id) else {
+                    return;
+                };
+                for y in 0..h.min(texture.height.saturating_sub(y0)) {
+                    let row = (y0 + y) * texture.width + x0;
+                    let n = w.min(texture.width.saturating_sub(x0));
+                    texture.pixels[row..row + n].copy_from_slice(&image.pixels[y * w..y * w + n]);
+                }
+            }
+            None => {
+                let texture = Texture { width: w, height: h, pixels: image.pixels.clone() };
+                self.textures.insert(id, texture);
+            }
+        }
+    }
+
+    /// Rasterize one of egui's meshes into `frame`, clipped to its rect,
+    /// blending its premultiplied colours over what is there.
+    fn draw(&self, frame: &mut Grid<u32>, primitive: ClippedPrimitive) {
+        let Primitive::Mesh(mesh) = primitive.primitive else {
+            return;
+        };
+        let Some(texture) = self.textures.get(&mesh.texture_id) else {
+            return;
+        };
+        let clip = primitive.clip_rect;
+        let x_min = clip.min.x.max(0.0) as usize;
+        let y_min = clip.min.y.max(0.0) as usize;
+        let x_max = (clip.max.x.ceil().max(0.0) as usize).min(frame.width());
+        let y_max = (clip.max.y.ceil().max(0.0) as usize).min(frame.height());
+        for tri in mesh.indices.chunks_exact(3) {
+            let v = [0, 1, 2].map(|i| mesh.vertices[tri[i] as usize]);
+            let area = edge(v[0].pos, v[1].pos, v[2].pos);
+            if area == 0.0 {
+                continue;
+            }
+            let xs = v.map(|v| v.pos.x);
+            let ys = v.map(|v| v.pos.y);
+            let x0 = (xs.iter().copied().fold(f32::MAX, f32::min).max(0.0) as usize).max(x_min);
+            let y0 = (ys.iter().copied().fold(f32::MAX, f32::min).max(0.0) as usize).max(y_min);
+            let x1 = (xs.iter().copied().fold(0.0, f32::max).ceil() as usize).min(x_max);
+            let y1 = (ys.iter().copied().fold(0.0, f32::max).ceil() as usize).min(y_max);
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    let p = Pos2::new(x as f32 + 0.5, y as f32 + 0.5);
+                    // Weights of the three corners, all of one sign inside.
+                    let w = [
+                        edge(v[1].pos, v[2].pos, p) / area,
+                        edge(v[2].pos, v[0].pos, p) / area,
+                        edge(v[0].pos, v[1].pos, p) / area,
+                    ];
+                    if w.iter().any(|&w| w < 0.0) {
+                        continue;
+                    }
+                    let color = shade(texture, &v, w);
+                    let old = frame.get(x, y);
+                    frame.set(x, y, blend(color, old));
+                }
+            }
+        }
+    }
+}
+
+/// Twice the signed area of the triangle `a`, `b`, `p`.
+fn edge(a: Pos2, b: Pos2, p: Pos2) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+/// The premultiplied colour, as `[r, g, b, a]` from 0 to 1, of the point
+/// with corner weights `w` in the triangle `v`: the corners' colours times
+/// the texel under it.
+fn shade(texture: &Texture, v: &[Vertex; 3], w: [f32; 3]) -> [f32; 4] {
+    let u = w[0] * v[0].uv.x + w[1] * v[1].uv.x + w[2] * v[2].uv.x;
+    let t = w[0] * v[0].uv.y + w[1] * v[1].uv.y + w[2] * v[2].uv.y;
+    let tx = ((u * texture.width as f32) as usize).min(texture.width - 1);
+    let ty = ((t * texture.height as f32) as usize).min(texture.height - 1);
+    let texel = texture.pixels[ty * texture.width + tx].to_array();
+    std::array::from_fn(|c| {
+        let corner = |i: usize| v[i].color.to_array()[c] as f32;
+        let color = w[0] * corner(0) + w[1] * corner(1) + w[2] * corner(2);
+        color / 255.0 * texel[c] as f32 / 255.0
+    })
+}
+
+/// `color` drawn over the 0xAARRGGBB pixel `old`.
+fn blend(color: [f32; 4], old: u32) -> u32 {
+    let keep = 1.0 - color[3];
+    let channel = |c: usize, shift: u32| {
+        let old = ((old >> shift) & 0xff) as f32;
+        ((color[c] * 255.0 + old * keep).round().min(255.0) as u32) << shift
+    };
+    0xff00_0000 | channel(0, 16) | channel(1, 8) | channel(2, 0)
+}
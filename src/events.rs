@@ -0,0 +1,23 @@
+// Things that happen in the world that a program embedding it may want to
+// react to, such as playing a sound or keeping score, without reaching into
+// the update. Handlers registered with `World::on_event` are called as each
+// one happens, in the order they were registered.
+
+use crate::material::Material;
+
+/// Something that happened in the world, and where.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// A grain came into the world: put down by a brush, a tool, a paste or
+    /// an emitter, or made by a reaction, a fire or the weather.
+    GrainSpawned { x: usize, y: usize, material: Material },
+    /// A falling or thrown grain hit something and came to a stop on it.
+    GrainSettled { x: usize, y: usize, material: Material },
+    /// A grain was removed by an open drain.
+    GrainDrained { x: usize, y: usize, material: Material },
+    /// Gunpowder blew up, centred on `(x, y)`.
+    ExplosionTriggered { x: usize, y: usize },
+}
+
+/// A callback given every event.
+pub(crate) type Handler = Box<dyn FnMut(&Event) + Send + Sync>;
@@ -0,0 +1,92 @@
+// Gunpowder explosions. Cells set off during a tick are queued on the world
+// and blown up here, after movement and reactions: everything near the blast
+// turns to fire and smoke, and a shockwave throws loose grains further out
+// clear, to fly until they land. Gunpowder caught in a blast goes off on the
+// next tick, so chains of it burn along instead of all going up at once.
+
+use crate::events::Event;
+use crate::material::{Material, Movement};
+use crate::world::{NO_GRAIN, World};
+
+/// Cells within this distance of a blast are turned to fire and smoke.
+const BLAST_RADIUS: isize = 7;
+/// Loose grains within this distance are thrown outwards by the shockwave.
+const PUSH_RADIUS: usize = 2 * BLAST_RADIUS as usize;
+/// Speed the shockwave would give a grain at its centre, in cells per tick,
+/// falling off to nothing at `PUSH_RADIUS`; half of it at the edge of the
+/// blast.
+const PUSH_SPEED: f32 = 12.0;
+/// Chance a cell in the blast becomes fire rather than smoke.
+const FIRE_CHANCE: f32 = 0.6;
+/// Chance an empty cell in the blast is filled at all.
+const FILL_CHANCE: f32 = 0.5;
+
+/// Blow up every gunpowder cell set off this tick.
+pub(crate) fn detonate(world: &mut World) {
+    if world.explosions.is_empty() {
+        return;
+    }
+    for (x, y) in std::mem::take(&mut world.explosions) {
+        // Another blast this tick may have got there first.
+        if world.grid.get(x, y) == Material::Gunpowder {
+            world.send(Event::ExplosionTriggered { x, y });
+            blast(world, x, y);
+        }
+    }
+}
+
+/// True if a blast leaves cells of `material` standing: solid, unburnable
+/// static cells such as wall and stone.
+fn withstands(material: Material) -> bool {
+    let props = material.props();
+    material != Material::Empty
+        && props.movement == Movement::Static
+        && props.flammability == 0.0
+}
+
+/// Burn everything near `(cx, cy)`, then throw what is left around it
+/// outwards.
+fn blast(world: &mut World, cx: usize, cy: usize) {
+    for dy in -BLAST_RADIUS..=BLAST_RADIUS {
+        for dx in -BLAST_RADIUS..=BLAST_RADIUS {
+            let d2 = dx * dx + dy * dy;
+            let (x, y) = (cx as isize + dx, cy as isize + dy);
+            if d2 > BLAST_RADIUS * BLAST_RADIUS || !world.grid.in_bounds(x, y) {
+                continue;
+            }
+            let (x, y) = (x as usize, y as usize);
+            let material = world.grid.get(x, y);
+            burst(world, x, y, material, d2 == 0);
+        }
+    }
+    world.shockwave(cx, cy, PUSH_RADIUS, PUSH_SPEED);
+}
+
+/// Turn the cell at `(x, y)`, holding `material`, to fire or smoke. Other
+/// gunpowder is set off instead, to blow up next tick.
+fn burst(world: &mut World, x: usize, y: usize, material: Material, centre: bool) {
+    if withstands(material) {
+        return;
+    }
+    if material == Material::Gunpowder && !centre {
+        world.explosions.push((x, y));
+        return;
+    }
+    if material == Material::Empty && world.rng.uni() >= FILL_CHANCE {
+        return;
+    }
+    let next = if world.rng.uni() < FIRE_CHANCE { Material::Fire } else { Material::Smoke };
+    let grain = world.new_grain(x, y, next);
+    match world.index.get(x, y) {
+        NO_GRAIN => {
+            world.grid.set(x, y, Material::Empty);
+            world.add_grain(grain);
+        }
+        idx => {
+            world.grains[idx as usize] = grain;
+            world.grid.set(x, y, next);
+            world.heat.set(x, y, next.props().temperature);
+            world.free_cell(x, y);
+        }
+    }
+}
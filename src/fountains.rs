@@ -0,0 +1,61 @@
+// Clone and void cells, for fountains that never run dry and sinks that
+// never fill. A clone cell takes on the material of the first grain to touch
+// it and from then on puts a grain of it into every empty cell beside it each
+// tick. A void cell deletes every grain that touches it. Both are static, so
+// only the chunks with something moving in them need looking through.
+
+use crate::material::Material;
+use crate::power::neighbours;
+use crate::world::World;
+
+/// Copy grains out of clone cells and delete the grains touching void
+/// cells.
+pub(crate) fn run(world: &mut World) {
+    let mut clones = Vec::new();
+    let mut voids = Vec::new();
+    for (x0, y0, w, h) in world.chunks.active_rects() {
+        for y in y0..y0 + h {
+            for (x, &m) in world.grid.row(y)[x0..x0 + w].iter().enumerate() {
+                match m {
+                    Material::Clone => clones.push((x0 + x, y)),
+                    Material::Void => voids.push((x0 + x, y)),
+                    _ => {}
+                }
+            }
+        }
+    }
+    if clones.is_empty() && voids.is_empty() {
+        return;
+    }
+
+    let (w, h) = (world.width, world.height);
+    let mut removed = false;
+    for (x, y) in voids {
+        for (nx, ny) in neighbours(w, h, x, y) {
+            if world.grid.get(nx, ny).is_movable() {
+                removed |= world.clear_cell(nx, ny);
+            }
+        }
+    }
+    if removed {
+        world.grains.retain(|g| g.material != Material::Empty);
+        world.reindex();
+    }
+
+    for (x, y) in clones {
+        let mut source = world.cloned.get(x, y);
+        if source == Material::Empty {
+            let Some(touching) = neighbours(w, h, x, y)
+                .map(|(nx, ny)| world.grid.get(nx, ny))
+                .find(|m| m.is_movable())
+            else {
+                continue;
+            };
+            world.cloned.set(x, y, touching);
+            source = touching;
+        }
+        for (nx, ny) in neighbours(w, h, x, y) {
+            world.place(nx, ny, source);
+        }
+    }
+}
@@ -0,0 +1,267 @@
+// A backend that shows frames with wgpu, built with the `wgpu` feature. Each
+// frame is uploaded as a texture and drawn over the window by one triangle
+// covering it, so scaling a big world up to the window costs the GPU rather
+// than the CPU. The fragment shader only samples the texture for now; it is
+// where post-processing such as bloom or scanlines would go.
+
+use sandfall::grid::Grid;
+
+use crate::backend::{Backend, Key, MouseButton};
+use crate::winit_window::WinitWindow;
+
+/// Draws the frame texture over the viewport, nearest cell first so cells
+/// stay square when scaled up.
+const SHADER: &str = r"
+@group(0) @binding(0) var frame: texture_2d<f32>;
+@group(0) @binding(1) var cells: sampler;
+
+struct Corner {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+// One triangle twice the size of the viewport, clipped to it.
+@vertex
+fn vs(@builtin(vertex_index) i: u32) -> Corner {
+    let uv = vec2<f32>(f32((i << 1u) & 2u), f32(i & 2u));
+    return Corner(vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0), uv);
+}
+
+@fragment
+fn fs(corner: Corner) -> @location(0) vec4<f32> {
+    return vec4<f32>(textureSample(frame, cells, corner.uv).rgb, 1.0);
+}
+";
+
+/// A backend on a winit window, drawn into with wgpu.
+pub struct Gpu {
+    window: WinitWindow,
+    surface: wgpu::Surface<'static>,
+    config: wgpu::SurfaceConfiguration,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::RenderPipeline,
+    sampler: wgpu::Sampler,
+    /// The frame texture and what binds it to the pipeline, remade when
+    /// the frame changes size.
+    texture: Option<(wgpu::Texture, wgpu::BindGroup)>,
+}
+
+impl Gpu {
+    /// Open a `width` x `height` window titled `title`, which the user can
+    /// resize if `resizable`.
+    pub fn new(title: &str, width: usize, height: usize, resizable: bool) -> Self {
+        let window = WinitWindow::open(title, width, height, resizable);
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let surface: wgpu::Surface<'static> =
+            instance.create_surface(window.window()).expect("Unable to create window");
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            compatible_surface: Some(&surface),
+            ..Default::default()
+        }))
+        .expect("No GPU adapter can draw to the window");
+        let (device, queue) =
+            pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default()))
+                .expect("Unable to open the GPU");
+
+        let (w, h) = window.size();
+        let config = surface
+            .get_default_config(&adapter, w.max(1) as u32, h.max(1) as u32)
+            .expect("The GPU cannot draw to the window");
+        surface.configure(&device, &config);
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("frame"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("frame"),
+            layout: None,
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: Some("vs"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: Some("fs"),
+                compilation_options: Default::default(),
+                targets: &[Some(config.format.into())],
+            }),
+            multiview: None,
+            cache: None,
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        Self { window, surface, config, device, queue, pipeline, sampler, texture: None }
+    }
+
+    /// The frame texture for a `width` x `height` frame, made anew if the
+    /// last one was another size.
+    fn texture(&mut self, width: u32, height: u32) -> &(wgpu::Texture, wgpu::BindGroup) {
+        let fits = |(t, _): &(wgpu::Texture, _)| (t.width(), t.height()) == (width, height);
+        if !self.texture.as_ref().is_some_and(fits) {
+            // Frames hold 0xAARRGGBB words, which are B, G, R, A in memory.
+            // Colours are sRGB already, so they go through untouched if the
+            // window is sRGB too.
+            let format = if self.config.format.is_srgb() {
+                wgpu::TextureFormat::Bgra8UnormSrgb
+            } else {
+                wgpu::TextureFormat::Bgra8Unorm
+            };
+            let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("frame"),
+                size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("frame"),
+                layout: &self.pipeline.get_bind_group_layout(0),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                ],
+            });
+            self.texture = Some((texture, bind_group));
+        }
+        self.texture.as_ref().expect("made above")
+    }
+
+    /// Draw `frame` to the window, scaled to fit with black bars.
+    fn draw(&mut self, frame: &Grid<u32>) {
+        let (ww, wh) = self.window.size();
+        if ww == 0 || wh == 0 {
+            // Minimised.
+            return;
+        }
+        if (self.config.width, self.config.height) != (ww as u32, wh as u32) {
+            self.config.width = ww as u32;
+            self.config.height = wh as u32;
+            self.surface.configure(&self.device, &self.config);
+        }
+        let output = match self.surface.get_current_texture() {
+            Ok(output) => output,
+            // The window changed under the surface; draw the next frame.
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                self.surface.configure(&self.device, &self.config);
+                return;
+            }
+            Err(wgpu::SurfaceError::Timeout) => return,
+            Err(e) => panic!("Failed to update window: {e}"),
+        };
+
+        let (fw, fh) = (frame.width() as u32, frame.height() as u32);
+        let size = wgpu::Extent3d { width: fw, height: fh, depth_or_array_layers: 1 };
+        let texture = self.texture(fw, fh).0.clone();
+        self.queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            bytemuck::cast_slice(frame.as_slice()),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * fw),
+                rows_per_image: Some(fh),
+            },
+            size,
+        );
+
+        // Scale to fit keeping the frame's shape, as minifb's
+        // `AspectRatioStretch` does.
+        let (ww, wh) = (ww as f32, wh as f32);
+        let scale = (ww / fw as f32).min(wh / fh as f32);
+        let (vw, vh) = (fw as f32 * scale, fh as f32 * scale);
+
+        let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("frame"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_viewport((ww - vw) / 2.0, (wh - vh) / 2.0, vw, vh, 0.0, 1.0);
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &self.texture.as_ref().expect("made above").1, &[]);
+            pass.draw(0..3, 0..1);
+        }
+        self.queue.submit(Some(encoder.finish()));
+        self.window.window().pre_present_notify();
+        output.present();
+    }
+}
+
+impl Backend for Gpu {
+    fn is_open(&self) -> bool {
+        self.window.is_open()
+    }
+
+    fn is_key_down(&self, key: Key) -> bool {
+        self.window.is_key_down(key)
+    }
+
+    fn keys_pressed(&self) -> Vec<Key> {
+        self.window.keys_pressed()
+    }
+
+    fn is_mouse_down(&self, button: MouseButton) -> bool {
+        self.window.is_mouse_down(button)
+    }
+
+    fn mouse_pos(&self) -> Option<(f32, f32)> {
+        self.window.mouse_pos()
+    }
+
+    fn scroll_wheel(&self) -> Option<(f32, f32)> {
+        self.window.scroll_wheel()
+    }
+
+    fn size(&self) -> (usize, usize) {
+        self.window.size()
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.window.set_title(title);
+    }
+
+    fn set_fullscreen(&mut self, fullscreen: bool, _: (usize, usize)) {
+        self.window.set_fullscreen(fullscreen);
+    }
+
+    fn present(&mut self, frame: &Grid<u32>) {
+        self.draw(frame);
+        self.window.update();
+    }
+}
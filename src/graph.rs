@@ -0,0 +1,66 @@
+// A rolling graph of how long recent frames took, drawn under the HUD so a
+// slow brush or material shows up as a spike the moment it happens. Each
+// column is one frame: its whole height is the frame's time and the lower,
+// brighter part the time spent in physics. A line marks the time a frame
+// has at 60 frames a second.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use sandfall::chunks::Rect;
+use sandfall::grid::Grid;
+use sandfall::render::{draw_square, Pixel};
+
+/// Frames shown, one pixel wide each.
+const LEN: usize = 240;
+/// Height of the graph in pixels.
+const HEIGHT: usize = 60;
+/// Frame time at the top of the graph; longer frames are cut off there.
+const FULL_SCALE: Duration = Duration::from_micros(33_333);
+/// Frame time at 60 frames a second, marked across the graph.
+const TARGET: Duration = Duration::from_micros(16_667);
+
+const BACKGROUND: Pixel = Pixel { r: 0, g: 0, b: 0, a: 255 };
+const FRAME: Pixel = Pixel { r: 110, g: 110, b: 120, a: 255 };
+const PHYSICS: Pixel = Pixel { r: 255, g: 160, b: 40, a: 255 };
+const LINE: Pixel = Pixel { r: 40, g: 160, b: 70, a: 255 };
+
+/// Frame and physics times of the last `LEN` frames, oldest first.
+#[derive(Default)]
+pub struct FrameGraph {
+    frames: VecDeque<(Duration, Duration)>,
+}
+
+impl FrameGraph {
+    /// Add a frame that took `frame` in all, `physics` of it stepping the
+    /// world.
+    pub fn push(&mut self, frame: Duration, physics: Duration) {
+        if self.frames.len() == LEN {
+            self.frames.pop_front();
+        }
+        self.frames.push_back((frame, physics));
+    }
+
+    /// Draw the graph into `frame` with its top-left corner at `(x, y)`,
+    /// returning the area covered.
+    pub fn draw(&self, frame: &mut Grid<u32>, x: usize, y: usize) -> Rect {
+        draw_square(frame, x, y, LEN, HEIGHT, BACKGROUND);
+        let height = |t: Duration| {
+            let h = t.as_secs_f32() / FULL_SCALE.as_secs_f32() * HEIGHT as f32;
+            (h.round() as usize).min(HEIGHT)
+        };
+        let bottom = y + HEIGHT;
+        // Newest on the right, so the graph scrolls left as frames come in.
+        let start = x + LEN - self.frames.len();
+        for (i, &(total, physics)) in self.frames.iter().enumerate() {
+            let (total, physics) = (height(total), height(physics).min(height(total)));
+            draw_square(frame, start + i, bottom - total, 1, total - physics, FRAME);
+            draw_square(frame, start + i, bottom - physics, 1, physics, PHYSICS);
+        }
+        draw_square(frame, x, bottom - height(TARGET), LEN, 1, LINE);
+
+        let w = LEN.min(frame.width().saturating_sub(x));
+        let h = HEIGHT.min(frame.height().saturating_sub(y));
+        (x, y, w, h)
+    }
+}
@@ -0,0 +1,112 @@
+// A flat, row-major 2D buffer. One allocation with a width stride avoids the
+// pointer chase per row of a `Vec<Vec<T>>`.
+
+/// A `width` x `height` grid of `T` stored row by row.
+#[derive(Clone, PartialEq)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    data: Vec<T>,
+}
+
+impl<T: Copy> Grid<T> {
+    /// Create a grid with every cell set to `value`.
+    pub fn new(width: usize, height: usize, value: T) -> Self {
+        Self { width, height, data: vec![value; width * height] }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Offset of `(x, y)` into the flat data.
+    #[inline]
+    pub fn idx(&self, x: usize, y: usize) -> usize {
+        y * self.width + x
+    }
+
+    #[inline]
+    pub fn get(&self, x: usize, y: usize) -> T {
+        self.data[self.idx(x, y)]
+    }
+
+    #[inline]
+    pub fn set(&mut self, x: usize, y: usize, value: T) {
+        let i = self.idx(x, y);
+        self.data[i] = value;
+    }
+
+    /// True if the signed coordinates `(x, y)` fall inside the grid.
+    #[inline]
+    pub fn in_bounds(&self, x: isize, y: isize) -> bool {
+        x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height
+    }
+
+    /// Set every cell to `value`.
+    pub fn fill(&mut self, value: T) {
+        self.data.fill(value);
+    }
+
+    pub fn row(&self, y: usize) -> &[T] {
+        &self.data[y * self.width..(y + 1) * self.width]
+    }
+
+    pub fn row_mut(&mut self, y: usize) -> &mut [T] {
+        &mut self.data[y * self.width..(y + 1) * self.width]
+    }
+
+    /// All cells, row by row.
+    pub fn as_slice(&self) -> &[T] {
+        &self.data
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.data
+    }
+}
+
+impl<T: Copy + PartialEq> Grid<T> {
+    /// Set the cell at `(x, y)` and every cell joined to it through its four
+    /// neighbours with the same value to `value`, and return the filled
+    /// spans as `(y, x0, x1)` with both ends included.
+    ///
+    /// Fills a row-long span at a time from an explicit stack, so even a
+    /// fill over the whole grid cannot overflow the call stack.
+    pub fn flood_fill(&mut self, x: usize, y: usize, value: T) -> Vec<(usize, usize, usize)> {
+        let target = self.get(x, y);
+        let mut spans = Vec::new();
+        if target == value {
+            return spans;
+        }
+        let mut seeds = vec![(x, y)];
+        while let Some((x, y)) = seeds.pop() {
+            if self.get(x, y) != target {
+                continue;
+            }
+            let row = self.row_mut(y);
+            let x0 = row[..x].iter().rposition(|&v| v != target).map_or(0, |i| i + 1);
+            let x1 = row[x..].iter().position(|&v| v != target).map_or(row.len(), |i| x + i) - 1;
+            row[x0..=x1].fill(value);
+            spans.push((y, x0, x1));
+
+            // Seed each run of matching cells in the rows above and below.
+            let above = y.checked_sub(1);
+            let below = Some(y + 1).filter(|&ny| ny < self.height);
+            for ny in [above, below].into_iter().flatten() {
+                let mut in_run = false;
+                for nx in x0..=x1 {
+                    let matches = self.get(nx, ny) == target;
+                    if matches && !in_run {
+                        seeds.push((nx, ny));
+                    }
+                    in_run = matches;
+                }
+            }
+        }
+        spans
+    }
+}
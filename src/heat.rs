@@ -0,0 +1,68 @@
+// The temperature of every cell. Heat travels with the material holding it
+// when grains move, and spreads between neighbouring cells of active chunks,
+// so hot material warms what it touches and cools off in the open.
+
+use crate::grid::Grid;
+use crate::material::Material;
+use crate::world::World;
+
+/// Temperature everything relaxes towards, unless the world has a day and
+/// night.
+pub const AMBIENT: f32 = 20.0;
+/// Fraction of the gap to the mean of its four neighbours a cell closes
+/// each tick.
+const CONDUCTION: f32 = 0.02;
+/// Fraction of its difference from the air's temperature an empty cell
+/// loses each tick.
+const AIR_LOSS: f32 = 0.1;
+/// Temperature above which flammable cells may catch fire.
+pub const IGNITION: f32 = 300.0;
+
+/// A grid with every cell at the starting temperature of its material.
+pub(crate) fn initial(grid: &Grid<Material>) -> Grid<f32> {
+    let mut heat = Grid::new(grid.width(), grid.height(), AMBIENT);
+    for (t, m) in heat.as_mut_slice().iter_mut().zip(grid.as_slice()) {
+        *t = m.props().temperature;
+    }
+    heat
+}
+
+/// Spread heat for one tick across the active chunks, and collect the
+/// flammable cells that got hot enough to catch fire.
+pub(crate) fn diffuse(world: &mut World, ignite: &mut Vec<(usize, usize)>) {
+    let rects: Vec<_> = world.chunks.active_rects().collect();
+    let (w, h) = (world.width, world.height);
+    let ambient = world.ambient();
+    let mut scratch = Vec::new();
+    for (rx, ry, rw, rh) in rects {
+        scratch.clear();
+        let heat = &world.heat;
+        for y in ry..ry + rh {
+            for x in rx..rx + rw {
+                let t = heat.get(x, y);
+                let mean = (heat.get(x.saturating_sub(1), y)
+                    + heat.get((x + 1).min(w - 1), y)
+                    + heat.get(x, y.saturating_sub(1))
+                    + heat.get(x, (y + 1).min(h - 1)))
+                    / 4.0;
+                let mut t = t + CONDUCTION * (mean - t);
+                if world.grid.get(x, y) == Material::Empty {
+                    t -= AIR_LOSS * (t - ambient);
+                }
+                scratch.push(t);
+            }
+        }
+
+        let mut next = scratch.iter();
+        for y in ry..ry + rh {
+            for x in rx..rx + rw {
+                let t = *next.next().unwrap();
+                world.heat.set(x, y, t);
+                let flammability = world.grid.get(x, y).props().flammability;
+                if t > IGNITION && flammability > 0.0 && world.rng.uni() < flammability {
+                    ignite.push((x, y));
+                }
+            }
+        }
+    }
+}
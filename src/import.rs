@@ -0,0 +1,187 @@
+// Builds a world from a PNG so levels can be drawn in an image editor. Each
+// pixel becomes the material whose key colour it is closest to, and the
+// image is stretched to fill the world. The same conversion reads small
+// images as stamps to paste in one piece.
+//
+// Walls can also be drawn from a text file of lines and filled shapes, for
+// funnels and ramps that need to be exact:
+//
+//     # coordinates run from 0 to the size, stretched to fill the world
+//     size 100 100
+//     # line thickness, in the same units
+//     thickness 1
+//     # a line through each point in turn; `loop` closes it
+//     line 10 20 45 60 45 70
+//     loop 70 10 90 10 80 30
+//     # a filled convex polygon, at least three points
+//     fill 0 100 30 100 0 80
+
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::Path;
+
+use png::{ColorType, Decoder, Transformations};
+
+use crate::grid::Grid;
+use crate::material::Material;
+use crate::scenes::Shape;
+use crate::world::{Region, World};
+
+/// Colours an image is matched against. Anything closest to white, or
+/// mostly transparent, is left empty.
+const KEY: [([u8; 3], Material); 8] = [
+    ([255, 255, 255], Material::Empty),
+    ([0, 0, 0], Material::Wall),
+    ([128, 128, 128], Material::Wall),
+    ([255, 255, 0], Material::Sand),
+    ([194, 178, 128], Material::Sand),
+    ([0, 0, 255], Material::Water),
+    ([255, 0, 0], Material::Fire),
+    ([255, 128, 0], Material::Fire),
+];
+
+/// The material a pixel of colour `(r, g, b)` and alpha `a` stands for.
+pub fn material_for(r: u8, g: u8, b: u8, a: u8) -> Material {
+    if a < 128 {
+        return Material::Empty;
+    }
+    let distance = |key: &[u8; 3]| -> i32 {
+        let d = |p: u8, q: u8| (p as i32 - q as i32).pow(2);
+        d(r, key[0]) + d(g, key[1]) + d(b, key[2])
+    };
+    KEY.iter().min_by_key(|(key, _)| distance(key)).map_or(Material::Empty, |&(_, m)| m)
+}
+
+/// Read the PNG at `path` as a grid of materials, one cell per pixel.
+pub fn read_png(path: &Path) -> io::Result<Grid<Material>> {
+    let mut decoder = Decoder::new(BufReader::new(File::open(path)?));
+    decoder.set_transformations(Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let size = reader
+        .output_buffer_size()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "image too large"))?;
+    let mut buf = vec![0; size];
+    let info = reader.next_frame(&mut buf)?;
+    let channels = match info.color_type {
+        ColorType::Grayscale => 1,
+        ColorType::GrayscaleAlpha => 2,
+        ColorType::Rgb => 3,
+        ColorType::Rgba => 4,
+        ColorType::Indexed => {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "unexpanded palette image"));
+        }
+    };
+    let (width, height) = (info.width as usize, info.height as usize);
+    if width == 0 || height == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "empty image"));
+    }
+
+    let mut cells = Grid::new(width, height, Material::Empty);
+    for y in 0..height {
+        let row = &buf[y * info.line_size..];
+        for x in 0..width {
+            let p = &row[x * channels..][..channels];
+            let (r, g, b, a) = match *p {
+                [v] => (v, v, v, 255),
+                [v, a] => (v, v, v, a),
+                [r, g, b] => (r, g, b, 255),
+                [r, g, b, a] => (r, g, b, a),
+                _ => unreachable!(),
+            };
+            cells.set(x, y, material_for(r, g, b, a));
+        }
+    }
+    Ok(cells)
+}
+
+/// Clear `world` and fill it from the PNG at `path`.
+pub fn load_png(world: &mut World, path: &Path) -> io::Result<()> {
+    let cells = read_png(path)?;
+    let (iw, ih) = (cells.width(), cells.height());
+
+    world.clear();
+    let (width, height) = (world.width(), world.height());
+    for y in 0..height {
+        for x in 0..width {
+            match cells.get(x * iw / width, y * ih / height) {
+                Material::Empty => {}
+                m => world.place(x, y, m),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Read the PNG at `path` as a stamp, at one cell per pixel.
+pub fn load_stamp(path: &Path) -> io::Result<Region> {
+    Ok(Region::from_cells(read_png(path)?))
+}
+
+/// Read the wall file at `path` as shapes, in fractions of the world's size.
+pub fn read_walls(path: &Path) -> io::Result<Vec<Shape>> {
+    let text = fs::read_to_string(path)?;
+    let invalid = |line: usize, message: String| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("line {line}: {message}"))
+    };
+    let (mut size_w, mut size_h) = (1.0, 1.0);
+    let mut thickness = 0.0;
+    let mut shapes = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line_no = i + 1;
+        let line = line.split('#').next().unwrap_or("");
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            continue;
+        };
+        let numbers = words
+            .map(|w| w.parse::<f32>().ok().filter(|n| n.is_finite()))
+            .collect::<Option<Vec<f32>>>()
+            .ok_or_else(|| invalid(line_no, format!("`{command}` takes numbers")))?;
+        let scale = (size_w, size_h);
+        let point = move |p: &[f32]| (p[0] / scale.0, p[1] / scale.1);
+        match command {
+            "size" => match numbers[..] {
+                [w, h] if w > 0.0 && h > 0.0 => (size_w, size_h) = (w, h),
+                _ => return Err(invalid(line_no, "`size` takes a width and height".into())),
+            },
+            "thickness" => match numbers[..] {
+                [t] if t >= 0.0 => thickness = t,
+                _ => return Err(invalid(line_no, "`thickness` takes one number".into())),
+            },
+            "line" | "loop" => {
+                if numbers.len() < 4 || !numbers.len().is_multiple_of(2) {
+                    return Err(invalid(line_no, format!("`{command}` takes two points or more")));
+                }
+                let mut points: Vec<_> = numbers.chunks(2).map(point).collect();
+                if command == "loop" {
+                    points.push(points[0]);
+                }
+                let radius = thickness / 2.0 / size_w;
+                shapes.extend(
+                    points.windows(2).map(|p| Shape::Line { from: p[0], to: p[1], radius }),
+                );
+            }
+            "fill" => {
+                if numbers.len() < 6 || !numbers.len().is_multiple_of(2) {
+                    return Err(invalid(line_no, "`fill` takes three points or more".into()));
+                }
+                // A fan of triangles from the first point, which covers any
+                // convex polygon.
+                let points: Vec<_> = numbers.chunks(2).map(point).collect();
+                let a = points[0];
+                let fan = points[1..].windows(2).map(|p| Shape::Triangle { a, b: p[0], c: p[1] });
+                shapes.extend(fan);
+            }
+            other => return Err(invalid(line_no, format!("unknown command `{other}`"))),
+        }
+    }
+    Ok(shapes)
+}
+
+/// Draw the walls in the file at `path` over `world`, stretched to fill it.
+pub fn load_walls(world: &mut World, path: &Path) -> io::Result<()> {
+    for shape in read_walls(path)? {
+        shape.place(world, Material::Wall);
+    }
+    Ok(())
+}
@@ -0,0 +1,70 @@
+// What the person or program driving the world is doing, as plain data:
+// where the pointer is in world cells, which buttons are down, what the
+// brush places and whether the drains are open. Frontends fill one in from
+// whatever they read input from and hand it to `World::step`, so the same
+// world can be driven by a window, a terminal, a browser, a test or a
+// recording without knowing which.
+
+use crate::material::Material;
+use crate::world::World;
+
+/// Input for one tick.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InputState {
+    /// The cell under the pointer, `None` while it is off the world.
+    pub mouse: Option<(usize, usize)>,
+    /// The left button pours `material` there, or paints it if it does not
+    /// move.
+    pub left: bool,
+    /// The right button erases there.
+    pub right: bool,
+    pub material: Material,
+    /// Radius of the brush, in cells.
+    pub radius: usize,
+    /// Grains the left button tries to pour each time the input is applied.
+    pub tries: usize,
+    /// Every drain is open.
+    pub drain: bool,
+}
+
+impl InputState {
+    /// Nothing held and nowhere pointed at: a tick the world runs by itself.
+    pub const NONE: InputState = InputState {
+        mouse: None,
+        left: false,
+        right: false,
+        material: Material::Sand,
+        radius: 1,
+        tries: 0,
+        drain: false,
+    };
+
+    /// Tries enough for the left button to fill most of a brush of
+    /// `radius`: one for every cell of the box round the circle.
+    pub fn box_tries(radius: usize) -> usize {
+        radius.saturating_mul(2).saturating_add(1).saturating_pow(2)
+    }
+}
+
+impl Default for InputState {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+/// Pour, paint or erase at the pointer as the buttons say.
+pub(crate) fn apply(world: &mut World, input: &InputState) {
+    let Some((x, y)) = input.mouse else {
+        return;
+    };
+    let (x, y) = (x as isize, y as isize);
+    if input.left {
+        if input.material.is_movable() {
+            world.spawn(x, y, input.radius, input.tries, input.material);
+        } else {
+            world.paint(x, y, input.radius, input.material);
+        }
+    } else if input.right {
+        world.erase(x, y, input.radius);
+    }
+}
@@ -0,0 +1,394 @@
+// Which keys do what in the window frontend, read from an optional
+// `keys.toml` at startup so bindings can be changed without recompiling.
+// The frame loop asks about `Action`s rather than keys: `Input` reads every
+// action's keys from the backend once a frame and remembers the frame
+// before, so it can tell a fresh press from a key being held.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::backend::{Backend, Key};
+
+/// Something a key does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    Fullscreen,
+    Hud,
+    Panel,
+    Minimap,
+    /// Show what is in the cell under the cursor.
+    Inspect,
+    /// Open the console to type commands into.
+    Console,
+    Bounds,
+    Save,
+    Load,
+    Record,
+    NextScene,
+    /// Lay the puzzle or scene out again from the start.
+    Restart,
+    ToggleEmitters,
+    Slower,
+    Faster,
+    Pause,
+    /// One tick while paused.
+    Step,
+    /// Held to run the last few seconds backwards.
+    Rewind,
+    NextMaterial,
+    NextTool,
+    NextShape,
+    Symmetry,
+    /// Copy, cut and paste only act with `Ctrl` held.
+    Copy,
+    Cut,
+    Paste,
+    Ctrl,
+    ZoomIn,
+    ZoomOut,
+    /// Pan the view, or with `Gravity` held point gravity that way.
+    PanUp,
+    PanDown,
+    PanLeft,
+    PanRight,
+    Gravity,
+    /// Turn gravity round, like flipping an hourglass.
+    Flip,
+    /// Held to shake the world, so piles slump.
+    Quake,
+    /// Held to place and remove emitters with the mouse.
+    Emitter,
+    /// Held to place and remove drains with the mouse.
+    Drain,
+    /// Held to place the two ends of a pair of portals, and remove pairs.
+    Portal,
+    /// Held to place and remove attractors with the mouse.
+    Attractor,
+    /// Held to open every drain.
+    OpenDrains,
+    /// Held to make the right button paint wall instead of erasing, and new
+    /// attractors push instead of pull.
+    Wall,
+    /// Pick the material in a slot of the palette, counting from 1.
+    Slot1,
+    Slot2,
+    Slot3,
+    Slot4,
+    Slot5,
+    Slot6,
+    Slot7,
+    Slot8,
+    Slot9,
+}
+
+impl Action {
+    pub const COUNT: usize = 51;
+    pub const ALL: [Action; Action::COUNT] = [
+        Action::Quit,
+        Action::Fullscreen,
+        Action::Hud,
+        Action::Panel,
+        Action::Minimap,
+        Action::Inspect,
+        Action::Console,
+        Action::Bounds,
+        Action::Save,
+        Action::Load,
+        Action::Record,
+        Action::NextScene,
+        Action::Restart,
+        Action::ToggleEmitters,
+        Action::Slower,
+        Action::Faster,
+        Action::Pause,
+        Action::Step,
+        Action::Rewind,
+        Action::NextMaterial,
+        Action::NextTool,
+        Action::NextShape,
+        Action::Symmetry,
+        Action::Copy,
+        Action::Cut,
+        Action::Paste,
+        Action::Ctrl,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::PanUp,
+        Action::PanDown,
+        Action::PanLeft,
+        Action::PanRight,
+        Action::Gravity,
+        Action::Flip,
+        Action::Quake,
+        Action::Emitter,
+        Action::Drain,
+        Action::Portal,
+        Action::Attractor,
+        Action::OpenDrains,
+        Action::Wall,
+        Action::Slot1,
+        Action::Slot2,
+        Action::Slot3,
+        Action::Slot4,
+        Action::Slot5,
+        Action::Slot6,
+        Action::Slot7,
+        Action::Slot8,
+        Action::Slot9,
+    ];
+    /// The material slots, in order from 1.
+    pub const SLOTS: [Action; 9] = [
+        Action::Slot1,
+        Action::Slot2,
+        Action::Slot3,
+        Action::Slot4,
+        Action::Slot5,
+        Action::Slot6,
+        Action::Slot7,
+        Action::Slot8,
+        Action::Slot9,
+    ];
+
+    /// The action's name in `keys.toml`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Fullscreen => "fullscreen",
+            Action::Hud => "hud",
+            Action::Panel => "panel",
+            Action::Minimap => "minimap",
+            Action::Inspect => "inspect",
+            Action::Console => "console",
+            Action::Bounds => "bounds",
+            Action::Save => "save",
+            Action::Load => "load",
+            Action::Record => "record",
+            Action::NextScene => "next_scene",
+            Action::Restart => "restart",
+            Action::ToggleEmitters => "toggle_emitters",
+            Action::Slower => "slower",
+            Action::Faster => "faster",
+            Action::Pause => "pause",
+            Action::Step => "step",
+            Action::Rewind => "rewind",
+            Action::NextMaterial => "next_material",
+            Action::NextTool => "next_tool",
+            Action::NextShape => "next_shape",
+            Action::Symmetry => "symmetry",
+            Action::Copy => "copy",
+            Action::Cut => "cut",
+            Action::Paste => "paste",
+            Action::Ctrl => "ctrl",
+            Action::ZoomIn => "zoom_in",
+            Action::ZoomOut => "zoom_out",
+            Action::PanUp => "pan_up",
+            Action::PanDown => "pan_down",
+            Action::PanLeft => "pan_left",
+            Action::PanRight => "pan_right",
+            Action::Gravity => "gravity",
+            Action::Flip => "flip",
+            Action::Quake => "quake",
+            Action::Emitter => "emitter",
+            Action::Drain => "drain",
+            Action::Portal => "portal",
+            Action::Attractor => "attractor",
+            Action::OpenDrains => "open_drains",
+            Action::Wall => "wall",
+            Action::Slot1 => "slot1",
+            Action::Slot2 => "slot2",
+            Action::Slot3 => "slot3",
+            Action::Slot4 => "slot4",
+            Action::Slot5 => "slot5",
+            Action::Slot6 => "slot6",
+            Action::Slot7 => "slot7",
+            Action::Slot8 => "slot8",
+            Action::Slot9 => "slot9",
+        }
+    }
+
+    /// The keys bound to the action when `keys.toml` does not say.
+    fn default_keys(self) -> &'static [Key] {
+        match self {
+            Action::Quit => &[Key::Escape],
+            Action::Fullscreen => &[Key::F11],
+            Action::Hud => &[Key::F1],
+            Action::Panel => &[Key::F2],
+            Action::Minimap => &[Key::F3],
+            Action::Inspect => &[Key::I],
+            Action::Console => &[Key::Backquote],
+            Action::Bounds => &[Key::B],
+            Action::Save => &[Key::S],
+            Action::Load => &[Key::L],
+            Action::Record => &[Key::R],
+            Action::NextScene => &[Key::Tab],
+            Action::Restart => &[Key::F5],
+            Action::ToggleEmitters => &[Key::T],
+            Action::Slower => &[Key::LeftBracket],
+            Action::Faster => &[Key::RightBracket],
+            Action::Pause => &[Key::P],
+            Action::Step => &[Key::N],
+            Action::Rewind => &[Key::Backspace],
+            Action::NextMaterial => &[Key::W],
+            Action::NextTool => &[Key::M],
+            Action::NextShape => &[Key::K],
+            Action::Symmetry => &[Key::V],
+            Action::Copy => &[Key::C],
+            Action::Cut => &[Key::X],
+            Action::Paste => &[Key::V],
+            Action::Ctrl => &[Key::LeftCtrl, Key::RightCtrl],
+            Action::ZoomIn => &[Key::Equal, Key::NumPadPlus],
+            Action::ZoomOut => &[Key::Minus, Key::NumPadMinus],
+            Action::PanUp => &[Key::Up],
+            Action::PanDown => &[Key::Down],
+            Action::PanLeft => &[Key::Left],
+            Action::PanRight => &[Key::Right],
+            Action::Gravity => &[Key::G],
+            Action::Flip => &[Key::F],
+            Action::Quake => &[Key::Q],
+            Action::Emitter => &[Key::E],
+            Action::Drain => &[Key::D],
+            Action::Portal => &[Key::O],
+            Action::Attractor => &[Key::A],
+            Action::OpenDrains => &[Key::Space],
+            Action::Wall => &[Key::LeftShift, Key::RightShift],
+            Action::Slot1 => &[Key::Key1],
+            Action::Slot2 => &[Key::Key2],
+            Action::Slot3 => &[Key::Key3],
+            Action::Slot4 => &[Key::Key4],
+            Action::Slot5 => &[Key::Key5],
+            Action::Slot6 => &[Key::Key6],
+            Action::Slot7 => &[Key::Key7],
+            Action::Slot8 => &[Key::Key8],
+            Action::Slot9 => &[Key::Key9],
+        }
+    }
+}
+
+/// Every key that can be bound, looked up by its minifb name.
+const KEYS: [Key; 106] = [
+    Key::Key0, Key::Key1, Key::Key2, Key::Key3, Key::Key4,
+    Key::Key5, Key::Key6, Key::Key7, Key::Key8, Key::Key9,
+    Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I,
+    Key::J, Key::K, Key::L, Key::M, Key::N, Key::O, Key::P, Key::Q, Key::R,
+    Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z,
+    Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8,
+    Key::F9, Key::F10, Key::F11, Key::F12, Key::F13, Key::F14, Key::F15,
+    Key::Down, Key::Left, Key::Right, Key::Up,
+    Key::Apostrophe, Key::Backquote, Key::Backslash, Key::Comma, Key::Equal,
+    Key::LeftBracket, Key::Minus, Key::Period, Key::RightBracket, Key::Semicolon,
+    Key::Slash, Key::Backspace, Key::Delete, Key::End, Key::Enter, Key::Escape,
+    Key::Home, Key::Insert, Key::Menu, Key::PageDown, Key::PageUp, Key::Pause,
+    Key::Space, Key::Tab, Key::NumLock, Key::CapsLock, Key::ScrollLock,
+    Key::LeftShift, Key::RightShift, Key::LeftCtrl, Key::RightCtrl,
+    Key::NumPad0, Key::NumPad1, Key::NumPad2, Key::NumPad3, Key::NumPad4,
+    Key::NumPad5, Key::NumPad6, Key::NumPad7, Key::NumPad8, Key::NumPad9,
+    Key::NumPadDot, Key::NumPadSlash, Key::NumPadAsterisk, Key::NumPadMinus,
+    Key::NumPadPlus, Key::NumPadEnter,
+    Key::LeftAlt, Key::RightAlt, Key::LeftSuper, Key::RightSuper,
+];
+
+/// The key called `name`, ignoring case: `Space`, `F2`, `LeftBracket`,
+/// `Key1` and so on.
+fn key_named(name: &str) -> Option<Key> {
+    KEYS.into_iter().find(|k| format!("{k:?}").eq_ignore_ascii_case(name))
+}
+
+/// An action's keys in `keys.toml`: one name, or a list of them.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Binding {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// The keys bound to each action.
+pub struct Keys {
+    bindings: Vec<Vec<Key>>,
+}
+
+impl Default for Keys {
+    fn default() -> Self {
+        Self { bindings: Action::ALL.iter().map(|a| a.default_keys().to_vec()).collect() }
+    }
+}
+
+impl Keys {
+    /// Read the bindings at `path` over the defaults, or just the defaults
+    /// if there is no such file.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(format!("{}: {e}", path.display())),
+        };
+        let table: HashMap<String, Binding> =
+            toml::from_str(&text).map_err(|e| format!("{}: {e}", path.display()))?;
+
+        let mut keys = Self::default();
+        for (name, binding) in table {
+            let Some(action) = Action::ALL.into_iter().find(|a| a.name() == name) else {
+                return Err(format!("{}: unknown action `{name}`", path.display()));
+            };
+            let names = match binding {
+                Binding::One(name) => vec![name],
+                Binding::Many(names) => names,
+            };
+            keys.bindings[action as usize] = names
+                .iter()
+                .map(|key| {
+                    key_named(key).ok_or_else(|| {
+                        format!("{}: unknown key `{key}` for `{name}`", path.display())
+                    })
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        Ok(keys)
+    }
+}
+
+/// Which actions' keys are down this frame and were last frame.
+pub struct Input {
+    keys: Keys,
+    down: [bool; Action::COUNT],
+    last: [bool; Action::COUNT],
+}
+
+impl Input {
+    pub fn new(keys: Keys) -> Self {
+        Self { keys, down: [false; Action::COUNT], last: [false; Action::COUNT] }
+    }
+
+    /// Read the keys from `window`, once at the start of every frame.
+    pub fn update(&mut self, window: &dyn Backend) {
+        self.last = self.down;
+        for (down, keys) in self.down.iter_mut().zip(&self.keys.bindings) {
+            *down = keys.iter().any(|&k| window.is_key_down(k));
+        }
+    }
+
+    /// True while any of `action`'s keys is held.
+    pub fn held(&self, action: Action) -> bool {
+        self.down[action as usize]
+    }
+
+    /// True while any bound key is held.
+    pub fn any(&self) -> bool {
+        self.down.contains(&true)
+    }
+
+    /// True on the frame one of `action`'s keys goes down.
+    pub fn pressed(&self, action: Action) -> bool {
+        self.down[action as usize] && !self.last[action as usize]
+    }
+
+    /// Treat `action`'s keys as up this frame, for an action the current
+    /// mode does not allow. Call after `update`.
+    pub fn block(&mut self, action: Action) {
+        self.down[action as usize] = false;
+    }
+}
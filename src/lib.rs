@@ -0,0 +1,49 @@
+//! Falling sand simulation core.
+//! by Rich from mathsDOTearth
+//!
+//! The physics lives here so it can be embedded in other programs or driven
+//! without a window; `main.rs` is a thin minifb frontend over [`World`], and
+//! with the `web` feature `web.rs` is one for the browser.
+
+mod attractors;
+pub mod brush;
+pub mod camera;
+pub mod chunks;
+pub mod climate;
+pub mod commands;
+#[cfg(feature = "compute")]
+pub mod compute;
+mod conveyors;
+pub mod events;
+mod explosions;
+mod fountains;
+pub mod grid;
+pub mod heat;
+pub mod import;
+pub mod input;
+pub mod material;
+pub mod persist;
+mod physics;
+mod portals;
+mod power;
+pub mod puzzle;
+#[cfg(feature = "pyo3")]
+pub mod python;
+mod reactions;
+pub mod render;
+pub mod rules;
+pub mod sandpile;
+pub mod scenes;
+#[cfg(feature = "rhai")]
+pub mod script;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "web")]
+pub mod web;
+pub mod weather;
+pub mod world;
+
+pub use events::Event;
+pub use input::InputState;
+pub use material::Material;
+pub use world::{Attractor, Drain, Emitter, Grain, Gravity, Portal, Region, World, Wrap};
@@ -3,177 +3,1288 @@
 
 extern crate minifb;
 
-use minifb::{Key, MouseButton, MouseMode, Window, WindowOptions};
 use rayon::prelude::*;
-use unirand::MarsagliaUniRng;
 
-mod render;
-use render::{buffer_to_u32_in_place, draw_pixel, draw_rect, Pixel};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-pub const WIDTH: usize = 1200;
-pub const HEIGHT: usize = 800;
+#[cfg(feature = "audio")]
+mod audio;
+mod backend;
+mod btw;
+mod cli;
+mod config;
+mod console;
+mod demo;
+#[cfg(feature = "egui")]
+mod egui_panel;
+#[cfg(feature = "wgpu")]
+mod gpu;
+mod graph;
+mod keys;
+mod minimap;
+#[cfg(not(feature = "egui"))]
+mod panel;
+mod pipe;
+mod record;
+mod rewind;
+mod tool;
+mod tui;
+#[cfg(any(feature = "softbuffer", feature = "wgpu"))]
+mod winit_window;
+#[cfg(feature = "egui")]
+use egui_panel as panel;
+use backend::{Backend, Key, MouseButton};
+use cli::{Args, Mode};
+use config::Config;
+use console::Console;
+use demo::Demo;
+use graph::FrameGraph;
+use keys::{Action, Input, Keys};
+use minimap::Minimap;
+use panel::Panel;
+use pipe::FramePipe;
+use record::Recorder;
+use rewind::Rewind;
+use tool::{ActiveTool, Kind, Symmetry};
 
-const SAND: Pixel = Pixel { r: 194, g: 178, b: 128, a: 255 };
-const SPAWN_RADIUS: usize = 16;
-const TRIES_PER_FRAME: usize = 25;
+use sandfall::brush::{Brush, Shape};
+use sandfall::camera::{self, Camera};
+use sandfall::chunks::{Rect, CHUNK_SIZE};
+use sandfall::climate::DayNight;
+use sandfall::grid::Grid;
+use sandfall::material::{shades, SHADES};
+use sandfall::puzzle::{Puzzle, Status};
+use sandfall::render::{
+    draw_circle, draw_circle_blend, draw_line, draw_line_aa, draw_rect, draw_rect_blend,
+    draw_square, draw_text, BlendMode, Pixel, GLYPH_HEIGHT, GLYPH_WIDTH,
+};
+use sandfall::scenes::SCENES;
+use sandfall::weather::Weather;
+use sandfall::{
+    commands, import, persist, rules, Attractor, Drain, Emitter, Gravity, InputState, Material,
+    Portal, World,
+};
 
-const DRAIN_X: usize = WIDTH / 2;
-const DRAIN_Y: usize = HEIGHT - 1;
-const DRAIN_HALF: usize = 50;
+/// Optional settings file read at startup.
+const CONFIG_PATH: &str = "sandfall.toml";
+/// Optional key bindings read at startup.
+const KEYS_PATH: &str = "keys.toml";
+/// Optional table of extra reactions read at startup.
+const REACTIONS_PATH: &str = "reactions.toml";
+/// Optional directory of Rhai scripts making up materials and reactions,
+/// run at startup.
+#[cfg(feature = "rhai")]
+const SCRIPTS_PATH: &str = "scripts";
 
-#[derive(Clone, Copy)]
-struct Grain {
-    x: usize,
-    y: usize,
+const MIN_SPAWN_RADIUS: usize = 1;
+const MAX_SPAWN_RADIUS: usize = 128;
+/// Range of grains a second the spray can be set to try to place.
+const MIN_SPAWN_RATE: usize = 10;
+const MAX_SPAWN_RATE: usize = 6000;
+/// Spray rate change per `Shift`+scroll notch.
+const SPAWN_RATE_STEP: f32 = 1.25;
+const WALL_RADIUS: usize = 4;
+
+/// How close, in cells, a click must be to a drain to remove it.
+const DRAIN_REACH: usize = 16;
+
+/// Grains per tick from a newly placed emitter.
+const EMITTER_RATE: f32 = 2.0;
+/// How close, in cells, a click must be to an emitter to remove it.
+const EMITTER_REACH: usize = 16;
+
+/// How close, in cells, a click must be to either end of a pair of portals
+/// to remove it.
+const PORTAL_REACH: usize = 16;
+
+/// Pull of a newly placed attractor one cell away, in cells per tick per
+/// tick: as strong as gravity about 14 cells out.
+const ATTRACTOR_STRENGTH: f32 = 40.0;
+/// How close, in cells, a click must be to an attractor to remove it.
+const ATTRACTOR_REACH: usize = 16;
+
+/// Fastest sideways jolt a grain gets each frame `Q` is held, in cells per
+/// tick.
+const QUAKE_SPEED: f32 = 1.5;
+
+/// Actions ignored while playing a puzzle, where only walls may be drawn.
+const PUZZLE_BLOCKED: [Action; 28] = [
+    Action::Console,
+    Action::Panel,
+    Action::Load,
+    Action::NextScene,
+    Action::ToggleEmitters,
+    Action::Rewind,
+    Action::NextMaterial,
+    Action::NextTool,
+    Action::Copy,
+    Action::Cut,
+    Action::Paste,
+    Action::Gravity,
+    Action::Flip,
+    Action::Quake,
+    Action::Emitter,
+    Action::Drain,
+    Action::Portal,
+    Action::Attractor,
+    Action::OpenDrains,
+    Action::Slot1,
+    Action::Slot2,
+    Action::Slot3,
+    Action::Slot4,
+    Action::Slot5,
+    Action::Slot6,
+    Action::Slot7,
+    Action::Slot8,
+    Action::Slot9,
+];
+
+/// Colour of the empty sky at noon with `--day-night`; at midnight it is
+/// black.
+const NOON_SKY: [u8; 3] = [40, 60, 90];
+/// Steps the sky's colour takes from midnight to noon. Every cell is
+/// repainted on each step, so there are few of them.
+const SKY_STEPS: f32 = 12.0;
+
+/// Physics ticks per second, independent of how fast the window refreshes.
+const TICK_RATE: f64 = 120.0;
+/// Most ticks run in one frame, so a slow frame cannot snowball into ever
+/// more catching up.
+const MAX_TICKS_PER_FRAME: u32 = 8;
+/// Simulation speeds `[` and `]` step through, as multiples of `TICK_RATE`.
+const SPEEDS: [f64; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+/// Index into `SPEEDS` of normal speed.
+const NORMAL_SPEED: usize = 2;
+
+/// Zoom change per key press or scroll notch.
+const ZOOM_STEP: f32 = 1.25;
+/// Screen pixels the arrow keys pan per frame.
+const PAN_SPEED: f32 = 12.0;
+
+const SAVE_PATH: &str = "sandfall.sav";
+
+/// The step of the sky's colour from black at midnight to `NOON_SKY` at
+/// noon, for a world with a day and night.
+fn sky_step(world: &World) -> Option<u32> {
+    let day_night = world.day_night()?;
+    let range = (day_night.high - day_night.low).abs().max(f32::EPSILON);
+    let t = ((world.ambient() - day_night.low.min(day_night.high)) / range).clamp(0.0, 1.0);
+    Some((t * SKY_STEPS).round() as u32)
+}
+
+/// Where the mouse is on `frame`, in frame pixels, allowing for the frame
+/// being scaled to fit the window. `None` while it is off the window or over
+/// a bar beside the frame.
+fn mouse_on_frame(window: &dyn Backend, frame: &Grid<u32>) -> Option<(f32, f32)> {
+    let (wx, wy) = window.mouse_pos()?;
+    camera::window_to_frame(window.size(), (frame.width(), frame.height()), wx, wy)
+}
+
+/// The world cell under the mouse. Everything the mouse draws or places
+/// goes through this, so it lands under the cursor whatever the window's
+/// size, the pixel scale or the zoom.
+fn mouse_cell(window: &dyn Backend, frame: &Grid<u32>, camera: &Camera) -> Option<(usize, usize)> {
+    let (sx, sy) = mouse_on_frame(window, frame)?;
+    Some(camera.cell_under(sx, sy))
+}
+
+/// Draw `lines` of text on a black panel in the top-left corner and return
+/// the area covered.
+fn draw_hud(buffer: &mut Grid<u32>, lines: &[String]) -> Rect {
+    draw_panel(buffer, 0, 0, lines)
+}
+
+/// Draw `lines` of text on a black panel with its top-left corner at
+/// `(x, y)`, moved left and up as far as it must be to fit, and return the
+/// area covered.
+fn draw_panel(buffer: &mut Grid<u32>, x: usize, y: usize, lines: &[String]) -> Rect {
+    const MARGIN: usize = 4;
+    let cols = lines.iter().map(|l| l.len()).max().unwrap_or(0);
+    let w = cols * GLYPH_WIDTH + 2 * MARGIN;
+    let h = lines.len() * GLYPH_HEIGHT + 2 * MARGIN;
+    let x = x.min(buffer.width().saturating_sub(w));
+    let y = y.min(buffer.height().saturating_sub(h));
+    draw_square(buffer, x, y, w, h, Pixel::new(0, 0, 0, 255));
+    let white = Pixel::new(255, 255, 255, 255);
+    for (i, line) in lines.iter().enumerate() {
+        draw_text(buffer, x + MARGIN, y + MARGIN + i * GLYPH_HEIGHT, line, white);
+    }
+    clip_rect(buffer, x as i32, y as i32, (x + w) as i32, (y + h) as i32)
+}
+
+/// What the `I` inspector shows about cell `(x, y)`: its material and
+/// temperature, and for a grain its velocity, what is left of its life or,
+/// for mud, how long until it dries, and whether it is asleep, then the
+/// chunk it is in and whether that is being simulated.
+fn inspect(world: &World, x: usize, y: usize) -> Vec<String> {
+    let material = world.cells().get(x, y);
+    let mut lines = vec![
+        format!("CELL {x},{y}"),
+        format!("MATERIAL {}", material.props().name),
+        format!("TEMP {:.1} C", world.heat().get(x, y)),
+    ];
+    match world.grain_at(x, y) {
+        Some(g) => {
+            lines.push(format!("VEL {:.2},{:.2}", g.vx, g.vy));
+            if g.material == Material::Mud {
+                lines.push(format!("WET {} TICKS", g.life));
+            } else if g.material.props().lifetime.is_some() {
+                lines.push(format!("LIFE {} TICKS", g.life));
+            }
+            let sleep = if g.is_asleep() { "ASLEEP" } else { "AWAKE" };
+            lines.push(format!("{sleep}, IDLE {}", g.idle));
+        }
+        None if material != Material::Empty => lines.push("STATIC".to_string()),
+        None => {}
+    }
+    let active = if world.chunks().is_active(x, y) { "ACTIVE" } else { "IDLE" };
+    lines.push(format!("CHUNK {},{} {active}", x / CHUNK_SIZE, y / CHUNK_SIZE));
+    lines
+}
+
+/// Clip the inclusive corners `(x0, y0)`-`(x1, y1)` to `buffer`.
+fn clip_rect<T: Copy>(buffer: &Grid<T>, x0: i32, y0: i32, x1: i32, y1: i32) -> Rect {
+    let (w, h) = (buffer.width() as i32, buffer.height() as i32);
+    let x0 = x0.clamp(0, w - 1) as usize;
+    let y0 = y0.clamp(0, h - 1) as usize;
+    let x1 = x1.clamp(0, w - 1) as usize;
+    let y1 = y1.clamp(0, h - 1) as usize;
+    (x0, y0, x1 - x0 + 1, y1 - y0 + 1)
+}
+
+/// Window title naming the tool in use.
+fn title(tool: &ActiveTool, brush: &Brush) -> String {
+    let name = tool.material().props().name;
+    let mut title = match tool.kind() {
+        Kind::Brush => format!("Sand - {name} {} brush", brush.shape.name()),
+        kind => format!("Sand - {name} {}", kind.name()),
+    };
+    if tool.symmetry() != Symmetry::None {
+        title.push_str(&format!(", {} symmetry", tool.symmetry().name()));
+    }
+    title
+}
+
+/// Stop `pipe` and report how many frames went through it.
+fn finish_pipe(pipe: FramePipe) {
+    match pipe.finish() {
+        Ok(frames) => eprintln!("Piped {frames} frames"),
+        Err(e) => eprintln!("Stopped piping frames: {e}"),
+    }
+}
+
+/// Stop `rec` and report where the GIF went.
+fn finish_recording(rec: Recorder) {
+    let path = rec.path().display().to_string();
+    match rec.finish() {
+        Ok(frames) => eprintln!("Wrote {frames} frames to {path}"),
+        Err(e) => eprintln!("Failed to write {path}: {e}"),
+    }
 }
 
 fn main() {
-    let mut window = Window::new("Sand", WIDTH, HEIGHT, WindowOptions::default())
-        .expect("Unable to create window");
+    let args = Args::parse(std::env::args()).unwrap_or_else(|e| {
+        // An empty error means help was asked for.
+        if e.is_empty() {
+            println!("{}", cli::USAGE);
+            process::exit(0);
+        }
+        eprintln!("sandfall: {e}\n{}", cli::USAGE);
+        process::exit(2);
+    });
+    let config = Config::load(Path::new(CONFIG_PATH)).unwrap_or_else(|e| {
+        eprintln!("sandfall: {e}");
+        process::exit(2);
+    });
+    let keys = Keys::load(Path::new(KEYS_PATH)).unwrap_or_else(|e| {
+        eprintln!("sandfall: {e}");
+        process::exit(2);
+    });
+    // Scripts go first, so the reaction table can name the materials they
+    // make up.
+    #[cfg(feature = "rhai")]
+    let scripted = sandfall::script::load(Path::new(SCRIPTS_PATH)).unwrap_or_else(|e| {
+        eprintln!("sandfall: {e}");
+        process::exit(2);
+    });
+    let reactions = rules::load(Path::new(REACTIONS_PATH)).unwrap_or_else(|e| {
+        eprintln!("sandfall: {e}");
+        process::exit(2);
+    });
+    #[cfg(feature = "rhai")]
+    let reactions = [scripted, reactions].concat();
+    let (width, height) = (config.width, config.height);
+    // The frame is drawn this much smaller than the window.
+    let scale = config.pixel_scale;
+    let (frame_w, frame_h) = ((width / scale).max(1), (height / scale).max(1));
+
+    if args.mode == Mode::Btw {
+        btw::run(width, height);
+        return;
+    }
+
+    // Messages go to stderr, leaving stdout to `--pipe-frames`.
+    let seed = args.seed.unwrap_or_else(|| {
+        let seed = cli::random_seed();
+        eprintln!("Using random seed {seed}");
+        seed
+    });
+
+    if args.tui {
+        tui::run(seed, args.scene);
+        return;
+    }
 
-    let mut pixel_buffer = vec![vec![Pixel::new(0, 0, 0, 255); WIDTH]; HEIGHT];
-    let mut flat_buffer = vec![0u32; WIDTH * HEIGHT];
+    let mut window = backend::open("Sand", width, height, true);
+    let mut input = Input::new(keys);
+    // F11 swaps the window for a borderless one the size of the desktop.
+    let mut fullscreen = false;
+
+    // The frame is drawn straight into 0xAARRGGBB words for minifb. It is
+    // the size of the window, or `pixel_scale` times smaller, and may be
+    // resized away from the world's.
+    let black = Pixel::new(0, 0, 0, 255).to_u32();
+    let mut frame = Grid::new(frame_w, frame_h, black);
+    // Every shade of every material, indexed by `material * SHADES + shade`.
+    let [r, g, b] = config.sand_color;
+    let mut palette: Vec<u32> = Material::ALL
+        .iter()
+        .flat_map(|&m| match m {
+            Material::Sand => shades(Pixel::new(r, g, b, 255), m.props().shade),
+            _ => m.color_variants(),
+        })
+        .map(|p| p.to_u32())
+        .collect();
+
+    // The world is the size of the window unless `--world` says otherwise;
+    // the camera shows the part of it that fits, and only that is drawn.
+    let (world_w, world_h) = args.world.unwrap_or((frame_w, frame_h));
+    let mut world = World::new(world_w, world_h, seed);
+    let drain_x = config.drain_x.unwrap_or(world_w / 2).min(world_w - 1);
+    world.add_drain(Drain { x: drain_x, y: world_h - 1, half_width: config.drain_half_width });
+    world.set_rules(reactions);
+    world.set_wrap(args.wrap);
+    world.set_weather(args.weather.map(|precipitation| Weather {
+        precipitation,
+        rate: config.weather_rate,
+        wind: config.wind,
+    }));
+    if args.day_night {
+        world.set_day_night(Some(DayNight {
+            day_ticks: (config.day_length_secs as f64 * TICK_RATE).max(1.0) as u64,
+            low: config.night_temperature,
+            high: config.day_temperature,
+        }));
+    }
+    if let Some(scene) = args.scene {
+        scene.apply(&mut world);
+    }
+    // `--puzzle` lays out its own world, and only walls may be drawn in it.
+    let mut puzzle = args.puzzle.map(|scene| Puzzle::start(scene, &mut world));
+    if let Some(path) = &args.image
+        && let Err(e) = import::load_png(&mut world, path)
+    {
+        eprintln!("sandfall: failed to load {}: {e}", path.display());
+        process::exit(1);
+    }
+    if let Some(path) = &args.walls
+        && let Err(e) = import::load_walls(&mut world, path)
+    {
+        eprintln!("sandfall: failed to load walls {}: {e}", path.display());
+        process::exit(1);
+    }
+    if let Some(path) = &args.script {
+        let ran = fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|script| commands::run_script(&mut world, &script));
+        if let Err(e) = ran {
+            eprintln!("sandfall: failed to run script {}: {e}", path.display());
+            process::exit(1);
+        }
+    }
+    // `--serve` answers requests from other programs between frames.
+    #[cfg(feature = "server")]
+    let mut server = args.serve.map(|port| {
+        let server = sandfall::server::Server::start(port).unwrap_or_else(|e| {
+            eprintln!("sandfall: failed to listen on port {port}: {e}");
+            process::exit(1);
+        });
+        eprintln!("Taking requests on {}", server.addr());
+        server
+    });
+    #[cfg(not(feature = "server"))]
+    if args.serve.is_some() {
+        eprintln!("sandfall: --serve needs sandfall built with the server feature");
+        process::exit(2);
+    }
+    // Playing for as long as it is kept; no sound device just means quiet.
+    #[cfg(feature = "audio")]
+    let sound = audio::Sound::start(&mut world)
+        .map_err(|e| eprintln!("sandfall: no sound: {e}"))
+        .ok();
 
-    let mut grid = vec![vec![false; WIDTH]; HEIGHT];
-    let mut grains = Vec::<Grain>::new();
+    // Tab steps through the built-in scenes.
+    let mut scene_index = args.scene.and_then(|s| SCENES.iter().position(|t| t.name == s.name));
 
-    let mut min_x = WIDTH;
-    let mut max_x = 0;
-    let mut min_y = HEIGHT;
-    let mut max_y = 0;
+    let mut camera = Camera::new(frame_w, frame_h, world.width(), world.height());
+    // Where the middle button was last frame while dragging the view.
+    let mut drag_from: Option<(f32, f32)> = None;
+
+    // Areas drawn over by overlays, repainted from the grid next frame.
+    let mut overlay_rects = Vec::<Rect>::new();
+    // The sky's colour step last drawn, with a day and night.
+    let mut last_sky = None;
 
     let mut show_bounds = false;
-    let mut last_b_state = false;
 
-    let mut rng = MarsagliaUniRng::new();
-    rng.rinit(170);
+    let mut recorder: Option<Recorder> = None;
+    // `--pipe-frames` streams every frame to stdout from the start.
+    let mut pipe = args.pipe_frames.then(|| {
+        eprintln!(
+            "Piping {frame_w}x{frame_h} RGBA frames at {} a second to stdout",
+            pipe::FPS
+        );
+        FramePipe::start(frame_w, frame_h)
+    });
 
-    let in_bounds = |x: isize, y: isize| {
-        x >= 0 && y >= 0 && (x as usize) < WIDTH && (y as usize) < HEIGHT
-    };
+    let mut show_hud = false;
+    // I shows what is in the cell under the cursor beside it.
+    let mut show_inspector = false;
+    // ` opens the console along the bottom.
+    let mut console = Console::default();
+    // F2 shows the settings panel down the right-hand side.
+    let mut show_panel = false;
+    let mut panel = Panel::new();
+    // F3 shows the minimap, at first only if the world does not fit.
+    let mut minimap = Minimap::new(world.width(), world.height());
+    let mut show_minimap = world.width() > width || world.height() > height;
+    // Smoothed frame and physics times for the HUD.
+    let mut frame_time = Duration::ZERO;
+    let mut physics_time = Duration::ZERO;
+    let mut last_frame = Instant::now();
+    // Every recent frame's time, and the physics part of the last one, for
+    // the graph under the HUD.
+    let mut graph = FrameGraph::default();
+    let mut frame_physics = Duration::ZERO;
+    // Time not yet simulated, in seconds.
+    let mut tick_accumulator = 0.0;
+    let tick_secs = 1.0 / TICK_RATE;
+
+    let mut paused = false;
+    // `--demo` runs the world by itself once it has been left alone.
+    let mut demo = args.demo.then(|| Demo::new(Duration::from_secs(config.demo_idle_secs)));
+    let mut last_mouse = None;
+    // Snapshots of the last few seconds, run back through while the rewind
+    // key is held.
+    let mut rewind = Rewind::default();
+    let mut speed = NORMAL_SPEED;
+
+    let mut brush = Brush::new(
+        config.spawn_radius.clamp(MIN_SPAWN_RADIUS, MAX_SPAWN_RADIUS),
+        config.spawn_rate.clamp(MIN_SPAWN_RATE, MAX_SPAWN_RATE),
+    );
+    let mut tool =
+        ActiveTool::new(if puzzle.is_some() { Material::Wall } else { Material::Sand });
+    if let Some(path) = &args.stamp {
+        match import::load_stamp(path) {
+            Ok(stamp) => tool.set_stamp(stamp),
+            Err(e) => {
+                eprintln!("sandfall: failed to load stamp {}: {e}", path.display());
+                process::exit(1);
+            }
+        }
+    }
+    window.set_title(&title(&tool, &brush));
+    let mut last_left_state = false;
+    let mut last_right_state = false;
+    // One end of a pair of portals, waiting for the other to be placed.
+    let mut portal_start: Option<(usize, usize)> = None;
+
+    while window.is_open() {
+        input.update(&*window);
+        if puzzle.is_some() {
+            for action in PUZZLE_BLOCKED {
+                input.block(action);
+            }
+        }
+        // While the console is open, keys type into it and do nothing else.
+        if input.pressed(Action::Console) {
+            console.toggle();
+        } else if console.is_open() {
+            let shift = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
+            console.type_keys(&window.keys_pressed(), shift, &mut world);
+        }
+        if console.is_open() {
+            for action in Action::ALL.into_iter().filter(|&a| a != Action::Console) {
+                input.block(action);
+            }
+        }
+        if input.held(Action::Quit) {
+            break;
+        }
+        let now = Instant::now();
+        let frame_dt = now - last_frame;
+        frame_time = (frame_time * 15 + frame_dt) / 16;
+        last_frame = now;
+        graph.push(frame_dt, frame_physics);
+        frame_physics = Duration::ZERO;
 
-    while window.is_open() && !window.is_key_down(Key::Escape) {
-        let b_down = window.is_key_down(Key::B);
-        if b_down && !last_b_state {
+        if input.pressed(Action::Bounds) {
             show_bounds = !show_bounds;
         }
-        last_b_state = b_down;
-
-        // 1. spawn
-        if window.get_mouse_down(MouseButton::Left) {
-            if let Some((mx, my)) = window.get_mouse_pos(MouseMode::Discard) {
-                let (cx, cy) = (mx as isize, my as isize);
-                for _ in 0..TRIES_PER_FRAME {
-                    loop {
-                        let span = (2 * SPAWN_RADIUS + 1) as f32;
-                        let dx = (rng.uni() * span).floor() as isize - SPAWN_RADIUS as isize;
-                        let dy = (rng.uni() * span).floor() as isize - SPAWN_RADIUS as isize;
-                        if dx * dx + dy * dy > (SPAWN_RADIUS * SPAWN_RADIUS) as isize {
-                            continue;
-                        }
-                        let (x, y) = (cx + dx, cy + dy);
-                        if in_bounds(x, y) && !grid[y as usize][x as usize] {
-                            let (xu, yu) = (x as usize, y as usize);
-                            grid[yu][xu] = true;
-                            grains.push(Grain { x: xu, y: yu });
-
-                            if xu < min_x { min_x = xu; }
-                            if xu > max_x { max_x = xu; }
-                            if yu < min_y { min_y = yu; }
-                            if yu > max_y { max_y = yu; }
+
+        if input.pressed(Action::Fullscreen) {
+            fullscreen = !fullscreen;
+            let [fw, fh] = config.fullscreen_size;
+            window.set_fullscreen(fullscreen, (fw, fh));
+        }
+
+        if input.pressed(Action::Hud) {
+            show_hud = !show_hud;
+        }
+
+        if input.pressed(Action::Inspect) {
+            show_inspector = !show_inspector;
+        }
+
+        if input.pressed(Action::Panel) {
+            show_panel = !show_panel;
+        }
+
+        if input.pressed(Action::Minimap) {
+            show_minimap = !show_minimap;
+        }
+
+        if input.pressed(Action::Save) {
+            match persist::save(&world, Path::new(SAVE_PATH)) {
+                Ok(()) => eprintln!("Saved world to {SAVE_PATH}"),
+                Err(e) => eprintln!("Failed to save {SAVE_PATH}: {e}"),
+            }
+        }
+
+        if input.pressed(Action::Load) {
+            match persist::load(&mut world, Path::new(SAVE_PATH)) {
+                Ok(()) => eprintln!("Loaded world from {SAVE_PATH}"),
+                Err(e) => eprintln!("Failed to load {SAVE_PATH}: {e}"),
+            }
+        }
+
+        // R starts recording a GIF, and stops and writes it.
+        if input.pressed(Action::Record) {
+            match recorder.take() {
+                Some(rec) => finish_recording(rec),
+                None => {
+                    let secs = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map_or(0, |d| d.as_secs());
+                    let path = PathBuf::from(format!("sandfall-{secs}.gif"));
+                    match Recorder::start(&path, frame.width(), frame.height()) {
+                        Ok(rec) => {
+                            eprintln!("Recording to {}", path.display());
+                            recorder = Some(rec);
                         }
-                        break;
+                        Err(e) => eprintln!("Failed to record to {}: {e}", path.display()),
                     }
                 }
             }
         }
 
-        // 2. physics update
-        let mut new_min_x = WIDTH;
-        let mut new_max_x = 0;
-        let mut new_min_y = HEIGHT;
-        let mut new_max_y = 0;
+        if input.pressed(Action::NextScene) {
+            let next = scene_index.map_or(0, |i| (i + 1) % SCENES.len());
+            SCENES[next].apply(&mut world);
+            eprintln!("Loaded scene {}", SCENES[next].name);
+            scene_index = Some(next);
+        }
 
-        for idx in (0..grains.len()).rev() {
-            let Grain { mut x, mut y } = grains[idx];
+        // F5 lays the puzzle, or the last scene, out again.
+        if input.pressed(Action::Restart) {
+            if let Some(puzzle) = &mut puzzle {
+                puzzle.restart(&mut world);
+                eprintln!("Restarted puzzle {}", puzzle.name());
+            } else if let Some(i) = scene_index {
+                SCENES[i].apply(&mut world);
+                eprintln!("Restarted scene {}", SCENES[i].name);
+            }
+        }
 
-            if x < min_x || x > max_x || y < min_y || y > max_y {
-                continue;
+        // T switches every emitter off, or back on if they all were off.
+        if input.pressed(Action::ToggleEmitters) {
+            let on = !world.emitters().iter().any(|e| e.enabled);
+            for e in world.emitters_mut() {
+                e.enabled = on;
             }
+        }
 
-            for (nx, ny) in [
-                (x as isize, y as isize + 1),
-                (x as isize - 1, y as isize + 1),
-                (x as isize + 1, y as isize + 1),
-            ] {
-                if in_bounds(nx, ny) && !grid[ny as usize][nx as usize] {
-                    grid[y][x] = false;
-                    x = nx as usize;
-                    y = ny as usize;
-                    grid[y][x] = true;
-                    grains[idx] = Grain { x, y };
+        // [ and ] slow the simulation down and speed it up.
+        if input.pressed(Action::Slower) {
+            speed = speed.saturating_sub(1);
+        }
+        if input.pressed(Action::Faster) {
+            speed = (speed + 1).min(SPEEDS.len() - 1);
+        }
 
-                    if x < new_min_x { new_min_x = x; }
-                    if x > new_max_x { new_max_x = x; }
-                    if y < new_min_y { new_min_y = y; }
-                    if y > new_max_y { new_max_y = y; }
+        if input.pressed(Action::Pause) {
+            paused = !paused;
+        }
 
-                    break;
-                }
+        // N advances a single tick while paused.
+        let single_step = paused && input.pressed(Action::Step);
+
+        if input.pressed(Action::NextMaterial) {
+            tool.cycle();
+            window.set_title(&title(&tool, &brush));
+        }
+
+        // M switches between the brush and the line tool.
+        if input.pressed(Action::NextTool) {
+            tool.cycle_kind();
+            window.set_title(&title(&tool, &brush));
+        }
+
+        // K switches the brush between spray, disc, square and pencil.
+        if input.pressed(Action::NextShape) {
+            brush.cycle_shape();
+            window.set_title(&title(&tool, &brush));
+        }
+
+        // Ctrl+C, Ctrl+X and Ctrl+V copy, cut and paste the selection; V on
+        // its own steps through mirroring what is drawn left to right, top
+        // to bottom, both, or not at all.
+        let ctrl = input.held(Action::Ctrl);
+        if ctrl && input.pressed(Action::Copy) {
+            tool.copy(&world);
+        }
+        if ctrl && input.pressed(Action::Cut) {
+            tool.cut(&mut world);
+        }
+        if ctrl && input.pressed(Action::Paste) {
+            if let Some((x, y)) = mouse_cell(&*window, &frame, &camera) {
+                tool.paste(&mut world, x, y);
             }
+        } else if !ctrl && input.pressed(Action::Symmetry) {
+            tool.cycle_symmetry();
+            window.set_title(&title(&tool, &brush));
         }
 
-        if new_min_x <= new_max_x && new_min_y <= new_max_y {
-            min_x = new_min_x.saturating_sub(2);
-            max_x = (new_max_x + 2).min(WIDTH - 1);
-            min_y = new_min_y.saturating_sub(2);
-            max_y = (new_max_y + 2).min(HEIGHT - 1);
+        // 1 to 9 pick a material directly.
+        for (i, slot) in Action::SLOTS.into_iter().enumerate() {
+            if input.pressed(slot) && tool.select(i + 1) {
+                window.set_title(&title(&tool, &brush));
+            }
         }
 
-        // 3. drain
-        if window.is_key_down(Key::Space) {
-            let start = DRAIN_X.saturating_sub(DRAIN_HALF);
-            let end = (DRAIN_X + DRAIN_HALF).min(WIDTH - 1);
+        // Camera: +/- or Ctrl+scroll zoom, arrows or middle-drag pan.
+        let last_camera = camera;
 
-            for x in start..=end {
-                grid[DRAIN_Y][x] = false;
+        // A resized window gets a new frame, and the camera scales the world
+        // to fill it; the changed camera then repaints the whole frame.
+        let (window_w, window_h) = window.size();
+        let (view_w, view_h) = (window_w / scale, window_h / scale);
+        if (view_w, view_h) != (frame.width(), frame.height()) && view_w > 0 && view_h > 0 {
+            frame = Grid::new(view_w, view_h, black);
+            camera.resize(view_w, view_h);
+            overlay_rects.clear();
+            // The GIF is the size of the window it started in.
+            if let Some(rec) = recorder.take() {
+                finish_recording(rec);
             }
+        }
+        let (view_w, view_h) = (frame.width(), frame.height());
 
-            grains.retain(|g| {
-                let inside = g.y == DRAIN_Y && g.x >= start && g.x <= end;
-                if inside {
-                    if g.x < min_x { min_x = g.x; }
-                    if g.x > max_x { max_x = g.x; }
-                    if g.y < min_y { min_y = g.y; }
-                    if g.y > max_y { max_y = g.y; }
+        let mouse = mouse_on_frame(&*window, &frame);
+        if let Some(demo) = demo.as_mut() {
+            let buttons = [MouseButton::Left, MouseButton::Middle, MouseButton::Right];
+            let touched = input.any()
+                || console.is_open()
+                || mouse != last_mouse
+                || window.scroll_wheel().is_some()
+                || buttons.iter().any(|&b| window.is_mouse_down(b));
+            demo.update(touched, &mut world);
+        }
+        last_mouse = mouse;
+        let (zx, zy) = mouse.unwrap_or((view_w as f32 / 2.0, view_h as f32 / 2.0));
+        if input.pressed(Action::ZoomIn) {
+            camera.zoom_at(zx, zy, ZOOM_STEP);
+        }
+        if input.pressed(Action::ZoomOut) {
+            camera.zoom_at(zx, zy, 1.0 / ZOOM_STEP);
+        }
+
+        if let Some((_, scroll_y)) = window.scroll_wheel() {
+            if ctrl {
+                camera.zoom_at(zx, zy, ZOOM_STEP.powf(scroll_y.signum()));
+            } else if input.held(Action::Wall) {
+                // Shift and scroll speeds the spray up or slows it down.
+                let rate = brush.rate as f32 * SPAWN_RATE_STEP.powf(scroll_y.signum());
+                brush.rate = (rate.round() as usize).clamp(MIN_SPAWN_RATE, MAX_SPAWN_RATE);
+            } else {
+                // Plain scroll resizes the brush, one cell per notch.
+                let steps = scroll_y.signum() as isize;
+                brush.radius = (brush.radius as isize + steps)
+                    .clamp(MIN_SPAWN_RADIUS as isize, MAX_SPAWN_RADIUS as isize)
+                    as usize;
+            }
+        }
+
+        // F turns gravity round, so an hourglass runs the other way.
+        if input.pressed(Action::Flip) {
+            world.set_gravity(world.gravity().opposite());
+        }
+
+        // Q shakes everything sideways for as long as it is held.
+        if input.held(Action::Quake) {
+            world.shake(QUAKE_SPEED);
+        }
+
+        // With G held the arrow keys point gravity instead of panning.
+        if input.held(Action::Gravity) {
+            let arrows = [
+                (Action::PanDown, Gravity::Down),
+                (Action::PanLeft, Gravity::Left),
+                (Action::PanUp, Gravity::Up),
+                (Action::PanRight, Gravity::Right),
+            ];
+            if let Some(&(_, gravity)) = arrows.iter().find(|(a, _)| input.held(*a)) {
+                world.set_gravity(gravity);
+            }
+        } else {
+            let pan_x = input.held(Action::PanRight) as i32 - input.held(Action::PanLeft) as i32;
+            let pan_y = input.held(Action::PanDown) as i32 - input.held(Action::PanUp) as i32;
+            camera.pan(pan_x as f32 * PAN_SPEED, pan_y as f32 * PAN_SPEED);
+        }
+
+        drag_from = match (window.is_mouse_down(MouseButton::Middle), mouse, drag_from) {
+            (true, Some((mx, my)), Some((fx, fy))) => {
+                camera.pan(fx - mx, fy - my);
+                Some((mx, my))
+            }
+            (true, pos, None) => pos,
+            (true, None, from) => from,
+            (false, _, _) => None,
+        };
+        // Holding the left button on the minimap moves the view there.
+        if show_minimap
+            && window.is_mouse_down(MouseButton::Left)
+            && let Some((mx, my)) = mouse
+            && let Some((wx, wy)) = minimap.world_at(view_h, mx, my)
+        {
+            camera.look_at(wx, wy);
+        }
+        let camera_moved = camera != last_camera;
+
+        let left_down = window.is_mouse_down(MouseButton::Left);
+        let right_down = window.is_mouse_down(MouseButton::Right);
+        let left_click = left_down && !last_left_state;
+        let right_click = right_down && !last_right_state;
+        let left_release = !left_down && last_left_state;
+        last_left_state = left_down;
+        last_right_state = right_down;
+
+        // The panel and minimap take the mouse while it is over them.
+        let over_panel = mouse.is_some_and(|(x, y)| {
+            (show_panel && panel::contains(view_w, view_h, x, y))
+                || (show_minimap && minimap.world_at(view_h, x, y).is_some())
+        });
+
+        // 1. spawn and erase, or with D or E held place (left) and remove
+        //    (right) drains or emitters. The tools draw straight into the
+        //    world; plain erasing and the drains are handed to the ticks.
+        let mut held = InputState { radius: brush.radius, ..InputState::NONE };
+        if let Some((mx, my)) = mouse_cell(&*window, &frame, &camera)
+            && !over_panel
+        {
+            if input.held(Action::Emitter) {
+                // Emitters pour the selected material, which must be one
+                // that moves.
+                if left_click && tool.material().is_movable() {
+                    world.add_emitter(Emitter {
+                        x: mx,
+                        y: my,
+                        radius: brush.radius,
+                        rate: EMITTER_RATE,
+                        material: tool.material(),
+                        enabled: true,
+                    });
                 }
-                !inside
-            });
+                if right_click {
+                    world.remove_emitter_near(mx, my, EMITTER_REACH);
+                }
+            } else if input.held(Action::Attractor) {
+                // With Shift held the new one pushes grains away instead.
+                if left_click {
+                    let sign = if input.held(Action::Wall) { -1.0 } else { 1.0 };
+                    let strength = sign * ATTRACTOR_STRENGTH;
+                    world.add_attractor(Attractor { x: mx, y: my, strength });
+                }
+                if right_click {
+                    world.remove_attractor_near(mx, my, ATTRACTOR_REACH);
+                }
+            } else if input.held(Action::Portal) {
+                // The first click places one end and the second the other.
+                if left_click {
+                    match portal_start.take() {
+                        Some(a) => {
+                            world.add_portal(Portal { a, b: (mx, my), radius: brush.radius });
+                        }
+                        None => portal_start = Some((mx, my)),
+                    }
+                }
+                if right_click {
+                    portal_start = None;
+                    world.remove_portal_near(mx, my, PORTAL_REACH);
+                }
+            } else if input.held(Action::Drain) {
+                if left_click {
+                    let y = world.floor_below(mx, my);
+                    world.add_drain(Drain { x: mx, y, half_width: brush.radius });
+                }
+                if right_click {
+                    world.remove_drain_near(mx, my, DRAIN_REACH);
+                }
+            } else {
+                if left_down {
+                    brush.advance(frame_dt.as_secs_f32());
+                    tool.hold(&mut world, mx, my, &brush);
+                } else if left_release {
+                    tool.release(&mut world, mx, my);
+                }
+                if right_down {
+                    if input.held(Action::Wall) {
+                        world.paint(mx as isize, my as isize, WALL_RADIUS, Material::Wall);
+                    } else if puzzle.is_none() {
+                        held.mouse = Some((mx, my));
+                        held.right = true;
+                    }
+                }
+            }
+        }
+        // A line let go of off the window, or with E or D held, is dropped.
+        if !left_down {
+            tool.cancel();
         }
 
-        // 4. clear and draw
-        // Parallel clear is now safe!
-        pixel_buffer.par_iter_mut().for_each(|row| {
-                row.fill(Pixel::new(0, 0, 0, 255));
-            });
+        #[cfg(feature = "server")]
+        if let Some(server) = &mut server {
+            server.poll(&mut world);
+        }
 
-            // Drawing is kept serial to avoid mutable aliasing
-            for g in &grains {
-                draw_pixel(&mut pixel_buffer, g.x, g.y, SAND);
+        // 2. physics update and drain, at a fixed rate, or a snapshot back
+        //    each frame while rewinding
+        let rewinding = input.held(Action::Rewind);
+        if rewinding {
+            rewind.step_back(&mut world);
+        }
+        let ticks = if rewinding {
+            tick_accumulator = 0.0;
+            0
+        } else if paused {
+            tick_accumulator = 0.0;
+            single_step as u32
+        } else {
+            // Faster speeds run more ticks per frame, and are allowed more
+            // before the frame gives up catching up; slower ones skip frames.
+            let multiplier = SPEEDS[speed];
+            let max_ticks = (MAX_TICKS_PER_FRAME as f64 * multiplier.max(1.0)) as u32;
+            tick_accumulator += frame_dt.as_secs_f64() * multiplier;
+            let due = (tick_accumulator / tick_secs) as u32;
+            tick_accumulator -= due as f64 * tick_secs;
+            if due > max_ticks {
+                tick_accumulator = 0.0;
             }
+            due.min(max_ticks)
+        };
+        let drain_open = input.held(Action::OpenDrains);
+        if ticks == 0 {
+            world.apply_input(&held);
+        }
+        for _ in 0..ticks {
+            // The demo pours before the tick and opens the drains now and then.
+            let demo_drain = demo.as_mut().is_some_and(|d| d.tick(&mut world));
+            held.drain = drain_open || demo_drain;
+            let start = Instant::now();
+            world.step_parallel(&held);
+            let elapsed = start.elapsed();
+            physics_time = (physics_time * 15 + elapsed) / 16;
+            frame_physics += elapsed;
+            // The eraser acts once a frame, like the rest of the mouse.
+            held.right = false;
+            if let Some(puzzle) = &mut puzzle {
+                let before = puzzle.status();
+                match puzzle.tick(&mut world) {
+                    status if status == before => {}
+                    Status::Solved => eprintln!("Solved puzzle {}", puzzle.name()),
+                    Status::Failed => eprintln!("Failed puzzle {}", puzzle.name()),
+                    Status::Playing => {}
+                }
+            }
+            rewind.record(&world);
+            if args.verify
+                && let Err(problems) = world.verify()
+            {
+                panic!("grid and grains disagree after tick {}:\n{problems}", world.ticks());
+            }
+        }
+        // The whoosh plays while the drains are open and the world running.
+        #[cfg(feature = "audio")]
+        if let Some(sound) = &sound {
+            sound.set_drain((drain_open || held.drain) && !paused);
+        }
+        // `--auto-flip` turns the world over once the last grain has fallen
+        // past the middle, so the hourglass never runs out.
+        if args.auto_flip && ticks > 0 && !world.grains().is_empty() && world.grains_uphill() == 0 {
+            world.set_gravity(world.gravity().opposite());
+        }
+
+        // 4. redraw changed cells
+        // With a day and night the empty cells take the sky's colour, and
+        // everything is repainted when it changes.
+        let sky = sky_step(&world);
+        let sky_changed = sky != last_sky;
+        last_sky = sky;
+        if sky_changed && let Some(step) = sky {
+            let t = step as f32 / SKY_STEPS;
+            let [r, g, b] = NOON_SKY.map(|c| (c as f32 * t) as u8);
+            let empty = Material::Empty as usize * SHADES;
+            for (px, p) in palette[empty..empty + SHADES]
+                .iter_mut()
+                .zip(shades(Pixel::new(r, g, b, 255), Material::Empty.props().shade))
+            {
+                *px = p.to_u32();
+            }
+        }
+        // Only the box around the cells that changed in each chunk, plus
+        // wherever overlays were drawn last frame, is repainted from the
+        // grid; everything is when the camera moved. Rows are independent so
+        // this runs in parallel.
+        let mut rects: Vec<Rect> = if camera_moved || sky_changed {
+            overlay_rects.clear();
+            vec![(0, 0, view_w, view_h)]
+        } else {
+            world
+                .chunks()
+                .redraw_rects()
+                .filter_map(|r| camera.world_rect_to_screen(r))
+                .collect()
+        };
+        rects.append(&mut overlay_rects);
+        let cells = world.cells();
+        frame
+            .as_mut_slice()
+            .par_chunks_mut(view_w)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for &(rx, ry, rw, rh) in &rects {
+                    if y >= ry && y < ry + rh {
+                        for (x, px) in row.iter_mut().enumerate().skip(rx).take(rw) {
+                            let (cx, cy) = camera.cell_at(x, y);
+                            let shade = world.shade_at(cx, cy) as usize;
+                            *px = palette[cells.get(cx, cy) as usize * SHADES + shade];
+                        }
+                    }
+                }
+            });
+        // The boxes of cells that changed, kept for the bounds overlay.
+        let dirty: Vec<Rect> = if show_bounds {
+            world.chunks().redraw_rects().filter_map(|r| camera.world_rect_to_screen(r)).collect()
+        } else {
+            Vec::new()
+        };
+        world.clear_redraw();
+
+        // Recordings show the world without any of the overlays.
+        if let Some(rec) = recorder.as_mut() {
+            rec.capture(&frame);
+        }
+        // The pipe is closed when whatever was reading it stops.
+        if pipe.as_mut().is_some_and(|p| !p.capture(&frame))
+            && let Some(p) = pipe.take()
+        {
+            finish_pipe(p);
+        }
 
         if show_bounds {
-            let box_x = min_x as i32;
-            let box_y = min_y as i32;
-            let box_w = (max_x.saturating_sub(min_x)) as i32;
-            let box_h = (max_y.saturating_sub(min_y)) as i32;
-            let red = Pixel { r: 255, g: 0, b: 0, a: 255 };
-            draw_rect(&mut pixel_buffer, box_x, box_y, box_w, box_h, red);
-        }
-
-        buffer_to_u32_in_place(&pixel_buffer, &mut flat_buffer);
-        window
-            .update_with_buffer(&flat_buffer, WIDTH, HEIGHT)
-            .expect("Failed to update window");
+            // Translucent, so the sand under the outlines still shows: red
+            // round the chunks being simulated, cyan round the cells in them
+            // that changed and were redrawn.
+            let red = Pixel { r: 255, g: 0, b: 0, a: 128 };
+            let cyan = Pixel { r: 0, g: 220, b: 255, a: 128 };
+            let active =
+                world.chunks().active_rects().filter_map(|r| camera.world_rect_to_screen(r));
+            let outlines = active.map(|r| (r, red)).chain(dirty.into_iter().map(|r| (r, cyan)));
+            for (rect, color) in outlines {
+                let (x, y, w, h) = rect;
+                let (x, y, w, h) = (x as i32, y as i32, w as i32 - 1, h as i32 - 1);
+                draw_rect_blend(&mut frame, x, y, w, h, color, BlendMode::Alpha);
+                overlay_rects.push(rect);
+            }
+        }
+
+        // Drains are drawn as a strip along their row, green while open,
+        // with the F1 overlay showing how many grains each has taken above
+        // it. A puzzle's goals are gold and always show how far they have
+        // to go.
+        let drain_color = if drain_open || puzzle.is_some() {
+            Pixel { r: 0, g: 220, b: 90, a: 255 }
+        } else {
+            Pixel { r: 150, g: 40, b: 40, a: 255 }
+        };
+        let goal_color = Pixel { r: 255, g: 200, b: 40, a: 255 };
+        let goals: Vec<_> = puzzle.as_ref().map_or(Vec::new(), |p| p.progress(&world).collect());
+        for (i, (d, &count)) in world.drains().iter().zip(world.drained()).enumerate() {
+            let goal = goals.iter().find(|&&(drain, _, _)| drain == i);
+            let color = if goal.is_some() { goal_color } else { drain_color };
+            let (start, end) = d.span(world.width());
+            let span = (start, d.y, end - start + 1, 1);
+            if let Some(rect) = camera.world_rect_to_screen(span) {
+                let (x, y, w, _) = rect;
+                let (x0, x1) = (x as i32, (x + w) as i32 - 1);
+                draw_line(&mut frame, x0, y as i32, x1, y as i32, color);
+                overlay_rects.push((x, y, w, 1));
+                if (show_hud || goal.is_some()) && y > GLYPH_HEIGHT {
+                    let label = match goal {
+                        Some(&(_, got, needed)) => format!("{}/{needed}", got.min(needed)),
+                        None => count.to_string(),
+                    };
+                    let lx = (x + w / 2).saturating_sub(label.len() * GLYPH_WIDTH / 2);
+                    let ly = y - GLYPH_HEIGHT - 1;
+                    draw_text(&mut frame, lx, ly, &label, color);
+                    let x1 = (lx + label.len() * GLYPH_WIDTH) as i32 - 1;
+                    overlay_rects.push(clip_rect(&frame, lx as i32, ly as i32, x1, y as i32 - 1));
+                }
+            }
+        }
+
+        // Both ends of each pair of portals are ringed, as is an end waiting
+        // for its other half.
+        let portal_color = Material::Portal.color();
+        let ring = |frame: &mut Grid<u32>, (x, y): (usize, usize), radius: usize| {
+            let (x, y) = camera.world_to_screen(x as f32 + 0.5, y as f32 + 0.5);
+            let (x, y) = (x as i32, y as i32);
+            let r = ((radius as f32 * camera.zoom) as i32 + 2).max(3);
+            draw_circle(frame, x, y, r, portal_color);
+            clip_rect(frame, x - r - 1, y - r - 1, x + r + 1, y + r + 1)
+        };
+        for p in world.portals() {
+            overlay_rects.push(ring(&mut frame, p.a, p.radius));
+            overlay_rects.push(ring(&mut frame, p.b, p.radius));
+        }
+        if let Some(a) = portal_start {
+            overlay_rects.push(ring(&mut frame, a, brush.radius));
+        }
+
+        // Attractors are drawn as a small cross, blue if they pull and orange
+        // if they push.
+        for a in world.attractors() {
+            let color = if a.strength >= 0.0 {
+                Pixel::new(80, 140, 255, 255)
+            } else {
+                Pixel::new(255, 140, 40, 255)
+            };
+            let (x, y) = camera.world_to_screen(a.x as f32 + 0.5, a.y as f32 + 0.5);
+            let (x, y) = (x as i32, y as i32);
+            draw_line(&mut frame, x - 4, y, x + 4, y, color);
+            draw_line(&mut frame, x, y - 4, x, y + 4, color);
+            overlay_rects.push(clip_rect(&frame, x - 4, y - 4, x + 4, y + 4));
+        }
+
+        // Emitters are drawn as a ring in their material's colour, grey
+        // while switched off.
+        for e in world.emitters() {
+            let color = if e.enabled { e.material.color() } else { Pixel::new(90, 90, 90, 255) };
+            let (x, y) = camera.world_to_screen(e.x as f32 + 0.5, e.y as f32 + 0.5);
+            let (x, y) = (x as i32, y as i32);
+            let r = ((e.radius as f32 * camera.zoom) as i32).max(2);
+            draw_circle(&mut frame, x, y, r, color);
+            overlay_rects.push(clip_rect(&frame, x - r - 1, y - r - 1, x + r + 1, y + r + 1));
+        }
+
+        // Faint guides along the lines drawing is mirrored across.
+        let symmetry = tool.symmetry();
+        if symmetry != Symmetry::None {
+            let guide = Pixel { r: 120, g: 160, b: 255, a: 90 };
+            let middle = (world.width() as f32 / 2.0, world.height() as f32 / 2.0);
+            let (cx, cy) = camera.world_to_screen(middle.0, middle.1);
+            let (w, h) = (view_w as f32, view_h as f32);
+            if matches!(symmetry, Symmetry::Vertical | Symmetry::FourWay) && cx >= 0.0 && cx < w {
+                draw_line_aa(&mut frame, cx, 0.0, cx, h - 1.0, guide);
+                overlay_rects.push(clip_rect(&frame, cx as i32 - 1, 0, cx as i32 + 1, h as i32));
+            }
+            if matches!(symmetry, Symmetry::Horizontal | Symmetry::FourWay) && cy >= 0.0 && cy < h {
+                draw_line_aa(&mut frame, 0.0, cy, w - 1.0, cy, guide);
+                overlay_rects.push(clip_rect(&frame, 0, cy as i32 - 1, w as i32, cy as i32 + 1));
+            }
+        }
+
+        if let Some((mx, my)) = mouse
+            && let Some((ax, ay)) = tool.anchor()
+            && tool.kind() != Kind::Bucket
+            && !tool.is_moving()
+        {
+            // What the line or box tool will draw when the button comes up,
+            // or the box being selected.
+            let (ax, ay) = camera.world_to_screen(ax as f32 + 0.5, ay as f32 + 0.5);
+            let (ax, ay, mx, my) = (ax as i32, ay as i32, mx as i32, my as i32);
+            let color = match tool.kind() {
+                Kind::Select => Pixel::new(255, 255, 255, 255),
+                _ => tool.material().color(),
+            };
+            if tool.kind() == Kind::Line {
+                let (fax, fay, fmx, fmy) = (ax as f32, ay as f32, mx as f32, my as f32);
+                draw_line_aa(&mut frame, fax, fay, fmx, fmy, color);
+            } else {
+                let (x0, y0) = (ax.min(mx), ay.min(my));
+                draw_rect(&mut frame, x0, y0, ax.max(mx) - x0, ay.max(my) - y0, color);
+            }
+            overlay_rects.push(clip_rect(&frame, ax.min(mx), ay.min(my), ax.max(mx), ay.max(my)));
+        }
+
+        // Where the stamp will land.
+        if tool.kind() == Kind::Stamp
+            && let Some(stamp) = tool.stamp()
+            && let Some((cx, cy)) = mouse_cell(&*window, &frame, &camera)
+        {
+            let (w, h) = (stamp.width(), stamp.height());
+            let (x0, y0) = (cx.saturating_sub(w / 2) as f32, cy.saturating_sub(h / 2) as f32);
+            let (sx0, sy0) = camera.world_to_screen(x0, y0);
+            let (sx1, sy1) = camera.world_to_screen(x0 + w as f32, y0 + h as f32);
+            let (sx0, sy0, sx1, sy1) = (sx0 as i32, sy0 as i32, sx1 as i32 - 1, sy1 as i32 - 1);
+            let grey = Pixel { r: 200, g: 200, b: 200, a: 140 };
+            draw_rect_blend(&mut frame, sx0, sy0, sx1 - sx0, sy1 - sy0, grey, BlendMode::Alpha);
+            overlay_rects.push(clip_rect(&frame, sx0, sy0, sx1, sy1));
+        }
+
+        // The selection, following the mouse while it is dragged.
+        if let Some(((x0, y0), (x1, y1))) = tool.selection() {
+            let (mut dx, mut dy) = (0.0, 0.0);
+            if tool.is_moving()
+                && let Some((ax, ay)) = tool.anchor()
+                && let Some((cx, cy)) = mouse_cell(&*window, &frame, &camera)
+            {
+                dx = cx as f32 - ax as f32;
+                dy = cy as f32 - ay as f32;
+            }
+            let (sx0, sy0) = camera.world_to_screen(x0 as f32 + dx, y0 as f32 + dy);
+            let (sx1, sy1) = camera.world_to_screen((x1 + 1) as f32 + dx, (y1 + 1) as f32 + dy);
+            let (sx0, sy0, sx1, sy1) = (sx0 as i32, sy0 as i32, sx1 as i32 - 1, sy1 as i32 - 1);
+            let white = Pixel { r: 255, g: 255, b: 255, a: 160 };
+            draw_rect_blend(&mut frame, sx0, sy0, sx1 - sx0, sy1 - sy0, white, BlendMode::Alpha);
+            overlay_rects.push(clip_rect(&frame, sx0, sy0, sx1, sy1));
+        }
+
+        if let Some((mx, my)) = mouse
+            && !over_panel
+        {
+            let grey = Pixel { r: 200, g: 200, b: 200, a: 140 };
+            let (mx, my) = (mx as i32, my as i32);
+            let r = match brush.shape {
+                Shape::Pencil => (camera.zoom / 2.0).ceil() as i32,
+                _ => (brush.radius as f32 * camera.zoom) as i32,
+            };
+            if brush.shape == Shape::Square {
+                draw_rect_blend(&mut frame, mx - r, my - r, 2 * r, 2 * r, grey, BlendMode::Alpha);
+            } else {
+                draw_circle_blend(&mut frame, mx, my, r, grey, BlendMode::Alpha);
+            }
+            overlay_rects.push(clip_rect(&frame, mx - r - 1, my - r - 1, mx + r + 1, my + r + 1));
+        }
+
+        if show_panel {
+            let mut ui = panel.begin(&mut frame, mouse, left_down, left_click);
+            ui.label("BRUSH");
+            let mut shape = Shape::ALL.iter().position(|&s| s == brush.shape).unwrap_or(0);
+            if ui.choice(&["SPRAY", "DISC", "SQUARE", "PENCIL"], &mut shape) {
+                brush.shape = Shape::ALL[shape];
+                window.set_title(&title(&tool, &brush));
+            }
+            ui.slider("SIZE", &mut brush.radius, MIN_SPAWN_RADIUS, MAX_SPAWN_RADIUS);
+            ui.slider("SPAWN RATE", &mut brush.rate, MIN_SPAWN_RATE, MAX_SPAWN_RATE);
+            ui.label("SPEED");
+            ui.choice(&["1/4", "1/2", "1", "2", "4", "8"], &mut speed);
+            ui.label("GRAVITY");
+            let gravities = [Gravity::Down, Gravity::Left, Gravity::Up, Gravity::Right];
+            let mut gravity = gravities.iter().position(|&g| g == world.gravity()).unwrap_or(0);
+            if ui.choice(&["DOWN", "LEFT", "UP", "RIGHT"], &mut gravity) {
+                world.set_gravity(gravities[gravity]);
+            }
+            ui.label("MATERIAL");
+            let pickable = tool::pickable();
+            let colors: Vec<_> = pickable.iter().map(|m| m.color()).collect();
+            let mut slot = pickable.iter().position(|&m| m == tool.material()).unwrap_or(0);
+            if ui.palette(&colors, &mut slot) {
+                tool.select(slot + 1);
+                window.set_title(&title(&tool, &brush));
+            }
+            ui.label(tool.material().props().name);
+            overlay_rects.push(ui.end());
+        }
+
+        if show_minimap {
+            minimap.update(&world, &palette);
+            overlay_rects.push(minimap.draw(&mut frame, &camera));
+        }
+
+        if show_hud {
+            let mut lines = vec![
+                format!("FPS {:.1}", 1.0 / frame_time.as_secs_f64().max(1e-6)),
+                format!("PHYSICS {:.2} MS", physics_time.as_secs_f64() * 1000.0),
+                format!("GRAINS {}", world.grains().len()),
+                format!("DRAINED {}", world.drained_total()),
+                format!("SPEED {}X", SPEEDS[speed]),
+                format!("TOOL {}", tool.material().props().name),
+                format!("SPAWN {}/S", brush.rate),
+            ];
+            if world.day_night().is_some() {
+                lines.push(format!("AIR {:.0} C", world.ambient()));
+            }
+            let hud = draw_hud(&mut frame, &lines);
+            overlay_rects.push(hud);
+            overlay_rects.push(graph.draw(&mut frame, 0, hud.1 + hud.3));
+        }
+
+        if show_inspector
+            && let Some((mx, my)) = mouse
+            && let Some((cx, cy)) = mouse_cell(&*window, &frame, &camera)
+        {
+            // Down and to the right of the cursor, clear of the brush
+            // outline's middle.
+            const OFFSET: usize = 16;
+            let lines = inspect(&world, cx, cy);
+            let (x, y) = (mx as usize + OFFSET, my as usize + OFFSET);
+            overlay_rects.push(draw_panel(&mut frame, x, y, &lines));
+        }
+
+        if console.is_open() {
+            overlay_rects.push(draw_panel(&mut frame, 0, usize::MAX, &console.lines()));
+        }
+
+        // A finished puzzle says so across the middle of the view.
+        if let Some(puzzle) = &puzzle
+            && puzzle.status() != Status::Playing
+        {
+            const MARGIN: usize = 4;
+            let text = match puzzle.status() {
+                Status::Solved => "SOLVED! F5 TO PLAY AGAIN",
+                _ => "FAILED - F5 TO TRY AGAIN",
+            };
+            let (w, h) = (text.len() * GLYPH_WIDTH + 2 * MARGIN, GLYPH_HEIGHT + 2 * MARGIN);
+            let (x, y) = (view_w.saturating_sub(w) / 2, view_h.saturating_sub(h) / 2);
+            draw_square(&mut frame, x, y, w, h, Pixel::new(0, 0, 0, 255));
+            draw_text(&mut frame, x + MARGIN, y + MARGIN, text, Pixel::new(255, 200, 40, 255));
+            let (x1, y1) = ((x + w) as i32 - 1, (y + h) as i32 - 1);
+            overlay_rects.push(clip_rect(&frame, x as i32, y as i32, x1, y1));
+        }
+
+        window.present(&frame);
+    }
+
+    // Do not lose a recording still running when the window closes.
+    if let Some(rec) = recorder {
+        finish_recording(rec);
+    }
+    if let Some(pipe) = pipe {
+        finish_pipe(pipe);
     }
 }
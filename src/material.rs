@@ -0,0 +1,702 @@
+// Materials that can occupy a grid cell, and the table describing how each
+// one looks and behaves.
+
+use std::ops::Range;
+use std::sync::OnceLock;
+
+use crate::heat::AMBIENT;
+use crate::render::Pixel;
+
+/// Number of shades each material's colour comes in.
+pub const SHADES: usize = 8;
+
+/// `color` in `SHADES` steps of brightness, spread `spread` either side of
+/// it in total, from darkest to lightest.
+pub fn shades(color: Pixel, spread: f32) -> [Pixel; SHADES] {
+    std::array::from_fn(|i| {
+        let t = i as f32 / (SHADES - 1) as f32 - 0.5;
+        let scale = |v: u8| (v as f32 * (1.0 + t * spread)).clamp(0.0, 255.0) as u8;
+        Pixel { r: scale(color.r), g: scale(color.g), b: scale(color.b), a: color.a }
+    })
+}
+
+/// How a material moves during the physics update.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Movement {
+    /// Never moves.
+    Static,
+    /// Falls straight down or slides diagonally, forming piles.
+    Powder,
+    /// Falls like a powder but also flows sideways to find a level.
+    Liquid,
+    /// Rises, drifting from side to side, and bubbles up through liquids.
+    Gas,
+}
+
+/// Per-material properties.
+#[derive(Clone)]
+pub struct MaterialProps {
+    pub name: &'static str,
+    pub color: Pixel,
+    /// Brightness spread between the darkest and lightest shade, as a
+    /// fraction of `color`.
+    pub shade: f32,
+    /// Relative weight; heavier materials sink through lighter ones.
+    pub density: u8,
+    pub movement: Movement,
+    /// Chance per tick, for each burning neighbour, of catching fire.
+    pub flammability: f32,
+    /// Ticks a grain of this material burns for once lit, giving off flames;
+    /// 0 if it turns straight into fire.
+    pub burn_time: u16,
+    /// Range of ticks a cell of this material lasts before it burns out or
+    /// fades, each cell picking its own so cells made together do not all
+    /// go at once; `None` means it lasts forever.
+    pub lifetime: Option<Range<u16>>,
+    /// Chance per tick that a resting grain stays put instead of flowing,
+    /// making thick liquids creep.
+    pub viscosity: f32,
+    /// Chance per tick that a resting powder grain which cannot fall
+    /// straight slides off diagonally. Below 1, piles stand steeper; at 0
+    /// the grains stack in columns.
+    pub slide: f32,
+    /// Chance per tick that a resting powder grain which cannot fall at
+    /// all topples sideways off its neighbour towards a drop, giving
+    /// shallower piles.
+    pub slip: f32,
+    /// Most cells a resting liquid grain flows sideways in a tick, so thin
+    /// liquids level out quickly and thick ones pool. At most `MAX_SPEED`.
+    pub dispersion: u8,
+    /// Temperature a new cell of this material starts at.
+    pub temperature: f32,
+}
+
+/// What occupies a single grid cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Material {
+    Empty,
+    Sand,
+    Wall,
+    Water,
+    Fire,
+    Smoke,
+    Lava,
+    Stone,
+    Steam,
+    Oil,
+    Wood,
+    Plant,
+    Seed,
+    Gunpowder,
+    /// Sand that has soaked up water. Sticks together until it dries.
+    Mud,
+    /// Carries sparks from batteries.
+    Metal,
+    /// Sends a spark into the metal touching it every so often.
+    Battery,
+    /// Metal with a spark passing through it.
+    Spark,
+    /// Switched on and off by sparks.
+    Lamp,
+    LitLamp,
+    /// Copies the first grain that touches it into the empty cells around it.
+    Clone,
+    /// Deletes any grain that touches it.
+    Void,
+    /// Carries whatever rests on it one cell a tick to the left, or to the
+    /// right.
+    ConveyorLeft,
+    ConveyorRight,
+    /// One end of a pair of portals, placed with `World::add_portal`.
+    Portal,
+    /// A light, sticky powder that melts into water when warmed.
+    Snow,
+    /// Frozen water, left by the night of a day and night cycle.
+    Ice,
+    /// Slots for materials made up by scripts, with the `rhai` feature.
+    /// Until a script fills one in it is a plain grey solid.
+    Custom1,
+    Custom2,
+    Custom3,
+    Custom4,
+}
+
+/// Property table indexed by `Material as usize`.
+/// Keep the order in step with the `Material` enum.
+const MATERIALS: [MaterialProps; 31] = [
+    MaterialProps {
+        name: "empty",
+        color: Pixel { r: 0, g: 0, b: 0, a: 255 },
+        shade: 0.0,
+        density: 0,
+        movement: Movement::Static,
+        flammability: 0.0,
+        burn_time: 0,
+        lifetime: None,
+        viscosity: 0.0,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 0,
+        temperature: AMBIENT,
+    },
+    MaterialProps {
+        name: "sand",
+        color: Pixel { r: 194, g: 178, b: 128, a: 255 },
+        shade: 0.16,
+        density: 150,
+        movement: Movement::Powder,
+        flammability: 0.0,
+        burn_time: 0,
+        lifetime: None,
+        viscosity: 0.0,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 0,
+        temperature: AMBIENT,
+    },
+    MaterialProps {
+        name: "wall",
+        color: Pixel { r: 110, g: 110, b: 120, a: 255 },
+        shade: 0.08,
+        density: 255,
+        movement: Movement::Static,
+        flammability: 0.0,
+        burn_time: 0,
+        lifetime: None,
+        viscosity: 0.0,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 0,
+        temperature: AMBIENT,
+    },
+    MaterialProps {
+        name: "water",
+        color: Pixel { r: 28, g: 107, b: 160, a: 255 },
+        shade: 0.06,
+        density: 100,
+        movement: Movement::Liquid,
+        flammability: 0.0,
+        burn_time: 0,
+        lifetime: None,
+        viscosity: 0.0,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 5,
+        temperature: AMBIENT,
+    },
+    MaterialProps {
+        name: "fire",
+        color: Pixel { r: 255, g: 110, b: 20, a: 255 },
+        shade: 0.3,
+        density: 5,
+        movement: Movement::Gas,
+        flammability: 0.0,
+        burn_time: 0,
+        lifetime: Some(21..61),
+        viscosity: 0.0,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 0,
+        temperature: 600.0,
+    },
+    MaterialProps {
+        name: "smoke",
+        color: Pixel { r: 80, g: 80, b: 80, a: 255 },
+        shade: 0.12,
+        density: 2,
+        movement: Movement::Gas,
+        flammability: 0.0,
+        burn_time: 0,
+        lifetime: Some(61..181),
+        viscosity: 0.0,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 0,
+        temperature: AMBIENT,
+    },
+    MaterialProps {
+        name: "lava",
+        color: Pixel { r: 230, g: 70, b: 10, a: 255 },
+        shade: 0.3,
+        density: 180,
+        movement: Movement::Liquid,
+        flammability: 0.0,
+        burn_time: 0,
+        lifetime: None,
+        viscosity: 0.85,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 1,
+        temperature: 1200.0,
+    },
+    MaterialProps {
+        name: "stone",
+        color: Pixel { r: 72, g: 64, b: 62, a: 255 },
+        shade: 0.12,
+        density: 255,
+        movement: Movement::Static,
+        flammability: 0.0,
+        burn_time: 0,
+        lifetime: None,
+        viscosity: 0.0,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 0,
+        temperature: AMBIENT,
+    },
+    MaterialProps {
+        name: "steam",
+        color: Pixel { r: 196, g: 208, b: 218, a: 255 },
+        shade: 0.08,
+        density: 1,
+        movement: Movement::Gas,
+        flammability: 0.0,
+        burn_time: 0,
+        lifetime: Some(76..226),
+        viscosity: 0.0,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 0,
+        temperature: 100.0,
+    },
+    MaterialProps {
+        name: "oil",
+        color: Pixel { r: 92, g: 64, b: 28, a: 255 },
+        shade: 0.1,
+        density: 80,
+        movement: Movement::Liquid,
+        flammability: 0.04,
+        burn_time: 300,
+        lifetime: None,
+        viscosity: 0.3,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 3,
+        temperature: AMBIENT,
+    },
+    MaterialProps {
+        name: "wood",
+        color: Pixel { r: 112, g: 74, b: 40, a: 255 },
+        shade: 0.14,
+        density: 255,
+        movement: Movement::Static,
+        flammability: 0.03,
+        burn_time: 0,
+        lifetime: None,
+        viscosity: 0.0,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 0,
+        temperature: AMBIENT,
+    },
+    MaterialProps {
+        name: "plant",
+        color: Pixel { r: 58, g: 150, b: 48, a: 255 },
+        shade: 0.2,
+        density: 255,
+        movement: Movement::Static,
+        flammability: 0.1,
+        burn_time: 0,
+        lifetime: None,
+        viscosity: 0.0,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 0,
+        temperature: AMBIENT,
+    },
+    MaterialProps {
+        name: "seed",
+        color: Pixel { r: 150, g: 118, b: 62, a: 255 },
+        shade: 0.16,
+        density: 60,
+        movement: Movement::Powder,
+        flammability: 0.1,
+        burn_time: 0,
+        lifetime: None,
+        viscosity: 0.0,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 0,
+        temperature: AMBIENT,
+    },
+    MaterialProps {
+        name: "gunpowder",
+        color: Pixel { r: 58, g: 56, b: 62, a: 255 },
+        shade: 0.2,
+        density: 140,
+        movement: Movement::Powder,
+        flammability: 0.5,
+        burn_time: 0,
+        lifetime: None,
+        viscosity: 0.0,
+        slide: 1.0,
+        slip: 0.3,
+        dispersion: 0,
+        temperature: AMBIENT,
+    },
+    MaterialProps {
+        name: "mud",
+        color: Pixel { r: 112, g: 92, b: 60, a: 255 },
+        shade: 0.12,
+        density: 160,
+        movement: Movement::Powder,
+        flammability: 0.0,
+        burn_time: 0,
+        // How long it stays wet away from water before drying into sand.
+        lifetime: Some(901..2701),
+        viscosity: 0.0,
+        slide: 0.0,
+        slip: 0.0,
+        dispersion: 0,
+        temperature: AMBIENT,
+    },
+    MaterialProps {
+        name: "metal",
+        color: Pixel { r: 150, g: 156, b: 170, a: 255 },
+        shade: 0.1,
+        density: 255,
+        movement: Movement::Static,
+        flammability: 0.0,
+        burn_time: 0,
+        lifetime: None,
+        viscosity: 0.0,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 0,
+        temperature: AMBIENT,
+    },
+    MaterialProps {
+        name: "battery",
+        color: Pixel { r: 60, g: 150, b: 80, a: 255 },
+        shade: 0.08,
+        density: 255,
+        movement: Movement::Static,
+        flammability: 0.0,
+        burn_time: 0,
+        lifetime: None,
+        viscosity: 0.0,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 0,
+        temperature: AMBIENT,
+    },
+    MaterialProps {
+        name: "spark",
+        color: Pixel { r: 255, g: 250, b: 170, a: 255 },
+        shade: 0.05,
+        density: 255,
+        movement: Movement::Static,
+        flammability: 0.0,
+        burn_time: 0,
+        lifetime: None,
+        viscosity: 0.0,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 0,
+        temperature: AMBIENT,
+    },
+    MaterialProps {
+        name: "lamp",
+        color: Pixel { r: 96, g: 84, b: 52, a: 255 },
+        shade: 0.06,
+        density: 255,
+        movement: Movement::Static,
+        flammability: 0.0,
+        burn_time: 0,
+        lifetime: None,
+        viscosity: 0.0,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 0,
+        temperature: AMBIENT,
+    },
+    MaterialProps {
+        name: "lit_lamp",
+        color: Pixel { r: 255, g: 214, b: 96, a: 255 },
+        shade: 0.06,
+        density: 255,
+        movement: Movement::Static,
+        flammability: 0.0,
+        burn_time: 0,
+        lifetime: None,
+        viscosity: 0.0,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 0,
+        temperature: AMBIENT,
+    },
+    MaterialProps {
+        name: "clone",
+        color: Pixel { r: 200, g: 170, b: 40, a: 255 },
+        shade: 0.12,
+        density: 255,
+        movement: Movement::Static,
+        flammability: 0.0,
+        burn_time: 0,
+        lifetime: None,
+        viscosity: 0.0,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 0,
+        temperature: AMBIENT,
+    },
+    MaterialProps {
+        name: "void",
+        color: Pixel { r: 40, g: 10, b: 50, a: 255 },
+        shade: 0.1,
+        density: 255,
+        movement: Movement::Static,
+        flammability: 0.0,
+        burn_time: 0,
+        lifetime: None,
+        viscosity: 0.0,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 0,
+        temperature: AMBIENT,
+    },
+    MaterialProps {
+        name: "conveyor_left",
+        color: Pixel { r: 60, g: 70, b: 90, a: 255 },
+        shade: 0.15,
+        density: 255,
+        movement: Movement::Static,
+        flammability: 0.0,
+        burn_time: 0,
+        lifetime: None,
+        viscosity: 0.0,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 0,
+        temperature: AMBIENT,
+    },
+    MaterialProps {
+        name: "conveyor_right",
+        color: Pixel { r: 90, g: 70, b: 60, a: 255 },
+        shade: 0.15,
+        density: 255,
+        movement: Movement::Static,
+        flammability: 0.0,
+        burn_time: 0,
+        lifetime: None,
+        viscosity: 0.0,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 0,
+        temperature: AMBIENT,
+    },
+    MaterialProps {
+        name: "portal",
+        color: Pixel { r: 120, g: 60, b: 220, a: 255 },
+        shade: 0.2,
+        density: 255,
+        movement: Movement::Static,
+        flammability: 0.0,
+        burn_time: 0,
+        lifetime: None,
+        viscosity: 0.0,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 0,
+        temperature: AMBIENT,
+    },
+    MaterialProps {
+        name: "snow",
+        color: Pixel { r: 240, g: 244, b: 250, a: 255 },
+        shade: 0.04,
+        density: 90,
+        movement: Movement::Powder,
+        flammability: 0.0,
+        burn_time: 0,
+        lifetime: None,
+        viscosity: 0.0,
+        slide: 0.4,
+        slip: 0.0,
+        dispersion: 0,
+        temperature: 0.0,
+    },
+    MaterialProps {
+        name: "ice",
+        color: Pixel { r: 170, g: 210, b: 235, a: 255 },
+        shade: 0.05,
+        density: 255,
+        movement: Movement::Static,
+        flammability: 0.0,
+        burn_time: 0,
+        lifetime: None,
+        viscosity: 0.0,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 0,
+        temperature: -5.0,
+    },
+    MaterialProps {
+        name: "custom1",
+        color: Pixel { r: 128, g: 128, b: 128, a: 255 },
+        shade: 0.0,
+        density: 255,
+        movement: Movement::Static,
+        flammability: 0.0,
+        burn_time: 0,
+        lifetime: None,
+        viscosity: 0.0,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 0,
+        temperature: AMBIENT,
+    },
+    MaterialProps {
+        name: "custom2",
+        color: Pixel { r: 128, g: 128, b: 128, a: 255 },
+        shade: 0.0,
+        density: 255,
+        movement: Movement::Static,
+        flammability: 0.0,
+        burn_time: 0,
+        lifetime: None,
+        viscosity: 0.0,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 0,
+        temperature: AMBIENT,
+    },
+    MaterialProps {
+        name: "custom3",
+        color: Pixel { r: 128, g: 128, b: 128, a: 255 },
+        shade: 0.0,
+        density: 255,
+        movement: Movement::Static,
+        flammability: 0.0,
+        burn_time: 0,
+        lifetime: None,
+        viscosity: 0.0,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 0,
+        temperature: AMBIENT,
+    },
+    MaterialProps {
+        name: "custom4",
+        color: Pixel { r: 128, g: 128, b: 128, a: 255 },
+        shade: 0.0,
+        density: 255,
+        movement: Movement::Static,
+        flammability: 0.0,
+        burn_time: 0,
+        lifetime: None,
+        viscosity: 0.0,
+        slide: 1.0,
+        slip: 0.0,
+        dispersion: 0,
+        temperature: AMBIENT,
+    },
+];
+
+/// The table in use once `set_table` has replaced the built-in one.
+static TABLE: OnceLock<Table> = OnceLock::new();
+
+struct Table {
+    props: [MaterialProps; 31],
+    /// How many of the custom slots the table fills in.
+    custom: usize,
+}
+
+/// A copy of the property table in use, indexed by `Material as usize`, to
+/// change and hand to `set_table`.
+pub fn table() -> [MaterialProps; 31] {
+    TABLE.get().map_or(MATERIALS, |t| t.props.clone())
+}
+
+/// Replace the property table for the rest of the run, with the first
+/// `custom` of `Material::CUSTOM` filled in. Worlds follow the table of the
+/// moment, so this is meant to be done once, before any are made; later
+/// calls fail.
+pub fn set_table(props: [MaterialProps; 31], custom: usize) -> Result<(), String> {
+    let custom = custom.min(Material::CUSTOM.len());
+    TABLE
+        .set(Table { props, custom })
+        .map_err(|_| "the material table has already been set".to_string())
+}
+
+impl Material {
+    /// Every material, in table order.
+    pub const ALL: [Material; 31] = [
+        Material::Empty,
+        Material::Sand,
+        Material::Wall,
+        Material::Water,
+        Material::Fire,
+        Material::Smoke,
+        Material::Lava,
+        Material::Stone,
+        Material::Steam,
+        Material::Oil,
+        Material::Wood,
+        Material::Plant,
+        Material::Seed,
+        Material::Gunpowder,
+        Material::Mud,
+        Material::Metal,
+        Material::Battery,
+        Material::Spark,
+        Material::Lamp,
+        Material::LitLamp,
+        Material::Clone,
+        Material::Void,
+        Material::ConveyorLeft,
+        Material::ConveyorRight,
+        Material::Portal,
+        Material::Snow,
+        Material::Ice,
+        Material::Custom1,
+        Material::Custom2,
+        Material::Custom3,
+        Material::Custom4,
+    ];
+
+    /// The slots for made-up materials, in the order they are filled.
+    pub const CUSTOM: [Material; 4] =
+        [Material::Custom1, Material::Custom2, Material::Custom3, Material::Custom4];
+
+    /// The custom materials the table in use fills in.
+    pub fn custom() -> &'static [Material] {
+        &Self::CUSTOM[..TABLE.get().map_or(0, |t| t.custom)]
+    }
+
+    /// The material with table index `index`, if there is one.
+    pub fn from_index(index: u8) -> Option<Material> {
+        Self::ALL.get(index as usize).copied()
+    }
+
+    /// The material called `name`, ignoring case. Custom slots no script
+    /// has filled in have no name.
+    pub fn from_name(name: &str) -> Option<Material> {
+        Self::ALL.into_iter().find(|m| m.in_use() && m.props().name.eq_ignore_ascii_case(name))
+    }
+
+    /// False for the custom slots the table in use leaves empty.
+    pub fn in_use(self) -> bool {
+        !Self::CUSTOM[Self::custom().len()..].contains(&self)
+    }
+
+    /// Look up the properties of this material.
+    pub fn props(self) -> &'static MaterialProps {
+        &TABLE.get().map_or(&MATERIALS, |t| &t.props)[self as usize]
+    }
+
+    pub fn color(self) -> Pixel {
+        self.props().color
+    }
+
+    /// The material's colour in `SHADES` steps from darkest to lightest, so
+    /// neighbouring grains do not all look the same.
+    pub fn color_variants(self) -> [Pixel; SHADES] {
+        let props = self.props();
+        shades(props.color, props.shade)
+    }
+
+    /// True if grains of this material take part in the physics update.
+    pub fn is_movable(self) -> bool {
+        self.props().movement != Movement::Static
+    }
+}
@@ -0,0 +1,117 @@
+// A small picture of the whole world in the bottom-left corner of the
+// window, with a box round the part the camera shows, for finding your way
+// round a world larger than the window. Each pixel stands for a block of
+// cells and shows the densest thing in it, so thin walls and lone grains
+// still show up. Looking through every cell is not cheap in a big world, so
+// the picture is only redrawn every few frames; the box follows the camera
+// every frame.
+
+use rayon::prelude::*;
+
+use sandfall::camera::Camera;
+use sandfall::chunks::Rect;
+use sandfall::grid::Grid;
+use sandfall::material::{Material, SHADES};
+use sandfall::render::{draw_rect, Pixel};
+use sandfall::World;
+
+/// Longest side of the picture in pixels.
+const MAX_SIZE: usize = 200;
+/// Gap between the picture and the window's edges.
+const MARGIN: usize = 8;
+/// Frames between redraws of the picture.
+const REFRESH_FRAMES: u32 = 10;
+
+const BORDER: Pixel = Pixel { r: 90, g: 90, b: 100, a: 255 };
+const VIEW: Pixel = Pixel { r: 255, g: 255, b: 255, a: 255 };
+
+/// The world in miniature, kept between frames.
+pub struct Minimap {
+    image: Grid<u32>,
+    /// World cells per pixel, across and down.
+    scale: usize,
+    /// Frames until the picture is redrawn.
+    countdown: u32,
+}
+
+impl Minimap {
+    /// A minimap for a `world_w` x `world_h` world, to be drawn on the
+    /// next `update`.
+    pub fn new(world_w: usize, world_h: usize) -> Self {
+        let scale = world_w.max(world_h).div_ceil(MAX_SIZE).max(1);
+        let image = Grid::new(world_w.div_ceil(scale), world_h.div_ceil(scale), 0);
+        Self { image, scale, countdown: 0 }
+    }
+
+    /// The screen area the minimap and its border cover in a window
+    /// `view_h` pixels high.
+    pub fn area(&self, view_h: usize) -> Rect {
+        let (w, h) = (self.image.width() + 2, self.image.height() + 2);
+        (MARGIN, view_h.saturating_sub(MARGIN + h), w, h)
+    }
+
+    /// The world position shown at screen position `(x, y)`, if that is on
+    /// the minimap.
+    pub fn world_at(&self, view_h: usize, x: f32, y: f32) -> Option<(f32, f32)> {
+        let (ax, ay, aw, ah) = self.area(view_h);
+        let (ax, ay) = (ax as f32, ay as f32);
+        if x < ax || y < ay || x >= ax + aw as f32 || y >= ay + ah as f32 {
+            return None;
+        }
+        let scale = self.scale as f32;
+        Some(((x - ax - 1.0).max(0.0) * scale, (y - ay - 1.0).max(0.0) * scale))
+    }
+
+    /// Count down a frame, and redraw the picture from `world` when it is
+    /// due, colouring cells from `palette`.
+    pub fn update(&mut self, world: &World, palette: &[u32]) {
+        if self.countdown > 0 {
+            self.countdown -= 1;
+            return;
+        }
+        self.countdown = REFRESH_FRAMES;
+
+        let scale = self.scale;
+        let cells = world.cells();
+        let (world_w, world_h) = (cells.width(), cells.height());
+        let width = self.image.width();
+        self.image.as_mut_slice().par_chunks_mut(width).enumerate().for_each(|(py, row)| {
+            let (y0, y1) = (py * scale, ((py + 1) * scale).min(world_h));
+            for (px, pixel) in row.iter_mut().enumerate() {
+                let (x0, x1) = (px * scale, ((px + 1) * scale).min(world_w));
+                let densest = (y0..y1)
+                    .flat_map(|y| cells.row(y)[x0..x1].iter().copied())
+                    .max_by_key(|m| m.props().density)
+                    .unwrap_or(Material::Empty);
+                *pixel = palette[densest as usize * SHADES + SHADES / 2];
+            }
+        });
+    }
+
+    /// Draw the picture into `frame` with a box round what `camera` shows,
+    /// returning the area covered.
+    pub fn draw(&self, frame: &mut Grid<u32>, camera: &Camera) -> Rect {
+        let (ax, ay, aw, ah) = self.area(frame.height());
+        let (x0, y0) = (ax + 1, ay + 1);
+        for iy in 0..self.image.height() {
+            let y = y0 + iy;
+            if y >= frame.height() || x0 >= frame.width() {
+                break;
+            }
+            let row = self.image.row(iy);
+            let w = row.len().min(frame.width() - x0);
+            frame.row_mut(y)[x0..x0 + w].copy_from_slice(&row[..w]);
+        }
+        draw_rect(frame, ax as i32, ay as i32, aw as i32 - 1, ah as i32 - 1, BORDER);
+
+        let (view_w, view_h) = camera.view_size();
+        let scale = self.scale as f32;
+        let (vx, vy) = (camera.x / scale, camera.y / scale);
+        let (vw, vh) = (view_w as f32 / camera.zoom / scale, view_h as f32 / camera.zoom / scale);
+        let (vx, vy) = (x0 as i32 + vx as i32, y0 as i32 + vy as i32);
+        draw_rect(frame, vx, vy, (vw as i32).max(1), (vh as i32).max(1), VIEW);
+
+        let (w, h) = (aw.min(frame.width().saturating_sub(ax)), ah.min(frame.height() - ay));
+        (ax, ay, w, h)
+    }
+}
@@ -0,0 +1,185 @@
+// An immediate-mode settings panel drawn straight into the frame down the
+// right-hand side of the window. Widgets are laid out top to bottom as they
+// are called, draw themselves, and hand back whether the mouse changed them
+// this frame, so the frame loop passes its settings in and reads them back
+// without keeping any state for the panel. With the `egui` feature,
+// egui_panel.rs takes its place with the same widgets drawn by egui.
+
+use sandfall::chunks::Rect;
+use sandfall::grid::Grid;
+use sandfall::render::{
+    draw_line, draw_rect, draw_square, draw_text, Pixel, GLYPH_HEIGHT, GLYPH_WIDTH,
+};
+
+/// Width of the panel in pixels.
+pub const WIDTH: usize = 220;
+/// Space around and between widgets.
+const MARGIN: usize = 6;
+/// Height of a slider, button or swatch row.
+const ROW: usize = GLYPH_HEIGHT + 6;
+/// Side of a material swatch.
+const SWATCH: usize = 20;
+
+const BACKGROUND: Pixel = Pixel { r: 24, g: 24, b: 28, a: 255 };
+const TEXT: Pixel = Pixel { r: 230, g: 230, b: 230, a: 255 };
+const DIM: Pixel = Pixel { r: 90, g: 90, b: 100, a: 255 };
+const HIGHLIGHT: Pixel = Pixel { r: 255, g: 200, b: 60, a: 255 };
+
+/// The screen area the panel covers in a `view_w` x `view_h` window, which
+/// the world should not take mouse input from while it is shown.
+pub fn area(view_w: usize, view_h: usize) -> Rect {
+    let x = view_w.saturating_sub(WIDTH);
+    (x, 0, view_w - x, view_h)
+}
+
+/// True if screen position `(x, y)` is on the panel.
+pub fn contains(view_w: usize, view_h: usize, x: f32, y: f32) -> bool {
+    let (ax, ay, aw, ah) = area(view_w, view_h);
+    x >= ax as f32 && x < (ax + aw) as f32 && y >= ay as f32 && y < (ay + ah) as f32
+}
+
+/// What the panel keeps between frames, which for these widgets is nothing.
+pub struct Panel;
+
+impl Panel {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Start this frame's panel in `frame`. `mouse` is the mouse position
+    /// in pixels, `down` whether the left button is held and `clicked`
+    /// whether it went down this frame.
+    pub fn begin<'a>(
+        &'a mut self,
+        frame: &'a mut Grid<u32>,
+        mouse: Option<(f32, f32)>,
+        down: bool,
+        clicked: bool,
+    ) -> Ui<'a> {
+        Ui::new(frame, mouse, down, clicked)
+    }
+}
+
+/// One frame's worth of panel, laid out into `frame`.
+pub struct Ui<'a> {
+    frame: &'a mut Grid<u32>,
+    /// Left edge and width of the widgets.
+    x: usize,
+    width: usize,
+    /// Top of the next widget.
+    y: usize,
+    mouse: Option<(usize, usize)>,
+    /// The left button is held.
+    down: bool,
+    /// The left button went down this frame.
+    clicked: bool,
+}
+
+impl<'a> Ui<'a> {
+    /// Start the panel, drawing its background.
+    fn new(
+        frame: &'a mut Grid<u32>,
+        mouse: Option<(f32, f32)>,
+        down: bool,
+        clicked: bool,
+    ) -> Self {
+        let (ax, ay, aw, ah) = area(frame.width(), frame.height());
+        draw_square(frame, ax, ay, aw, ah, BACKGROUND);
+        draw_line(frame, ax as i32, 0, ax as i32, ah as i32 - 1, DIM);
+        Self {
+            frame,
+            x: ax + MARGIN,
+            width: aw.saturating_sub(2 * MARGIN),
+            y: MARGIN,
+            mouse: mouse.map(|(x, y)| (x as usize, y as usize)),
+            down,
+            clicked,
+        }
+    }
+
+    /// Finish the panel, returning the area it covers, to be repainted
+    /// once it is hidden.
+    pub fn end(self) -> Rect {
+        area(self.frame.width(), self.frame.height())
+    }
+
+    /// The mouse position if it is inside the box at `(x, y)`, `w` x `h`.
+    fn hover(&self, x: usize, y: usize, w: usize, h: usize) -> Option<(usize, usize)> {
+        self.mouse.filter(|&(mx, my)| mx >= x && mx < x + w && my >= y && my < y + h)
+    }
+
+    /// A line of text.
+    pub fn label(&mut self, text: &str) {
+        draw_text(self.frame, self.x, self.y, text, TEXT);
+        self.y += GLYPH_HEIGHT + MARGIN / 2;
+    }
+
+    /// A slider setting `value` between `min` and `max` inclusive, dragged
+    /// with the left button. Returns true if it changed.
+    pub fn slider(&mut self, label: &str, value: &mut usize, min: usize, max: usize) -> bool {
+        let text = format!("{label} {value}");
+        draw_text(self.frame, self.x, self.y, &text, TEXT);
+        let (x, y, w) = (self.x, self.y + GLYPH_HEIGHT + 2, self.width);
+        let mid = (y + ROW / 4) as i32;
+        let last = w.saturating_sub(1);
+        draw_line(self.frame, x as i32, mid, (x + last) as i32, mid, DIM);
+
+        let old = *value;
+        if self.down
+            && let Some((mx, _)) = self.hover(x, y - GLYPH_HEIGHT - 2, w, ROW + GLYPH_HEIGHT)
+        {
+            let t = (mx - x) as f32 / last.max(1) as f32;
+            *value = min + (t * (max - min) as f32).round() as usize;
+        }
+        let t = (value.saturating_sub(min) as f32 / (max - min).max(1) as f32).min(1.0);
+        let knob = x + (t * last as f32) as usize;
+        draw_square(self.frame, knob.saturating_sub(2), y, 5, ROW / 2, HIGHLIGHT);
+
+        self.y = y + ROW / 2 + MARGIN;
+        *value != old
+    }
+
+    /// A row of buttons, one per option, with `selected` highlighted; a
+    /// click selects another. Returns true if the selection changed.
+    pub fn choice(&mut self, options: &[&str], selected: &mut usize) -> bool {
+        let n = options.len().max(1);
+        let w = self.width / n;
+        let mut changed = false;
+        for (i, option) in options.iter().enumerate() {
+            let x = self.x + i * w;
+            if self.clicked && self.hover(x, self.y, w, ROW).is_some() && *selected != i {
+                *selected = i;
+                changed = true;
+            }
+            let color = if *selected == i { HIGHLIGHT } else { DIM };
+            draw_rect(self.frame, x as i32, self.y as i32, w as i32 - 2, ROW as i32 - 1, color);
+            let text_x = x + (w.saturating_sub(option.len() * GLYPH_WIDTH)) / 2;
+            draw_text(self.frame, text_x, self.y + 4, option, TEXT);
+        }
+        self.y += ROW + MARGIN;
+        changed
+    }
+
+    /// Swatches of `colors`, wrapped into rows, with `selected` outlined; a
+    /// click picks another. Returns true if the selection changed.
+    pub fn palette(&mut self, colors: &[Pixel], selected: &mut usize) -> bool {
+        let per_row = (self.width / (SWATCH + 2)).max(1);
+        let mut changed = false;
+        for (i, &color) in colors.iter().enumerate() {
+            let x = self.x + (i % per_row) * (SWATCH + 2);
+            let y = self.y + (i / per_row) * (SWATCH + 2);
+            if self.clicked && self.hover(x, y, SWATCH, SWATCH).is_some() && *selected != i {
+                *selected = i;
+                changed = true;
+            }
+            draw_square(self.frame, x, y, SWATCH, SWATCH, color);
+            if *selected == i {
+                let s = SWATCH as i32 + 1;
+                draw_rect(self.frame, x as i32 - 1, y as i32 - 1, s, s, HIGHLIGHT);
+            }
+        }
+        let rows = colors.len().div_ceil(per_row);
+        self.y += rows * (SWATCH + 2) + MARGIN;
+        changed
+    }
+}
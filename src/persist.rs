@@ -0,0 +1,228 @@
+// Saving and loading worlds.
+//
+// File layout, all integers little-endian:
+//
+//   magic    b"SANDFALL"
+//   version  u16
+//   width    u32
+//   height   u32
+//   grid     runs of (count u32, material u8) covering width * height cells
+//   grains   count u32, then (x u32, y u32) per grain in update order
+//   emitters count u32, then per emitter (x u32, y u32, radius u32,
+//            rate f32, material u8, enabled u8); since version 2
+//   portals  count u32, then per pair (ax u32, ay u32, bx u32, by u32,
+//            radius u32); since version 3
+//   attractors count u32, then per attractor (x u32, y u32,
+//            strength f32); since version 4
+//
+// Grain materials are not stored; they are read back from the grid. Grains
+// are loaded at rest. Older saves, without emitters, portals or attractors, still
+// load.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::grid::Grid;
+use crate::material::Material;
+use crate::world::{Attractor, Emitter, Grain, Portal, World};
+
+const MAGIC: &[u8; 8] = b"SANDFALL";
+const VERSION: u16 = 4;
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+fn read_u16<R: Read>(r: &mut R) -> io::Result<u16> {
+    let mut b = [0u8; 2];
+    r.read_exact(&mut b)?;
+    Ok(u16::from_le_bytes(b))
+}
+
+fn read_u32<R: Read>(r: &mut R) -> io::Result<u32> {
+    let mut b = [0u8; 4];
+    r.read_exact(&mut b)?;
+    Ok(u32::from_le_bytes(b))
+}
+
+fn read_u8<R: Read>(r: &mut R) -> io::Result<u8> {
+    let mut b = [0u8; 1];
+    r.read_exact(&mut b)?;
+    Ok(b[0])
+}
+
+/// Write `world` to `w` in the save format.
+pub fn write_world<W: Write>(world: &World, w: &mut W) -> io::Result<()> {
+    w.write_all(MAGIC)?;
+    w.write_all(&VERSION.to_le_bytes())?;
+    w.write_all(&(world.width() as u32).to_le_bytes())?;
+    w.write_all(&(world.height() as u32).to_le_bytes())?;
+
+    let mut cells = world.cells().as_slice().iter().copied();
+    if let Some(first) = cells.next() {
+        let mut run = (first, 1u32);
+        for cell in cells {
+            if cell == run.0 {
+                run.1 += 1;
+            } else {
+                w.write_all(&run.1.to_le_bytes())?;
+                w.write_all(&[run.0 as u8])?;
+                run = (cell, 1);
+            }
+        }
+        w.write_all(&run.1.to_le_bytes())?;
+        w.write_all(&[run.0 as u8])?;
+    }
+
+    w.write_all(&(world.grains().len() as u32).to_le_bytes())?;
+    for g in world.grains() {
+        w.write_all(&(g.x as u32).to_le_bytes())?;
+        w.write_all(&(g.y as u32).to_le_bytes())?;
+    }
+
+    w.write_all(&(world.emitters().len() as u32).to_le_bytes())?;
+    for e in world.emitters() {
+        w.write_all(&(e.x as u32).to_le_bytes())?;
+        w.write_all(&(e.y as u32).to_le_bytes())?;
+        w.write_all(&(e.radius as u32).to_le_bytes())?;
+        w.write_all(&e.rate.to_le_bytes())?;
+        w.write_all(&[e.material as u8, e.enabled as u8])?;
+    }
+
+    w.write_all(&(world.portals().len() as u32).to_le_bytes())?;
+    for p in world.portals() {
+        for v in [p.a.0, p.a.1, p.b.0, p.b.1, p.radius] {
+            w.write_all(&(v as u32).to_le_bytes())?;
+        }
+    }
+
+    w.write_all(&(world.attractors().len() as u32).to_le_bytes())?;
+    for a in world.attractors() {
+        w.write_all(&(a.x as u32).to_le_bytes())?;
+        w.write_all(&(a.y as u32).to_le_bytes())?;
+        w.write_all(&a.strength.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Read a save from `r` into `world`, replacing its contents. The save must
+/// have the same dimensions as the world.
+pub fn read_world<R: Read>(world: &mut World, r: &mut R) -> io::Result<()> {
+    let mut magic = [0u8; 8];
+    r.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid("not a sandfall save"));
+    }
+    let version = read_u16(r)?;
+    if !(1..=VERSION).contains(&version) {
+        return Err(invalid(&format!("unsupported save version {version}")));
+    }
+    let width = read_u32(r)? as usize;
+    let height = read_u32(r)? as usize;
+    if width != world.width() || height != world.height() {
+        return Err(invalid(&format!(
+            "save is {width}x{height} but the world is {}x{}",
+            world.width(),
+            world.height()
+        )));
+    }
+
+    let mut grid = Grid::new(width, height, Material::Empty);
+    let mut filled = 0;
+    while filled < width * height {
+        let count = read_u32(r)? as usize;
+        let material =
+            Material::from_index(read_u8(r)?).ok_or_else(|| invalid("unknown material"))?;
+        if count == 0 || filled + count > width * height {
+            return Err(invalid("grid runs overflow the world"));
+        }
+        grid.as_mut_slice()[filled..filled + count].fill(material);
+        filled += count;
+    }
+
+    // Every movable cell holds exactly one grain, so the count is known
+    // before any are read, and checked before it sizes an allocation.
+    let movable = grid.as_slice().iter().filter(|m| m.is_movable()).count();
+    let count = read_u32(r)? as usize;
+    if count != movable {
+        return Err(invalid("grain count does not match the grid"));
+    }
+    let mut grains = Vec::with_capacity(count);
+    let mut taken = Grid::new(width, height, false);
+    for _ in 0..count {
+        let x = read_u32(r)? as usize;
+        let y = read_u32(r)? as usize;
+        if x >= width || y >= height || !grid.get(x, y).is_movable() || taken.get(x, y) {
+            return Err(invalid("grain does not match the grid"));
+        }
+        taken.set(x, y, true);
+        grains.push(Grain::new(x, y, grid.get(x, y)));
+    }
+
+    let mut emitters = Vec::new();
+    if version >= 2 {
+        let count = read_u32(r)? as usize;
+        for _ in 0..count {
+            let x = read_u32(r)? as usize;
+            let y = read_u32(r)? as usize;
+            let radius = read_u32(r)? as usize;
+            let rate = f32::from_bits(read_u32(r)?);
+            let material =
+                Material::from_index(read_u8(r)?).ok_or_else(|| invalid("unknown material"))?;
+            let enabled = read_u8(r)? != 0;
+            if x >= width || y >= height || !rate.is_finite() || rate < 0.0 {
+                return Err(invalid("bad emitter"));
+            }
+            emitters.push(Emitter { x, y, radius, rate, material, enabled });
+        }
+    }
+
+    let mut portals = Vec::new();
+    if version >= 3 {
+        let count = read_u32(r)? as usize;
+        for _ in 0..count {
+            let mut fields = [0; 5];
+            for f in &mut fields {
+                *f = read_u32(r)? as usize;
+            }
+            let [ax, ay, bx, by, radius] = fields;
+            if ax >= width || ay >= height || bx >= width || by >= height {
+                return Err(invalid("bad portal"));
+            }
+            portals.push(Portal { a: (ax, ay), b: (bx, by), radius });
+        }
+    }
+
+    let mut attractors = Vec::new();
+    if version >= 4 {
+        let count = read_u32(r)? as usize;
+        for _ in 0..count {
+            let x = read_u32(r)? as usize;
+            let y = read_u32(r)? as usize;
+            let strength = f32::from_bits(read_u32(r)?);
+            if x >= width || y >= height || !strength.is_finite() {
+                return Err(invalid("bad attractor"));
+            }
+            attractors.push(Attractor { x, y, strength });
+        }
+    }
+
+    world.restore(grid, grains);
+    world.emitters = emitters;
+    world.portals = portals;
+    world.attractors = attractors;
+    Ok(())
+}
+
+/// Save `world` to the file at `path`.
+pub fn save(world: &World, path: &Path) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    write_world(world, &mut w)?;
+    w.flush()
+}
+
+/// Load the file at `path` into `world`.
+pub fn load(world: &mut World, path: &Path) -> io::Result<()> {
+    read_world(world, &mut BufReader::new(File::open(path)?))
+}
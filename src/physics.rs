@@ -0,0 +1,479 @@
+// The movement rules for a single grain, written against the `Cells` trait
+// so the same rules drive both the serial update in `World::step` and the
+// tasks of the parallel checkerboard update in `step_parallel`.
+//
+// The parallel update splits the chunks into four interleaved sets, like the
+// squares of a chessboard coloured in two directions. Chunks of one set are at
+// least a whole chunk apart, and a grain moves far less than that in a tick,
+// so every active chunk in a set can be updated on its own thread without two
+// threads ever touching the same cell or grain. The sets run one after the
+// other.
+
+use rayon::prelude::*;
+
+use crate::chunks::CHUNK_SIZE;
+use crate::events::Event;
+use crate::material::{Material, Movement};
+use crate::world::{Grain, Gravity, World, Wrap, MAX_SPEED, NO_GRAIN, SLEEP_TICKS};
+
+/// Furthest, in cells, the update of a grain can reach from where it
+/// started: a full-speed sweep, plus the grain it may swap with, plus one.
+const REACH: usize = MAX_SPEED as usize + 2;
+
+// Chunks in a set are a chunk apart. Across the four sets a grain can drift
+// `4 * REACH` cells from its chunk, and the update of a grain reads `REACH`
+// further, so that must not reach the next chunk of the set.
+const _: () = assert!(5 * REACH < CHUNK_SIZE);
+
+/// The world's size and which of its edges join up, for finding the cells
+/// around a grain.
+#[derive(Clone, Copy)]
+pub(crate) struct Edges {
+    width: usize,
+    height: usize,
+    wrap: Wrap,
+}
+
+impl Edges {
+    /// Where `(x, y)` really is, which may be back across a joined edge.
+    fn join(self, x: isize, y: isize) -> (isize, isize) {
+        self.wrap.join(self.width, self.height, x, y)
+    }
+}
+
+/// What the movement rules need from the world.
+pub(crate) trait Cells {
+    fn edges(&self) -> Edges;
+    /// How fast falling grains speed up, in cells per tick per tick.
+    fn pull(&self) -> f32;
+    fn in_bounds(&self, x: isize, y: isize) -> bool;
+    fn material(&self, x: usize, y: usize) -> Material;
+    fn grain_mut(&mut self, idx: usize) -> &mut Grain;
+    /// Move grain `idx` to `(nx, ny)`, swapping places with any grain
+    /// already there, and wake both cells.
+    fn move_grain(&mut self, idx: usize, nx: usize, ny: usize);
+    /// Grain `idx` has just landed on something after falling or being
+    /// thrown.
+    fn landed(&mut self, idx: usize);
+    /// A uniform random number in `0..1`.
+    fn uni(&mut self) -> f32;
+}
+
+fn is_empty<C: Cells>(cells: &C, x: isize, y: isize) -> bool {
+    cells.in_bounds(x, y) && cells.material(x as usize, y as usize) == Material::Empty
+}
+
+/// True if a grain of `mover` may move into `(x, y)`: the cell is empty,
+/// or holds a lighter movable material for it to sink through.
+fn can_enter<C: Cells>(cells: &C, mover: Material, x: isize, y: isize) -> bool {
+    if !cells.in_bounds(x, y) {
+        return false;
+    }
+    let target = cells.material(x as usize, y as usize);
+    target == Material::Empty
+        || (target.is_movable() && target.props().density < mover.props().density)
+}
+
+/// True if a gas grain of `mover` may rise into `(x, y)`: the cell is
+/// empty, or holds a heavier liquid for it to bubble up through.
+fn can_rise<C: Cells>(cells: &C, mover: Material, x: isize, y: isize) -> bool {
+    if !cells.in_bounds(x, y) {
+        return false;
+    }
+    let target = cells.material(x as usize, y as usize);
+    target == Material::Empty
+        || (target.props().movement == Movement::Liquid
+            && target.props().density > mover.props().density)
+}
+
+/// Walk from `(x, y)` towards `(x + dx, y + dy)` one cell at a time and
+/// return the last free cell reached, plus the cell that was hit before
+/// the end of the path, if one was.
+fn sweep<C: Cells>(
+    cells: &C,
+    x: usize,
+    y: usize,
+    dx: isize,
+    dy: isize,
+) -> ((usize, usize), Option<(isize, isize)>) {
+    let (xi, yi) = (x as isize, y as isize);
+    let edges = cells.edges();
+    let n = dx.abs().max(dy.abs());
+    let mut last = (x, y);
+    for i in 1..=n {
+        let (cx, cy) = edges.join(xi + dx * i / n, yi + dy * i / n);
+        if !is_empty(cells, cx, cy) {
+            return (last, Some((cx, cy)));
+        }
+        last = (cx as usize, cy as usize);
+    }
+    (last, None)
+}
+
+/// The furthest of up to `reach` cells towards side `s` that a liquid of
+/// `mover` can flow to without being blocked, stopping at the first with a
+/// drop below it. `down(n, s)` is the cell `n` along gravity and `s` across
+/// it from the grain.
+fn flow<C: Cells>(
+    cells: &C,
+    mover: Material,
+    reach: isize,
+    s: isize,
+    down: impl Fn(isize, isize) -> (isize, isize),
+) -> Option<(usize, usize)> {
+    let mut last = None;
+    for i in 1..=reach {
+        let (x, y) = down(0, s * i);
+        if !can_enter(cells, mover, x, y) {
+            break;
+        }
+        last = Some((x as usize, y as usize));
+        let (bx, by) = down(1, s * i);
+        if can_enter(cells, mover, bx, by) {
+            break;
+        }
+    }
+    last
+}
+
+/// Move grain `idx` for one tick. `side` is the diagonal tried first. A
+/// grain that does not move counts towards falling asleep, unless it was
+/// only held back by its viscosity.
+pub(crate) fn update_grain<C: Cells>(cells: &mut C, idx: usize, gravity: Gravity, side: isize) {
+    let (x, y) = {
+        let g = cells.grain_mut(idx);
+        (g.x, g.y)
+    };
+    if !apply_rules(cells, idx, gravity, side) {
+        return;
+    }
+    let g = cells.grain_mut(idx);
+    g.idle = if (g.x, g.y) == (x, y) { g.idle.saturating_add(1).min(SLEEP_TICKS) } else { 0 };
+}
+
+/// Returns false if the grain sat this tick out because of its viscosity.
+fn apply_rules<C: Cells>(cells: &mut C, idx: usize, gravity: Gravity, side: isize) -> bool {
+    let (gx, gy) = gravity.vector();
+    let (ax, ay) = gravity.across();
+    let mut g = *cells.grain_mut(idx);
+    let (xi, yi) = (g.x as isize, g.y as isize);
+    // Neighbours are found relative to gravity: `down` is the way it
+    // pulls and `side` is at right angles to it. Across a joined edge they
+    // are on the far side of the world.
+    let edges = cells.edges();
+    let pull = cells.pull();
+    let down = |n: isize, s: isize| edges.join(xi + gx * n + ax * s, yi + gy * n + ay * s);
+
+    // Gases rise against gravity, bubbling up through liquids, or drift
+    // to a random side. Solids are only ever passed around.
+    if g.material.props().movement == Movement::Gas {
+        let drift = if cells.uni() < 0.5 { -1 } else { 1 };
+        let rises = [down(-1, 0), down(-1, drift), down(-1, -drift)];
+        let (sx, sy) = down(0, drift);
+        let rise = rises.into_iter().find(|&(nx, ny)| can_rise(cells, g.material, nx, ny));
+        if let Some((nx, ny)) = rise {
+            cells.move_grain(idx, nx as usize, ny as usize);
+        } else if is_empty(cells, sx, sy) {
+            cells.move_grain(idx, sx as usize, sy as usize);
+        }
+        return true;
+    }
+
+    // Free fall, or flight after being thrown: accelerate, then sweep
+    // along the velocity so fast grains cover several cells per tick
+    // without tunnelling.
+    let (bx, by) = down(1, 0);
+    if is_empty(cells, bx, by) || g.is_launched(gravity) {
+        g.vx = (g.vx + gx as f32 * pull).clamp(-MAX_SPEED, MAX_SPEED);
+        g.vy = (g.vy + gy as f32 * pull).clamp(-MAX_SPEED, MAX_SPEED);
+        g.fx += g.vx;
+        g.fy += g.vy;
+        let (dx, dy) = (g.fx.trunc(), g.fy.trunc());
+        g.fx -= dx;
+        g.fy -= dy;
+
+        let ((nx, ny), hit) = sweep(cells, g.x, g.y, dx as isize, dy as isize);
+        // A grain running into a portal keeps its speed to come out of the
+        // other end with, which happens after every grain has moved.
+        let hit = hit.is_some_and(|(hx, hy)| {
+            !cells.in_bounds(hx, hy) || cells.material(hx as usize, hy as usize) != Material::Portal
+        });
+        if hit {
+            g.land(gravity);
+        }
+        if (nx, ny) != (g.x, g.y) {
+            cells.move_grain(idx, nx, ny);
+        }
+        *cells.grain_mut(idx) = Grain { x: nx, y: ny, ..g };
+        if hit {
+            cells.landed(idx);
+        }
+        return true;
+    }
+
+    // Resting on something: sink through it if it is lighter, else
+    // slide diagonally, and liquids that cannot do that spread
+    // sideways, picking a random side first so they level out instead
+    // of drifting one way.
+    cells.grain_mut(idx).land(gravity);
+    let props = g.material.props();
+    if props.viscosity > 0.0 && cells.uni() < props.viscosity {
+        return false;
+    }
+    let falls = [down(1, 0), down(1, side), down(1, -side)];
+    // Sticky powders only sometimes slide off diagonally.
+    let slides = props.slide >= 1.0 || cells.uni() < props.slide;
+    let candidates: &[(isize, isize)] = match props.movement {
+        Movement::Static | Movement::Gas => &[],
+        Movement::Powder if slides => &falls,
+        Movement::Powder => &falls[..1],
+        Movement::Liquid => &falls,
+    };
+
+    for &(nx, ny) in candidates {
+        if can_enter(cells, g.material, nx, ny) {
+            cells.move_grain(idx, nx as usize, ny as usize);
+            return true;
+        }
+    }
+
+    // Liquids that cannot fall flow sideways, up to their dispersion in
+    // cells, stopping early over a drop so they pour off ledges.
+    if props.movement == Movement::Liquid {
+        let first = if cells.uni() < 0.5 { -1 } else { 1 };
+        let reach = props.dispersion.min(MAX_SPEED as u8) as isize;
+        for s in [first, -first] {
+            if let Some((nx, ny)) = flow(cells, g.material, reach, s, down) {
+                cells.move_grain(idx, nx, ny);
+                return true;
+            }
+        }
+        return true;
+    }
+
+    // Slippery powders that found nowhere to fall may step sideways off the
+    // pile, but only where there is a drop just beyond to fall down next
+    // tick, so their piles level out at a shallower slope and still settle.
+    if props.movement == Movement::Powder && props.slip > 0.0 && cells.uni() < props.slip {
+        for s in [side, -side] {
+            let (sx, sy) = down(0, s);
+            let (dx, dy) = down(1, 2 * s);
+            if is_empty(cells, sx, sy) && is_empty(cells, dx, dy) {
+                cells.move_grain(idx, sx as usize, sy as usize);
+                break;
+            }
+        }
+    }
+    true
+}
+
+impl Cells for World {
+    fn edges(&self) -> Edges {
+        Edges { width: self.width, height: self.height, wrap: self.wrap() }
+    }
+
+    fn pull(&self) -> f32 {
+        self.pull
+    }
+
+    fn in_bounds(&self, x: isize, y: isize) -> bool {
+        self.grid.in_bounds(x, y)
+    }
+
+    fn material(&self, x: usize, y: usize) -> Material {
+        self.grid.get(x, y)
+    }
+
+    fn grain_mut(&mut self, idx: usize) -> &mut Grain {
+        &mut self.grains[idx]
+    }
+
+    fn move_grain(&mut self, idx: usize, nx: usize, ny: usize) {
+        let (x, y) = (self.grains[idx].x, self.grains[idx].y);
+        let other = self.index.get(nx, ny);
+        let mover = self.grid.get(x, y);
+
+        self.grid.set(x, y, self.grid.get(nx, ny));
+        self.grid.set(nx, ny, mover);
+        self.index.set(x, y, other);
+        self.index.set(nx, ny, idx as u32);
+        let heat = self.heat.get(x, y);
+        self.heat.set(x, y, self.heat.get(nx, ny));
+        self.heat.set(nx, ny, heat);
+        if other != NO_GRAIN {
+            let o = &mut self.grains[other as usize];
+            o.x = x;
+            o.y = y;
+        }
+        let g = &mut self.grains[idx];
+        g.x = nx;
+        g.y = ny;
+
+        self.free_cell(x, y);
+        self.chunks.wake(nx, ny);
+    }
+
+    fn landed(&mut self, idx: usize) {
+        let Grain { x, y, material, .. } = self.grains[idx];
+        self.send(Event::GrainSettled { x, y, material });
+    }
+
+    fn uni(&mut self) -> f32 {
+        self.rng.uni()
+    }
+}
+
+/// Raw views of the world's cells, grain index, heat and grains, shared by the
+/// tasks of one checkerboard set.
+#[derive(Clone, Copy)]
+struct Shared {
+    width: usize,
+    height: usize,
+    pull: f32,
+    grid: *mut Material,
+    index: *mut u32,
+    heat: *mut f32,
+    grains: *mut Grain,
+}
+
+// SAFETY: the tasks sharing these pointers only touch cells and grains near
+// their own chunk, and the chunks of a set are far enough apart that no two
+// tasks touch the same memory (see `REACH`).
+unsafe impl Send for Shared {}
+unsafe impl Sync for Shared {}
+
+/// The update of one chunk: its own random numbers, the cells it moved
+/// grains out of and into, to be woken once every task of the set is done,
+/// and the grains that landed, to be reported then too.
+struct Task {
+    shared: Shared,
+    rng: u64,
+    woken: Vec<Moved>,
+    landed: Vec<u32>,
+}
+
+/// Cells a grain moved between.
+struct Moved {
+    from: (usize, usize),
+    to: (usize, usize),
+}
+
+impl Cells for Task {
+    fn edges(&self) -> Edges {
+        // Worlds with joined edges are never updated in parallel.
+        Edges { width: self.shared.width, height: self.shared.height, wrap: Wrap::default() }
+    }
+
+    fn pull(&self) -> f32 {
+        self.shared.pull
+    }
+
+    fn in_bounds(&self, x: isize, y: isize) -> bool {
+        x >= 0 && y >= 0 && (x as usize) < self.shared.width && (y as usize) < self.shared.height
+    }
+
+    fn material(&self, x: usize, y: usize) -> Material {
+        // SAFETY: in bounds, and only this task touches cells this close.
+        unsafe { *self.shared.grid.add(y * self.shared.width + x) }
+    }
+
+    fn grain_mut(&mut self, idx: usize) -> &mut Grain {
+        // SAFETY: the grain is in this task's chunk, or was swapped into
+        // cells only this task touches.
+        unsafe { &mut *self.shared.grains.add(idx) }
+    }
+
+    fn move_grain(&mut self, idx: usize, nx: usize, ny: usize) {
+        let w = self.shared.width;
+        let (x, y) = {
+            let g = self.grain_mut(idx);
+            (g.x, g.y)
+        };
+        // SAFETY: as for `material` and `grain_mut`.
+        unsafe {
+            let (from, to) = (y * w + x, ny * w + nx);
+            let other = *self.shared.index.add(to);
+            std::ptr::swap(self.shared.grid.add(from), self.shared.grid.add(to));
+            std::ptr::swap(self.shared.heat.add(from), self.shared.heat.add(to));
+            *self.shared.index.add(from) = other;
+            *self.shared.index.add(to) = idx as u32;
+            if other != NO_GRAIN {
+                let o = &mut *self.shared.grains.add(other as usize);
+                o.x = x;
+                o.y = y;
+            }
+        }
+        let g = self.grain_mut(idx);
+        g.x = nx;
+        g.y = ny;
+        self.woken.push(Moved { from: (x, y), to: (nx, ny) });
+    }
+
+    fn landed(&mut self, idx: usize) {
+        self.landed.push(idx as u32);
+    }
+
+    fn uni(&mut self) -> f32 {
+        // xorshift64*: quick, and plenty for picking sides.
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        (self.rng.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// Move every grain in an active chunk, updating the four checkerboard sets
+/// of chunks one after another with the chunks of each set in parallel.
+/// Reactions are left to the caller.
+pub(crate) fn step_parallel(world: &mut World, side: isize) {
+    let (cols, rows) = (world.chunks.cols(), world.chunks.rows());
+
+    // Grains of each active chunk, in the reverse order the serial update
+    // visits them. A grain is updated once, by the chunk it starts in.
+    let mut buckets: Vec<Vec<u32>> = vec![Vec::new(); cols * rows];
+    for (idx, g) in world.grains.iter().enumerate().rev() {
+        if g.material.is_movable() && !g.is_asleep() && world.chunks.is_active(g.x, g.y) {
+            buckets[(g.y / CHUNK_SIZE) * cols + g.x / CHUNK_SIZE].push(idx as u32);
+        }
+    }
+
+    // One draw from the world's generator seeds every task, so a run is
+    // the same whatever the number of threads.
+    let seed = (world.rng.uni() as f64 * u32::MAX as f64) as u64;
+    let gravity = world.gravity();
+    let shared = Shared {
+        width: world.width,
+        height: world.height,
+        pull: world.pull,
+        grid: world.grid.as_mut_slice().as_mut_ptr(),
+        index: world.index.as_mut_slice().as_mut_ptr(),
+        heat: world.heat.as_mut_slice().as_mut_ptr(),
+        grains: world.grains.as_mut_ptr(),
+    };
+
+    for set in 0..4 {
+        let (woken, landed): (Vec<Vec<Moved>>, Vec<Vec<u32>>) = buckets
+            .par_iter()
+            .enumerate()
+            .filter(|&(i, bucket)| {
+                let (cx, cy) = (i % cols, i / cols);
+                (cx % 2) + 2 * (cy % 2) == set && !bucket.is_empty()
+            })
+            .map(|(i, bucket)| {
+                let rng = (seed << 32 | i as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+                let mut task = Task { shared, rng, woken: Vec::new(), landed: Vec::new() };
+                for &idx in bucket {
+                    update_grain(&mut task, idx as usize, gravity, side);
+                }
+                (task.woken, task.landed)
+            })
+            .unzip();
+        for Moved { from, to } in woken.into_iter().flatten() {
+            world.free_cell(from.0, from.1);
+            world.chunks.wake(to.0, to.1);
+        }
+        for idx in landed.into_iter().flatten() {
+            world.landed(idx as usize);
+        }
+    }
+}
@@ -0,0 +1,81 @@
+// Raw frames written to stdout for `--pipe-frames`, so a video can be made
+// by any encoder that reads raw video, e.g.
+// `sandfall --pipe-frames | ffmpeg -f rawvideo -pixel_format rgba
+// -video_size 1200x800 -framerate 30 -i - sandfall.mp4`. Frames go out at a
+// steady rate whatever the window's frame rate, repeating the last one when
+// the window falls behind, and are written by a background thread so a slow
+// reader does not hold up drawing until the queue fills.
+
+use std::io::{self, BufWriter, Write};
+use std::sync::mpsc::{self, SyncSender};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use sandfall::grid::Grid;
+
+/// Frames written per second.
+pub const FPS: u32 = 30;
+/// Frames waiting to be written before drawing waits for the reader.
+const QUEUE: usize = 4;
+
+/// A stream of RGBA frames on stdout.
+pub struct FramePipe {
+    width: usize,
+    height: usize,
+    started: Instant,
+    written: u64,
+    tx: SyncSender<Vec<u8>>,
+    writer: JoinHandle<io::Result<()>>,
+}
+
+impl FramePipe {
+    /// Start writing `width` x `height` frames to stdout.
+    pub fn start(width: usize, height: usize) -> Self {
+        let (tx, rx) = mpsc::sync_channel::<Vec<u8>>(QUEUE);
+        let writer = thread::spawn(move || {
+            let mut out = BufWriter::new(io::stdout().lock());
+            for rgba in rx {
+                out.write_all(&rgba)?;
+            }
+            out.flush()
+        });
+        Self { width, height, started: Instant::now(), written: 0, tx, writer }
+    }
+
+    /// Offer the frame just drawn, written as many times as the rate calls
+    /// for since the last one: usually once or not at all. A frame of
+    /// another size, after the window was resized, is stretched to the
+    /// size the stream started at. Returns false once the reader has gone
+    /// away.
+    pub fn capture(&mut self, frame: &Grid<u32>) -> bool {
+        let due = (self.started.elapsed().as_secs_f64() * FPS as f64) as u64 + 1;
+        if due <= self.written {
+            return true;
+        }
+
+        let mut rgba = Vec::with_capacity(self.width * self.height * 4);
+        for y in 0..self.height {
+            let row = frame.row(y * frame.height() / self.height);
+            for x in 0..self.width {
+                let [_, r, g, b] = row[x * frame.width() / self.width].to_be_bytes();
+                rgba.extend_from_slice(&[r, g, b, 255]);
+            }
+        }
+        while self.written + 1 < due {
+            if self.tx.send(rgba.clone()).is_err() {
+                return false;
+            }
+            self.written += 1;
+        }
+        self.written += 1;
+        self.tx.send(rgba).is_ok()
+    }
+
+    /// Stop writing, wait for the queue to empty and return the number of
+    /// frames written, or why the reader stopped taking them.
+    pub fn finish(self) -> io::Result<u64> {
+        drop(self.tx);
+        self.writer.join().map_err(|_| io::Error::other("frame writer panicked"))??;
+        Ok(self.written)
+    }
+}
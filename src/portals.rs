@@ -0,0 +1,105 @@
+// Portals: pairs of static discs that pass grains between them. The movement
+// rules stop a grain at the edge of a portal cell without slowing it; then,
+// once every grain has moved, any grain heading into a portal cell is put
+// the same distance from the middle of the other end, just past its edge,
+// still moving as before. A grain whose way out is blocked rests on the
+// portal until it clears.
+
+use crate::material::Material;
+use crate::physics::Cells;
+use crate::world::{World, NO_GRAIN};
+
+/// The step a grain moving at `(vx, vy)` takes next, along whichever axis
+/// it moves fastest.
+fn heading(vx: f32, vy: f32) -> (isize, isize) {
+    if vx == 0.0 && vy == 0.0 {
+        (0, 0)
+    } else if vx.abs() > vy.abs() {
+        (vx.signum() as isize, 0)
+    } else {
+        (0, vy.signum() as isize)
+    }
+}
+
+/// Pass every grain heading into a portal cell out of the other end.
+pub(crate) fn run(world: &mut World) {
+    if world.portals.is_empty() {
+        return;
+    }
+    let (w, h) = (world.width as isize, world.height as isize);
+    let in_world = |x: isize, y: isize| x >= 0 && y >= 0 && x < w && y < h;
+
+    // Grains beside a portal cell and moving into it. Each grain heads into
+    // one cell, so is found once.
+    let mut entering = Vec::new();
+    for (x0, y0, cw, ch) in world.chunks.active_rects() {
+        for y in y0..y0 + ch {
+            for x in x0..x0 + cw {
+                if world.grid.get(x, y) != Material::Portal {
+                    continue;
+                }
+                let (xi, yi) = (x as isize, y as isize);
+                for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                    let (gx, gy) = (xi - dx, yi - dy);
+                    if !in_world(gx, gy) {
+                        continue;
+                    }
+                    let idx = world.index.get(gx as usize, gy as usize);
+                    if idx == NO_GRAIN {
+                        continue;
+                    }
+                    let g = &world.grains[idx as usize];
+                    if heading(g.vx, g.vy) == (dx, dy) {
+                        entering.push((idx as usize, (x, y), (dx, dy)));
+                    }
+                }
+            }
+        }
+    }
+
+    let gravity = world.gravity();
+    for (idx, (px, py), (dx, dy)) in entering {
+        // The end the cell belongs to, and the one the grain comes out of.
+        let ends = world.portals.iter().find_map(|p| {
+            if p.covers(p.a, px, py) {
+                Some((p.a, p.b, p.radius))
+            } else if p.covers(p.b, px, py) {
+                Some((p.b, p.a, p.radius))
+            } else {
+                None
+            }
+        });
+        // Portal cells painted by hand belong to no pair and act as walls.
+        let Some((from, to, radius)) = ends else {
+            world.grains[idx].land(gravity);
+            continue;
+        };
+
+        // Walk on from the same place in the other end until clear of it.
+        let mut x = to.0 as isize + px as isize - from.0 as isize;
+        let mut y = to.1 as isize + py as isize - from.1 as isize;
+        let mut exit = None;
+        for _ in 0..=2 * radius + 1 {
+            if !in_world(x, y) {
+                break;
+            }
+            match world.grid.get(x as usize, y as usize) {
+                Material::Portal => {}
+                Material::Empty => {
+                    exit = Some((x as usize, y as usize));
+                    break;
+                }
+                _ => break,
+            }
+            x += dx;
+            y += dy;
+        }
+        match exit {
+            Some((nx, ny)) => {
+                world.move_grain(idx, nx, ny);
+                world.grains[idx].idle = 0;
+            }
+            None => world.grains[idx].land(gravity),
+        }
+    }
+}
@@ -0,0 +1,103 @@
+// Electricity, a second layer spreading over the grid beside heat. Every
+// few ticks a battery sends a spark into the metal touching it. A spark
+// moves one cell a tick through connected metal and leaves a cell of tail
+// behind it that cannot be sparked again straight away, so pulses run
+// along wires instead of back and forth. Sparks set off gunpowder and
+// switch lamps on or off when they reach them.
+
+use crate::material::Material;
+use crate::world::World;
+
+/// Ticks between the sparks a battery sends out.
+const BATTERY_PERIOD: u64 = 40;
+
+/// Cells a spark reaches from `(x, y)` in a `w` x `h` world.
+pub(crate) fn neighbours(
+    w: usize,
+    h: usize,
+    x: usize,
+    y: usize,
+) -> impl Iterator<Item = (usize, usize)> {
+    let (w, h) = (w as isize, h as isize);
+    [(-1, 0), (1, 0), (0, -1), (0, 1)].into_iter().filter_map(move |(dx, dy)| {
+        let (nx, ny) = (x as isize + dx, y as isize + dy);
+        (nx >= 0 && ny >= 0 && nx < w && ny < h).then_some((nx as usize, ny as usize))
+    })
+}
+
+/// Move every spark on by a cell and let batteries send out new ones.
+pub(crate) fn conduct(world: &mut World) {
+    let pulse = world.ticks().is_multiple_of(BATTERY_PERIOD);
+    if world.sparks.is_empty() && world.tails.is_empty() && !pulse {
+        return;
+    }
+
+    // Last tick's tails can carry a spark again once this tick's sparks have
+    // moved on, so none of them can turn back.
+    let old_tails = std::mem::take(&mut world.tails);
+
+    // This tick's sparks become tails, passing the spark to the metal and
+    // acting on whatever else they touch.
+    let heads = std::mem::take(&mut world.sparks);
+    let mut next = Vec::new();
+    let mut lamps = Vec::new();
+    for (x, y) in heads {
+        // The spark may have been erased or blown up.
+        if world.grid.get(x, y) != Material::Spark {
+            continue;
+        }
+        world.grid.set(x, y, Material::Metal);
+        world.chunks.wake(x, y);
+        world.tails.push((x, y));
+        for (nx, ny) in neighbours(world.width, world.height, x, y) {
+            match world.grid.get(nx, ny) {
+                Material::Metal if !world.charged.get(nx, ny) => next.push((nx, ny)),
+                Material::Gunpowder => world.ignite(nx, ny),
+                Material::Lamp | Material::LitLamp => lamps.push((nx, ny)),
+                _ => {}
+            }
+        }
+    }
+
+    for (x, y) in old_tails {
+        world.charged.set(x, y, false);
+    }
+
+    if pulse {
+        for y in 0..world.height {
+            for x in 0..world.width {
+                if world.grid.get(x, y) != Material::Battery {
+                    continue;
+                }
+                for (nx, ny) in neighbours(world.width, world.height, x, y) {
+                    if world.grid.get(nx, ny) == Material::Metal && !world.charged.get(nx, ny) {
+                        next.push((nx, ny));
+                    }
+                }
+            }
+        }
+    }
+
+    for (x, y) in next {
+        // Two sparks may reach the same cell.
+        if world.grid.get(x, y) != Material::Metal {
+            continue;
+        }
+        world.grid.set(x, y, Material::Spark);
+        world.charged.set(x, y, true);
+        world.chunks.wake(x, y);
+        world.sparks.push((x, y));
+    }
+
+    // A lamp touched by several sparks at once switches only once.
+    lamps.sort_unstable();
+    lamps.dedup();
+    for (x, y) in lamps {
+        let toggled = match world.grid.get(x, y) {
+            Material::Lamp => Material::LitLamp,
+            _ => Material::Lamp,
+        };
+        world.grid.set(x, y, toggled);
+        world.chunks.wake(x, y);
+    }
+}
@@ -0,0 +1,123 @@
+// Puzzles: scenes with goals. Every drain stays open, and some of them are
+// targets that must swallow a share of the grains the puzzle starts with;
+// the player may only draw walls to steer the grains into them. A puzzle is
+// solved once every target has had its share, and failed once too few
+// grains are left to make up what the targets still need.
+
+use crate::material::Material;
+use crate::scenes::{DrainSpec, Scene, Shape};
+use crate::world::World;
+
+/// A target: the scene's drain at index `drain` must swallow `share` of the
+/// grains in the world when the puzzle starts.
+pub struct Goal {
+    pub drain: usize,
+    pub share: f32,
+}
+
+/// How a puzzle is going.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Playing,
+    Solved,
+    Failed,
+}
+
+/// Every built-in puzzle.
+pub const PUZZLES: [Scene; 2] = [
+    Scene {
+        name: "ramp",
+        walls: &[],
+        fills: &[(Shape::Rect { min: (0.05, 0.05), max: (0.25, 0.25) }, Material::Sand)],
+        emitters: &[],
+        drains: &[
+            DrainSpec { x: 0.15, y: 1.0, half_width: 0.1 },
+            DrainSpec { x: 0.85, y: 1.0, half_width: 0.06 },
+        ],
+        goals: &[Goal { drain: 1, share: 0.5 }],
+    },
+    Scene {
+        name: "split",
+        walls: &[
+            Shape::Line { from: (0.35, 0.25), to: (0.35, 0.05), radius: 0.004 },
+            Shape::Line { from: (0.65, 0.25), to: (0.65, 0.05), radius: 0.004 },
+        ],
+        fills: &[(Shape::Rect { min: (0.36, 0.05), max: (0.64, 0.24) }, Material::Sand)],
+        emitters: &[],
+        drains: &[
+            DrainSpec { x: 0.1, y: 1.0, half_width: 0.05 },
+            DrainSpec { x: 0.5, y: 1.0, half_width: 0.2 },
+            DrainSpec { x: 0.9, y: 1.0, half_width: 0.05 },
+        ],
+        goals: &[Goal { drain: 0, share: 0.3 }, Goal { drain: 2, share: 0.3 }],
+    },
+];
+
+/// The built-in puzzle called `name`.
+pub fn find(name: &str) -> Option<&'static Scene> {
+    PUZZLES.iter().find(|s| s.name.eq_ignore_ascii_case(name))
+}
+
+/// A puzzle being played: its scene, and the grains each of its goals
+/// needs.
+pub struct Puzzle {
+    scene: &'static Scene,
+    needed: Vec<u64>,
+    status: Status,
+}
+
+impl Puzzle {
+    /// Lay `scene` out in `world` and start playing it.
+    pub fn start(scene: &'static Scene, world: &mut World) -> Self {
+        let mut puzzle = Self { scene, needed: Vec::new(), status: Status::Playing };
+        puzzle.restart(world);
+        puzzle
+    }
+
+    /// Lay the scene out again, throwing away whatever the player drew.
+    pub fn restart(&mut self, world: &mut World) {
+        self.scene.apply(world);
+        let grains = world.grains().len() as f32;
+        self.needed = self.scene.goals.iter().map(|g| (g.share * grains).ceil() as u64).collect();
+        self.status = Status::Playing;
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.scene.name
+    }
+
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    /// The drain behind each goal, with how many grains it has swallowed
+    /// and how many it needs.
+    pub fn progress<'a>(
+        &'a self,
+        world: &'a World,
+    ) -> impl Iterator<Item = (usize, u64, u64)> + 'a {
+        self.scene.goals.iter().zip(&self.needed).map(|(g, &needed)| {
+            let got = world.drained().get(g.drain).copied().unwrap_or(0);
+            (g.drain, got, needed)
+        })
+    }
+
+    /// Open every drain for a tick, after the world has stepped, and see
+    /// whether the puzzle has been solved or can no longer be. Once it has
+    /// been either it stays so until restarted.
+    pub fn tick(&mut self, world: &mut World) -> Status {
+        world.drain();
+        if self.status != Status::Playing {
+            return self.status;
+        }
+        let short: u64 =
+            self.progress(world).map(|(_, got, needed)| needed.saturating_sub(got)).sum();
+        let pouring = world.emitters().iter().any(|e| e.enabled);
+        if short == 0 {
+            self.status = Status::Solved;
+        } else if !pouring && (world.grains().len() as u64) < short {
+            self.status = Status::Failed;
+        }
+        self.status
+    }
+}
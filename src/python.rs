@@ -0,0 +1,211 @@
+// Python bindings, built with the `pyo3` feature into an extension module
+// also called `sandfall`, e.g. with `maturin develop`. Python gets a `World`
+// to build scenarios in and step, and the cells and temperatures as numpy
+// arrays to measure piles with. The arrays are copies taken when asked for:
+// the world moves grains around on every step, so a view kept into it
+// would change, or dangle, under the caller.
+
+use numpy::{PyArray1, PyArray2, PyArrayMethods};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::commands;
+use crate::input::InputState;
+use crate::material::Material;
+use crate::world::{Drain, Gravity, World};
+
+/// Largest seed the Marsaglia generator accepts.
+const MAX_SEED: i32 = 900_000_000;
+
+/// A world, as the Python class `sandfall.World`.
+#[pyclass(name = "World", unsendable)]
+pub struct PyWorld {
+    world: World,
+}
+
+/// The material called `name`, other than empty, or a `ValueError`.
+fn material_named(name: &str) -> PyResult<Material> {
+    Material::from_name(name)
+        .filter(|&m| m != Material::Empty)
+        .ok_or_else(|| PyValueError::new_err(format!("unknown material '{name}'")))
+}
+
+/// `radius`, if it is no bigger than `world` can use, or a `ValueError`.
+fn brush_radius(world: &World, radius: usize) -> PyResult<usize> {
+    if radius <= world.max_radius() {
+        Ok(radius)
+    } else {
+        Err(PyValueError::new_err(format!("radius {radius} is more than {}", world.max_radius())))
+    }
+}
+
+#[pymethods]
+impl PyWorld {
+    /// A `width` by `height` world of empty cells.
+    #[new]
+    #[pyo3(signature = (width, height, seed = 0))]
+    fn new(width: usize, height: usize, seed: i32) -> PyResult<Self> {
+        if width == 0 || height == 0 {
+            return Err(PyValueError::new_err("a world needs at least one cell"));
+        }
+        if !(0..=MAX_SEED).contains(&seed) {
+            return Err(PyValueError::new_err(format!("seed must be in 0..={MAX_SEED}")));
+        }
+        Ok(Self { world: World::new(width, height, seed) })
+    }
+
+    #[getter]
+    fn width(&self) -> usize {
+        self.world.width()
+    }
+
+    #[getter]
+    fn height(&self) -> usize {
+        self.world.height()
+    }
+
+    /// Ticks simulated so far.
+    #[getter]
+    fn ticks(&self) -> u64 {
+        self.world.ticks()
+    }
+
+    /// Number of grains in the world.
+    #[getter]
+    fn grains(&self) -> usize {
+        self.world.grains().len()
+    }
+
+    /// Grains swallowed by drains so far.
+    #[getter]
+    fn drained(&self) -> u64 {
+        self.world.drained_total()
+    }
+
+    /// Run `ticks` ticks, on every core if `parallel`, with the drains open
+    /// if `drain`.
+    #[pyo3(signature = (ticks = 1, parallel = false, drain = false))]
+    fn step(&mut self, ticks: usize, parallel: bool, drain: bool) {
+        let input = InputState { drain, ..InputState::NONE };
+        for _ in 0..ticks {
+            if parallel {
+                self.world.step_parallel(&input);
+            } else {
+                self.world.step(&input);
+            }
+        }
+    }
+
+    /// Pour grains of `material` into the circle of `radius` around
+    /// `(x, y)`, or fill it if the material does not move, and return how
+    /// many grains were added. Without `tries`, there is a try for every
+    /// cell of the box round the circle.
+    #[pyo3(signature = (material, x, y, radius, tries = None))]
+    fn spawn(
+        &mut self,
+        material: &str,
+        x: usize,
+        y: usize,
+        radius: usize,
+        tries: Option<usize>,
+    ) -> PyResult<usize> {
+        let material = material_named(material)?;
+        if x >= self.world.width() || y >= self.world.height() {
+            return Err(PyValueError::new_err(format!("{x},{y} is off the world")));
+        }
+        let radius = brush_radius(&self.world, radius)?;
+        let before = self.world.grains().len();
+        self.world.apply_input(&InputState {
+            mouse: Some((x, y)),
+            left: true,
+            material,
+            radius,
+            tries: tries.unwrap_or(InputState::box_tries(radius)),
+            ..InputState::NONE
+        });
+        Ok(self.world.grains().len() - before)
+    }
+
+    /// Clear the circle of `radius` around `(x, y)`.
+    fn erase(&mut self, x: i32, y: i32, radius: usize) -> PyResult<()> {
+        let radius = brush_radius(&self.world, radius)?;
+        self.world.erase(x as isize, y as isize, radius);
+        Ok(())
+    }
+
+    /// A straight wall, or line of any material, `radius` cells thick
+    /// either side.
+    #[pyo3(signature = (x0, y0, x1, y1, radius = 1, material = "wall"))]
+    fn line(
+        &mut self,
+        x0: usize,
+        y0: usize,
+        x1: usize,
+        y1: usize,
+        radius: usize,
+        material: &str,
+    ) -> PyResult<()> {
+        let material = material_named(material)?;
+        for (x, y) in [(x0, y0), (x1, y1)] {
+            if x >= self.world.width() || y >= self.world.height() {
+                return Err(PyValueError::new_err(format!("{x},{y} is off the world")));
+            }
+        }
+        let radius = brush_radius(&self.world, radius)?;
+        self.world.line((x0, y0), (x1, y1), radius, material);
+        Ok(())
+    }
+
+    /// Add a drain on row `y`, `half_width` cells either side of `x`.
+    fn add_drain(&mut self, x: usize, y: usize, half_width: usize) {
+        self.world.add_drain(Drain { x, y, half_width });
+    }
+
+    /// Point gravity `down`, `left`, `up` or `right`.
+    fn set_gravity(&mut self, direction: &str) -> PyResult<()> {
+        let gravity = match direction.to_ascii_lowercase().as_str() {
+            "down" => Gravity::Down,
+            "left" => Gravity::Left,
+            "up" => Gravity::Up,
+            "right" => Gravity::Right,
+            _ => return Err(PyValueError::new_err(format!("unknown direction '{direction}'"))),
+        };
+        self.world.set_gravity(gravity);
+        Ok(())
+    }
+
+    /// Empty the world.
+    fn clear(&mut self) {
+        self.world.clear();
+    }
+
+    /// Run a console command, such as `set gravity 0.3`, and return what it
+    /// said.
+    fn command(&mut self, line: &str) -> PyResult<String> {
+        commands::run(&mut self.world, line).map_err(PyValueError::new_err)
+    }
+
+    /// Every cell's material as its index in `sandfall.MATERIALS`, in a
+    /// `height` by `width` array of `uint8`.
+    fn cells<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray2<u8>>> {
+        let cells: Vec<u8> = self.world.cells().as_slice().iter().map(|&m| m as u8).collect();
+        PyArray1::from_vec(py, cells).reshape([self.world.height(), self.world.width()])
+    }
+
+    /// Every cell's temperature in degrees, in a `height` by `width` array
+    /// of `float32`.
+    fn heat<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray2<f32>>> {
+        let heat = self.world.heat().as_slice().to_vec();
+        PyArray1::from_vec(py, heat).reshape([self.world.height(), self.world.width()])
+    }
+}
+
+/// The `sandfall` module: the `World` class, and `MATERIALS`, the names of
+/// the materials in the order `World.cells` numbers them.
+#[pymodule]
+fn sandfall(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyWorld>()?;
+    let names: Vec<&str> = Material::ALL.iter().map(|m| m.props().name).collect();
+    m.add("MATERIALS", names)?;
+    Ok(())
+}
@@ -0,0 +1,379 @@
+// Reactions between neighbouring cells, run once per tick after movement:
+// fire and lava spreading to flammable neighbours, boiling water into steam
+// and lava setting into stone, sand soaking up water into mud, plants
+// drinking water to grow, warmed snow melting, cells with a lifetime burning
+// out, fading away or drying out, and then the world's rule table.
+
+use crate::heat;
+use crate::material::Material;
+use crate::world::{World, NO_GRAIN};
+
+/// Chance that a burnt-out fire or fuel cell leaves smoke behind.
+const SMOKE_CHANCE: f32 = 0.5;
+/// Chance per tick that burning fuel gives off a flame.
+const FLAME_CHANCE: f32 = 0.1;
+/// Temperature below which lava sets into stone.
+const SOLIDIFY: f32 = 500.0;
+/// Water next to plants and seeds is only checked every this many ticks.
+const GROW_EVERY: u64 = 8;
+/// Chance a check finds a plant drinking the water to grow a cell.
+const GROW_CHANCE: f32 = 0.3;
+/// Tallest a plant stem grows, in cells.
+const MAX_STEM: usize = 48;
+/// Chance a stem grows diagonally instead of straight up.
+const BEND_CHANCE: f32 = 0.3;
+/// Chance per tick that a water grain next to sand soaks into it.
+const SOAK_CHANCE: f32 = 0.05;
+/// Temperature above which snow melts into water.
+const MELT: f32 = 60.0;
+
+/// Age every grain with a lifetime, spread fire, cool lava, grow plants and
+/// spread heat.
+pub(crate) fn react(world: &mut World) {
+    let mut ignite = Vec::new();
+    let mut removed = false;
+    // Heat only needs spreading while something is giving it off.
+    let mut hot = false;
+    let growing = world.ticks().is_multiple_of(GROW_EVERY);
+
+    for idx in 0..world.grains.len() {
+        let g = world.grains[idx];
+        if g.material == Material::Lava {
+            hot = true;
+            // Lava cools even while it sits still, so its chunk stays awake.
+            world.chunks.wake(g.x, g.y);
+            catch_neighbours(world, g.x, g.y, &mut ignite);
+            if boil(world, g.x, g.y) || world.heat.get(g.x, g.y) < SOLIDIFY {
+                settle(world, idx, Material::Stone);
+                removed = true;
+            }
+            continue;
+        }
+        if g.material == Material::Snow {
+            if world.heat.get(g.x, g.y) > MELT {
+                transmute(world, g.x, g.y, Material::Water);
+            }
+            continue;
+        }
+        if g.material == Material::Water {
+            if soak(world, idx) {
+                removed = true;
+            } else if growing {
+                removed |= water_plants(world, idx);
+            }
+            continue;
+        }
+        // Grains are counting down if their material has a lifetime, or
+        // they are fuel that has been lit.
+        if g.life == 0 {
+            continue;
+        }
+        // Cells that are counting down must keep their chunk simulated even
+        // when they are not moving.
+        world.chunks.wake(g.x, g.y);
+
+        let burning_fuel = g.material.props().burn_time > 0;
+        if burning_fuel {
+            hot = true;
+            catch_neighbours(world, g.x, g.y, &mut ignite);
+            flame(world, g.x, g.y);
+        }
+
+        if g.material == Material::Fire {
+            hot = true;
+            // Water puts fire out.
+            if boil(world, g.x, g.y) {
+                removed |= world.clear_cell(g.x, g.y);
+                continue;
+            }
+            catch_neighbours(world, g.x, g.y, &mut ignite);
+        }
+
+        let g = &mut world.grains[idx];
+        g.life = g.life.saturating_sub(1);
+        if g.life > 0 {
+            continue;
+        }
+
+        // Run out: mud dries into sand, fire and fuel may leave smoke, and
+        // everything else vanishes.
+        let burnt = g.material == Material::Fire || burning_fuel;
+        let next = if g.material == Material::Mud {
+            Material::Sand
+        } else if burnt && world.rng.uni() < SMOKE_CHANCE {
+            Material::Smoke
+        } else {
+            Material::Empty
+        };
+        let (x, y) = (g.x, g.y);
+        if next == Material::Empty {
+            removed |= world.clear_cell(x, y);
+        } else {
+            world.grains[idx] = world.new_grain(x, y, next);
+            world.grid.set(x, y, next);
+            world.free_cell(x, y);
+        }
+    }
+
+    removed |= apply_rules(world);
+
+    if hot {
+        heat::diffuse(world, &mut ignite);
+    }
+
+    for (x, y) in ignite {
+        world.ignite(x, y);
+    }
+
+    if removed {
+        world.grains.retain(|g| g.material != Material::Empty);
+        world.reindex();
+    }
+}
+
+/// Turn grain `idx` into a static cell of `material`. The grain is marked
+/// for removal from the list.
+fn settle(world: &mut World, idx: usize, material: Material) {
+    let (x, y) = (world.grains[idx].x, world.grains[idx].y);
+    world.grains[idx].material = Material::Empty;
+    world.index.set(x, y, NO_GRAIN);
+    world.grid.set(x, y, material);
+    world.free_cell(x, y);
+}
+
+/// Try the world's rule table on every grain in an active chunk and the four
+/// cells next to it. A grain reacts with at most one neighbour a tick.
+/// Returns true if grains were marked for removal.
+fn apply_rules(world: &mut World) -> bool {
+    if world.rules.is_empty() {
+        return false;
+    }
+    let mut removed = false;
+    for idx in 0..world.grains.len() {
+        let g = world.grains[idx];
+        if g.material == Material::Empty || !world.chunks.is_active(g.x, g.y) {
+            continue;
+        }
+        'neighbours: for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+            let (nx, ny) = (g.x as isize + dx, g.y as isize + dy);
+            if !world.grid.in_bounds(nx, ny) {
+                continue;
+            }
+            let (nx, ny) = (nx as usize, ny as usize);
+            let other = world.grid.get(nx, ny);
+            for i in 0..world.rules.len() {
+                let rule = world.rules[i];
+                // Each touching pair is tried once: from the grain of `a`,
+                // or from the grain of `b` when `a` is static.
+                let (here, there) = if rule.a == g.material && rule.b == other {
+                    (rule.into_a, rule.into_b)
+                } else if !rule.a.is_movable() && rule.b == g.material && rule.a == other {
+                    (rule.into_b, rule.into_a)
+                } else {
+                    continue;
+                };
+                if world.rng.uni() < rule.chance {
+                    removed |= transmute(world, nx, ny, there);
+                    removed |= transmute(world, g.x, g.y, here);
+                    break 'neighbours;
+                }
+            }
+        }
+    }
+    removed
+}
+
+/// Turn the cell at `(x, y)` into `material`, whether it holds a grain or a
+/// static cell. Returns true if a grain was marked for removal.
+pub(crate) fn transmute(world: &mut World, x: usize, y: usize, material: Material) -> bool {
+    if world.grid.get(x, y) == material {
+        return false;
+    }
+    if material == Material::Empty {
+        return world.clear_cell(x, y);
+    }
+    let idx = world.index.get(x, y);
+    world.heat.set(x, y, material.props().temperature);
+    if idx != NO_GRAIN && material.is_movable() {
+        world.grains[idx as usize] = world.new_grain(x, y, material);
+        world.grid.set(x, y, material);
+        world.free_cell(x, y);
+        false
+    } else if idx != NO_GRAIN {
+        settle(world, idx as usize, material);
+        true
+    } else if material.is_movable() {
+        world.grid.set(x, y, Material::Empty);
+        let grain = world.new_grain(x, y, material);
+        world.add_grain(grain);
+        false
+    } else {
+        world.grid.set(x, y, material);
+        world.free_cell(x, y);
+        false
+    }
+}
+
+/// Let the water grain `idx` maybe soak into sand next to it, turning it to
+/// mud, and keep any mud next to it wet. Returns true if the water was soaked
+/// up and marked for removal.
+fn soak(world: &mut World, idx: usize) -> bool {
+    let (x, y) = (world.grains[idx].x, world.grains[idx].y);
+    for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+        let (nx, ny) = (x as isize + dx, y as isize + dy);
+        if !world.grid.in_bounds(nx, ny) {
+            continue;
+        }
+        let (nx, ny) = (nx as usize, ny as usize);
+        match world.grid.get(nx, ny) {
+            Material::Sand if world.rng.uni() < SOAK_CHANCE => {
+                let sand = world.index.get(nx, ny) as usize;
+                world.grains[sand] = world.new_grain(nx, ny, Material::Mud);
+                world.grid.set(nx, ny, Material::Mud);
+                world.chunks.wake(nx, ny);
+                return world.clear_cell(x, y);
+            }
+            Material::Mud => {
+                let mud = world.index.get(nx, ny) as usize;
+                let life = world.roll_life(Material::Mud);
+                let g = &mut world.grains[mud];
+                g.life = g.life.max(life);
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Let the water grain `idx` sprout any seeds next to it, and maybe be
+/// drunk by a plant next to it, which grows a cell at the top of its stem.
+/// Returns true if grains were marked for removal.
+fn water_plants(world: &mut World, idx: usize) -> bool {
+    let (x, y) = (world.grains[idx].x, world.grains[idx].y);
+    let mut removed = false;
+    for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+        let (nx, ny) = (x as isize + dx, y as isize + dy);
+        if !world.grid.in_bounds(nx, ny) {
+            continue;
+        }
+        let (nx, ny) = (nx as usize, ny as usize);
+        match world.grid.get(nx, ny) {
+            Material::Seed => {
+                settle(world, world.index.get(nx, ny) as usize, Material::Plant);
+                removed = true;
+            }
+            Material::Plant if world.rng.uni() < GROW_CHANCE && grow(world, nx, ny) => {
+                return world.clear_cell(x, y);
+            }
+            _ => {}
+        }
+    }
+    removed
+}
+
+/// Climb the stem of the plant cell at `(x, y)` against gravity and grow a
+/// new cell above or beside its top. Returns false if the stem is already
+/// as tall as it gets or there is no room.
+fn grow(world: &mut World, x: usize, y: usize) -> bool {
+    let (gx, gy) = world.gravity().vector();
+    let (ax, ay) = world.gravity().across();
+    let side = if world.rng.uni() < 0.5 { -1 } else { 1 };
+    // Neighbours of `(x, y)` relative to gravity, `up` cells against it and
+    // `s` cells to the side.
+    let at = |x: usize, y: usize, up: isize, s: isize| {
+        (x as isize - gx * up + ax * s, y as isize - gy * up + ay * s)
+    };
+    let is = |world: &World, (cx, cy): (isize, isize), m: Material| {
+        world.grid.in_bounds(cx, cy) && world.grid.get(cx as usize, cy as usize) == m
+    };
+
+    let (mut tx, mut ty) = (x, y);
+    let mut height = 0;
+    while let Some(next) =
+        [at(tx, ty, 1, 0), at(tx, ty, 1, side), at(tx, ty, 1, -side)]
+            .into_iter()
+            .find(|&c| is(world, c, Material::Plant))
+    {
+        height += 1;
+        if height >= MAX_STEM {
+            return false;
+        }
+        (tx, ty) = (next.0 as usize, next.1 as usize);
+    }
+
+    // Grow upwards or outwards, but only where the new cell would touch
+    // little else of the plant, so it stays leggy instead of filling in.
+    let mut spots =
+        [at(tx, ty, 1, 0), at(tx, ty, 1, side), at(tx, ty, 0, side), at(tx, ty, 0, -side)];
+    if world.rng.uni() < BEND_CHANCE {
+        spots.swap(0, 1);
+    }
+    for (cx, cy) in spots {
+        if !is(world, (cx, cy), Material::Empty) {
+            continue;
+        }
+        let touching = (-1..=1)
+            .flat_map(|dy| (-1..=1).map(move |dx| (cx + dx, cy + dy)))
+            .filter(|&c| is(world, c, Material::Plant))
+            .count();
+        if touching <= 2 {
+            world.place(cx as usize, cy as usize, Material::Plant);
+            return true;
+        }
+    }
+    false
+}
+
+/// Maybe give off a flame into the cell above the burning fuel at `(x, y)`,
+/// where above is against gravity.
+fn flame(world: &mut World, x: usize, y: usize) {
+    if world.rng.uni() >= FLAME_CHANCE {
+        return;
+    }
+    let (gx, gy) = world.gravity().vector();
+    let (fx, fy) = (x as isize - gx, y as isize - gy);
+    if world.grid.in_bounds(fx, fy) {
+        world.place(fx as usize, fy as usize, Material::Fire);
+    }
+}
+
+/// Turn the water in the four cells next to `(x, y)` into steam, and return
+/// whether there was any.
+fn boil(world: &mut World, x: usize, y: usize) -> bool {
+    let mut boiled = false;
+    for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+        let (nx, ny) = (x as isize + dx, y as isize + dy);
+        if !world.grid.in_bounds(nx, ny) {
+            continue;
+        }
+        let (nx, ny) = (nx as usize, ny as usize);
+        if world.grid.get(nx, ny) != Material::Water {
+            continue;
+        }
+        let idx = world.index.get(nx, ny) as usize;
+        world.grains[idx] = world.new_grain(nx, ny, Material::Steam);
+        world.grid.set(nx, ny, Material::Steam);
+        world.heat.set(nx, ny, Material::Steam.props().temperature);
+        world.free_cell(nx, ny);
+        boiled = true;
+    }
+    boiled
+}
+
+/// Collect the flammable neighbours of the fire or lava at `(x, y)` that catch light
+/// this tick.
+fn catch_neighbours(world: &mut World, x: usize, y: usize, ignite: &mut Vec<(usize, usize)>) {
+    for dy in -1..=1isize {
+        for dx in -1..=1isize {
+            let (nx, ny) = (x as isize + dx, y as isize + dy);
+            if (dx, dy) == (0, 0) || !world.grid.in_bounds(nx, ny) {
+                continue;
+            }
+            let (nx, ny) = (nx as usize, ny as usize);
+            let flammability = world.grid.get(nx, ny).props().flammability;
+            if flammability > 0.0 && world.rng.uni() < flammability {
+                ignite.push((nx, ny));
+            }
+        }
+    }
+}
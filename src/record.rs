@@ -0,0 +1,113 @@
+// Records the window to an animated GIF. Frames are downscaled on the main
+// thread and handed to a background thread for the slow colour quantising
+// and compression, so recording does not make the simulation stutter.
+
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use gif::{Encoder, Frame, Repeat};
+
+use sandfall::grid::Grid;
+
+/// Capture one frame in this many.
+const FRAME_EVERY: u32 = 3;
+/// Widest GIF written; larger windows are shrunk by a whole factor to fit.
+const MAX_GIF_WIDTH: usize = 600;
+/// Quantiser speed from 1 (best) to 30 (fastest).
+const QUANTISE_SPEED: i32 = 10;
+
+/// A downscaled RGBA frame and how long to show it, in hundredths of a second.
+struct Captured {
+    rgba: Vec<u8>,
+    delay: u16,
+}
+
+/// An animated GIF being written.
+pub struct Recorder {
+    path: PathBuf,
+    scale: usize,
+    width: usize,
+    height: usize,
+    frames_seen: u32,
+    last_capture: Instant,
+    tx: Sender<Captured>,
+    encoder: JoinHandle<io::Result<u32>>,
+}
+
+impl Recorder {
+    /// Start recording frames of `width` x `height` to `path`.
+    pub fn start(path: &Path, width: usize, height: usize) -> io::Result<Self> {
+        let scale = width.div_ceil(MAX_GIF_WIDTH).max(1);
+        let (gif_w, gif_h) = (width / scale, height / scale);
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder =
+            Encoder::new(file, gif_w as u16, gif_h as u16, &[]).map_err(io::Error::other)?;
+        encoder.set_repeat(Repeat::Infinite).map_err(io::Error::other)?;
+
+        let (tx, rx) = mpsc::channel::<Captured>();
+        let encoder = thread::spawn(move || {
+            let mut frames = 0;
+            for mut captured in rx {
+                let mut frame = Frame::from_rgba_speed(
+                    gif_w as u16,
+                    gif_h as u16,
+                    &mut captured.rgba,
+                    QUANTISE_SPEED,
+                );
+                frame.delay = captured.delay;
+                encoder.write_frame(&frame).map_err(io::Error::other)?;
+                frames += 1;
+            }
+            Ok(frames)
+        });
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            scale,
+            width: gif_w,
+            height: gif_h,
+            frames_seen: 0,
+            last_capture: Instant::now(),
+            tx,
+            encoder,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Offer the frame just drawn; every `FRAME_EVERY`th one is recorded.
+    pub fn capture(&mut self, frame: &Grid<u32>) {
+        self.frames_seen += 1;
+        if !(self.frames_seen - 1).is_multiple_of(FRAME_EVERY) {
+            return;
+        }
+        let now = Instant::now();
+        let delay = ((now - self.last_capture).as_millis() / 10).clamp(2, u16::MAX as u128);
+        self.last_capture = now;
+
+        let mut rgba = Vec::with_capacity(self.width * self.height * 4);
+        for y in 0..self.height {
+            let row = frame.row(y * self.scale);
+            for x in 0..self.width {
+                let [_, r, g, b] = row[x * self.scale].to_be_bytes();
+                rgba.extend_from_slice(&[r, g, b, 255]);
+            }
+        }
+        // A send only fails if the encoder thread already gave up, in which
+        // case its error is reported by `finish`.
+        let _ = self.tx.send(Captured { rgba, delay: delay as u16 });
+    }
+
+    /// Stop recording, wait for the encoder to catch up and return the
+    /// number of frames written.
+    pub fn finish(self) -> io::Result<u32> {
+        drop(self.tx);
+        self.encoder.join().map_err(|_| io::Error::other("GIF encoder panicked"))?
+    }
+}
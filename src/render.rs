@@ -1,8 +1,10 @@
 // This module contains rendering helper functions that extend minifb.
 // by Rich of maths.earth 202500308
 
+use crate::grid::Grid;
+
 /// A struct to represent an RGBA pixel.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub struct Pixel {
     pub r: u8,
     pub g: u8,
@@ -22,22 +24,79 @@ impl Pixel {
     }
 }
 
-/// Clears the given 2D pixel buffer by filling every pixel with black.
-pub fn clear_buffer(buffer: &mut [Vec<Pixel>]) {
-    for row in buffer.iter_mut() {
-        for pixel in row.iter_mut() {
-            *pixel = Pixel::new(0, 0, 0, 255);
+/// A buffer element the drawing functions can write a `Pixel` into. Drawing
+/// into a `Grid<u32>` packs 0xAARRGGBB directly, so the buffer can be handed
+/// to minifb without a conversion pass.
+pub trait PixelFormat: Copy {
+    fn from_pixel(pixel: Pixel) -> Self;
+    /// Read the element back as a `Pixel`, for blending onto it.
+    fn to_pixel(self) -> Pixel;
+}
+
+impl PixelFormat for Pixel {
+    fn from_pixel(pixel: Pixel) -> Self {
+        pixel
+    }
+
+    fn to_pixel(self) -> Pixel {
+        self
+    }
+}
+
+impl PixelFormat for u32 {
+    fn from_pixel(pixel: Pixel) -> Self {
+        pixel.to_u32()
+    }
+
+    fn to_pixel(self) -> Pixel {
+        Pixel::new((self >> 16) as u8, (self >> 8) as u8, self as u8, (self >> 24) as u8)
+    }
+}
+
+/// How a drawn colour is combined with the pixel already in the buffer.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlendMode {
+    /// Overwrite the pixel, as `draw_pixel` does.
+    Replace,
+    /// Mix the colour over the pixel by the colour's alpha, so 255 replaces
+    /// it and 0 leaves it untouched.
+    Alpha,
+    /// Add the colour, scaled by its alpha, to the pixel, saturating at
+    /// white. Good for glows.
+    Add,
+}
+
+impl BlendMode {
+    /// Combine `src` drawn over `dst`. The result keeps `dst`'s alpha
+    /// unless replacing.
+    pub fn blend(self, src: Pixel, dst: Pixel) -> Pixel {
+        let a = src.a as u32;
+        match self {
+            BlendMode::Replace => src,
+            BlendMode::Alpha => {
+                let mix = |s: u8, d: u8| ((s as u32 * a + d as u32 * (255 - a) + 127) / 255) as u8;
+                Pixel::new(mix(src.r, dst.r), mix(src.g, dst.g), mix(src.b, dst.b), dst.a)
+            }
+            BlendMode::Add => {
+                let add = |s: u8, d: u8| d.saturating_add(((s as u32 * a + 127) / 255) as u8);
+                Pixel::new(add(src.r, dst.r), add(src.g, dst.g), add(src.b, dst.b), dst.a)
+            }
         }
     }
 }
 
+/// Clears the given 2D pixel buffer by filling every pixel with black.
+pub fn clear_buffer<P: PixelFormat>(buffer: &mut Grid<P>) {
+    buffer.fill(P::from_pixel(Pixel::new(0, 0, 0, 255)));
+}
+
 /// Draw a square into the provided 2D pixel buffer.
 /// 
 /// * `x` and `y` are the top-left coordinates of the square.
 /// * `square_width` and `square_height` specify its dimensions.
 /// * `color` is the colour to draw.
-pub fn draw_square(
-    buffer: &mut [Vec<Pixel>],
+pub fn draw_square<P: PixelFormat>(
+    buffer: &mut Grid<P>,
     x: usize,
     y: usize,
     square_width: usize,
@@ -47,29 +106,44 @@ pub fn draw_square(
     for j in y..(y + square_height) {
         for i in x..(x + square_width) {
             // Ensure we remain within bounds.
-            if j < buffer.len() && i < buffer[j].len() {
-                buffer[j][i] = color;
+            if j < buffer.height() && i < buffer.width() {
+                buffer.set(i, j, P::from_pixel(color));
             }
         }
     }
 }
 
 /// Draw a single pixel into the provided 2D pixel buffer.
-pub fn draw_pixel(
-    buffer: &mut [Vec<Pixel>], 
+pub fn draw_pixel<P: PixelFormat>(
+    buffer: &mut Grid<P>, 
     x: usize, 
     y: usize, 
     color: 
     Pixel
 ) {
-    if y < buffer.len() && x < buffer[y].len() {
-        buffer[y][x] = color;
+    if y < buffer.height() && x < buffer.width() {
+        buffer.set(x, y, P::from_pixel(color));
+    }
+}
+
+/// Draw a single pixel into the provided 2D pixel buffer, combining it with
+/// the pixel already there according to `mode`.
+pub fn draw_pixel_blend<P: PixelFormat>(
+    buffer: &mut Grid<P>,
+    x: usize,
+    y: usize,
+    color: Pixel,
+    mode: BlendMode,
+) {
+    if y < buffer.height() && x < buffer.width() {
+        let dst = buffer.get(x, y).to_pixel();
+        buffer.set(x, y, P::from_pixel(mode.blend(color, dst)));
     }
 }
 
 /// Draw a line in to the provided 2D pixel buffer.
-pub fn draw_line(
-    buffer: &mut [Vec<Pixel>],
+pub fn draw_line<P: PixelFormat>(
+    buffer: &mut Grid<P>,
     x0: i32,
     y0: i32,
     x1: i32,
@@ -111,9 +185,65 @@ pub fn draw_line(
     }
 }
 
+/// Draw an anti-aliased line in to the provided 2D pixel buffer using Xiaolin
+/// Wu's algorithm. Each step along the major axis covers the two pixels the
+/// line passes between, blended by how much of the line falls in each, and
+/// the ends are faded by how far they reach into their pixel.
+pub fn draw_line_aa<P: PixelFormat>(
+    buffer: &mut Grid<P>,
+    x0: f32,
+    y0: f32,
+    x1: f32,
+    y1: f32,
+    color: Pixel,
+) {
+    let steep = (y1 - y0).abs() > (x1 - x0).abs();
+    // Work along x, swapping axes for steep lines and ends for leftward ones.
+    let (mut x0, mut y0, mut x1, mut y1) = if steep { (y0, x0, y1, x1) } else { (x0, y0, x1, y1) };
+    if x0 > x1 {
+        std::mem::swap(&mut x0, &mut x1);
+        std::mem::swap(&mut y0, &mut y1);
+    }
+    let dx = x1 - x0;
+    let gradient = if dx == 0.0 { 1.0 } else { (y1 - y0) / dx };
+
+    // Blend `color` at `(x, y)` along the major axis with the given coverage.
+    let mut cover = |x: i32, y: i32, coverage: f32| {
+        let a = (color.a as f32 * coverage.clamp(0.0, 1.0)).round() as u8;
+        let (px, py) = if steep { (y, x) } else { (x, y) };
+        plot_blend(buffer, px, py, Pixel { a, ..color }, BlendMode::Alpha);
+    };
+
+    // The two ends: how far each reaches into its pixel scales it.
+    let mut ends = [(0, 0.0), (0, 0.0)];
+    for (i, (x, y, reach)) in [(x0, y0, 1.0 - (x0 + 0.5).fract()), (x1, y1, (x1 + 0.5).fract())]
+        .into_iter()
+        .enumerate()
+    {
+        let xend = x.round();
+        let yend = y + gradient * (xend - x);
+        let ypx = yend.floor();
+        let f = yend - ypx;
+        cover(xend as i32, ypx as i32, (1.0 - f) * reach);
+        cover(xend as i32, ypx as i32 + 1, f * reach);
+        ends[i] = (xend as i32, yend);
+    }
+
+    // Everything between them.
+    let mut y = ends[0].1 + gradient;
+    for x in ends[0].0 + 1..ends[1].0 {
+        let ypx = y.floor();
+        let f = y - ypx;
+        cover(x, ypx as i32, 1.0 - f);
+        cover(x, ypx as i32 + 1, f);
+        y += gradient;
+    }
+}
+
 /// Draw a triangle in to the provided 2D pixel buffer.
-pub fn draw_triangle(
-    buffer: &mut [Vec<Pixel>],
+#[allow(clippy::too_many_arguments)]
+pub fn draw_triangle<P: PixelFormat>(
+    buffer: &mut Grid<P>,
     x0: i32,
     y0: i32,
     x1: i32,
@@ -127,9 +257,59 @@ pub fn draw_triangle(
     draw_line(buffer, x2, y2, x0, y0, color);
 }
 
+/// Draw a filled triangle in to the provided 2D pixel buffer. Pixels on the
+/// edges are included, so it covers the same outline `draw_triangle` draws.
+/// Works for either winding and clips to the buffer.
+#[allow(clippy::too_many_arguments)]
+pub fn fill_triangle<P: PixelFormat>(
+    buffer: &mut Grid<P>,
+    x0: i32,
+    y0: i32,
+    x1: i32,
+    y1: i32,
+    x2: i32,
+    y2: i32,
+    color: Pixel,
+) {
+    // Twice the signed area of the triangle a, b, p: which side of the
+    // edge a-b the point p is on.
+    fn edge(ax: i64, ay: i64, bx: i64, by: i64, px: i64, py: i64) -> i64 {
+        (bx - ax) * (py - ay) - (by - ay) * (px - ax)
+    }
+    let (ax, ay, bx, by, cx, cy) =
+        (x0 as i64, y0 as i64, x1 as i64, y1 as i64, x2 as i64, y2 as i64);
+    let area = edge(ax, ay, bx, by, cx, cy);
+    if area == 0 {
+        // Degenerate: all three points on a line.
+        draw_triangle(buffer, x0, y0, x1, y1, x2, y2, color);
+        return;
+    }
+    let sign = area.signum();
+
+    // Bounding box clipped to the buffer.
+    let min_x = x0.min(x1).min(x2).max(0);
+    let max_x = x0.max(x1).max(x2).min(buffer.width() as i32 - 1);
+    let min_y = y0.min(y1).min(y2).max(0);
+    let max_y = y0.max(y1).max(y2).min(buffer.height() as i32 - 1);
+    let value = P::from_pixel(color);
+    for y in min_y..=max_y {
+        let row = buffer.row_mut(y as usize);
+        let py = y as i64;
+        for x in min_x..=max_x {
+            let px = x as i64;
+            let w0 = edge(bx, by, cx, cy, px, py) * sign;
+            let w1 = edge(cx, cy, ax, ay, px, py) * sign;
+            let w2 = edge(ax, ay, bx, by, px, py) * sign;
+            if w0 >= 0 && w1 >= 0 && w2 >= 0 {
+                row[x as usize] = value;
+            }
+        }
+    }
+}
+
 /// Draw a rectangle in to the provided 2D pixel buffer.
-pub fn draw_rect(
-    buffer: &mut [Vec<Pixel>],
+pub fn draw_rect<P: PixelFormat>(
+    buffer: &mut Grid<P>,
     x: i32,
     y: i32,
     width: i32,
@@ -142,27 +322,290 @@ pub fn draw_rect(
     draw_line(buffer, x, y + height, x, y, color); 
 }
 
-/// Converts a 2D pixel buffer into a 1D vector of u32 values (0xAARRGGBB).
-pub fn buffer_to_u32(buffer: &Vec<Vec<Pixel>>) -> Vec<u32> {
-    let mut flat: Vec<u32> = Vec::with_capacity(buffer.len() * buffer[0].len());
-    for row in buffer {
-        for &pixel in row {
-            flat.push(pixel.to_u32());
+/// Draw a rectangle outline in to the provided 2D pixel buffer, blended by
+/// `mode`. Each pixel is drawn once, so translucent corners come out no
+/// darker than the edges.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_rect_blend<P: PixelFormat>(
+    buffer: &mut Grid<P>,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+    color: Pixel,
+    mode: BlendMode,
+) {
+    let (x0, x1) = (x.min(x + width), x.max(x + width));
+    let (y0, y1) = (y.min(y + height), y.max(y + height));
+    for px in x0..=x1 {
+        plot_blend(buffer, px, y0, color, mode);
+        if y1 != y0 {
+            plot_blend(buffer, px, y1, color, mode);
+        }
+    }
+    for py in y0 + 1..y1 {
+        plot_blend(buffer, x0, py, color, mode);
+        if x1 != x0 {
+            plot_blend(buffer, x1, py, color, mode);
         }
     }
-    flat
 }
 
-/// Fills `out` with 0xAARRGGBB words converted from `buffer`.
-/// `out.len()` **must equal** buffer.len() * buffer[0].len().
-pub fn buffer_to_u32_in_place(buffer: &[Vec<Pixel>], out: &mut [u32]) {
-    debug_assert_eq!(out.len(), buffer.len() * buffer[0].len());
+/// Fill the area of one colour around `(x, y)` in the provided 2D pixel
+/// buffer with `color`, stopping at pixels of any other colour.
+pub fn flood_fill<P: PixelFormat + PartialEq>(
+    buffer: &mut Grid<P>,
+    x: usize,
+    y: usize,
+    color: Pixel,
+) {
+    if y < buffer.height() && x < buffer.width() {
+        buffer.flood_fill(x, y, P::from_pixel(color));
+    }
+}
 
-    let mut i = 0;
-    for row in buffer {
-        for &pix in row {
-            out[i] = pix.to_u32();
-            i += 1;
+/// Draw a pixel at signed coordinates, skipping anything off the buffer.
+fn plot<P: PixelFormat>(buffer: &mut Grid<P>, x: i32, y: i32, color: Pixel) {
+    if x >= 0 && y >= 0 {
+        draw_pixel(buffer, x as usize, y as usize, color);
+    }
+}
+
+/// Blend a pixel at signed coordinates, skipping anything off the buffer.
+fn plot_blend<P: PixelFormat>(
+    buffer: &mut Grid<P>,
+    x: i32,
+    y: i32,
+    color: Pixel,
+    mode: BlendMode,
+) {
+    if x >= 0 && y >= 0 {
+        draw_pixel_blend(buffer, x as usize, y as usize, color, mode);
+    }
+}
+
+/// Offsets from the centre of the points on a circle outline of `radius`,
+/// found with the midpoint algorithm. Each point appears once.
+fn circle_points(radius: i32) -> Vec<(i32, i32)> {
+    let mut points = Vec::new();
+    let mut x = radius;
+    let mut y = 0;
+    let mut err = 1 - radius;
+
+    // Walk one octant and mirror each point into the other seven.
+    while x >= y {
+        points.extend([
+            (x, y), (y, x), (-y, x), (-x, y),
+            (-x, -y), (-y, -x), (y, -x), (x, -y),
+        ]);
+        y += 1;
+        if err < 0 {
+            err += 2 * y + 1;
+        } else {
+            x -= 1;
+            err += 2 * (y - x) + 1;
         }
     }
+    // Points on the axes and diagonals are mirrored onto themselves.
+    points.sort_unstable();
+    points.dedup();
+    points
+}
+
+/// Draw a circle outline in to the provided 2D pixel buffer using the
+/// midpoint algorithm.
+pub fn draw_circle<P: PixelFormat>(
+    buffer: &mut Grid<P>,
+    cx: i32,
+    cy: i32,
+    radius: i32,
+    color: Pixel,
+) {
+    for (px, py) in circle_points(radius) {
+        plot(buffer, cx + px, cy + py, color);
+    }
+}
+
+/// Draw a circle outline in to the provided 2D pixel buffer, blended by
+/// `mode`.
+pub fn draw_circle_blend<P: PixelFormat>(
+    buffer: &mut Grid<P>,
+    cx: i32,
+    cy: i32,
+    radius: i32,
+    color: Pixel,
+    mode: BlendMode,
+) {
+    for (px, py) in circle_points(radius) {
+        plot_blend(buffer, cx + px, cy + py, color, mode);
+    }
+}
+
+/// Draw a filled circle in to the provided 2D pixel buffer, one horizontal
+/// span per row, clipped to the buffer.
+pub fn draw_filled_circle<P: PixelFormat>(
+    buffer: &mut Grid<P>,
+    cx: i32,
+    cy: i32,
+    radius: i32,
+    color: Pixel,
+) {
+    for dy in -radius..=radius {
+        let y = cy + dy;
+        if y < 0 || y as usize >= buffer.height() {
+            continue;
+        }
+        let row = buffer.row_mut(y as usize);
+        let half = ((radius * radius - dy * dy) as f32).sqrt() as i32;
+        let x0 = (cx - half).max(0);
+        let x1 = (cx + half).min(row.len() as i32 - 1);
+        if x0 <= x1 {
+            row[x0 as usize..=x1 as usize].fill(P::from_pixel(color));
+        }
+    }
+}
+
+/// Horizontal advance of one character drawn by `draw_text`.
+pub const GLYPH_WIDTH: usize = 8;
+/// Vertical advance of one line drawn by `draw_text`.
+pub const GLYPH_HEIGHT: usize = 9;
+
+/// 8x8 glyphs for every printable ASCII character, ' ' through '~'. Each
+/// byte is one row, top first, with bit 7 as the leftmost column. Capitals
+/// stand on row 6 and descenders use row 7.
+const FONT: [[u8; 8]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x10, 0x10, 0x10, 0x10, 0x10, 0x00, 0x10, 0x00], // '!'
+    [0x28, 0x28, 0x28, 0x00, 0x00, 0x00, 0x00, 0x00], // '"'
+    [0x28, 0x28, 0x7C, 0x28, 0x7C, 0x28, 0x28, 0x00], // '#'
+    [0x10, 0x3C, 0x50, 0x38, 0x14, 0x78, 0x10, 0x00], // '$'
+    [0x60, 0x64, 0x08, 0x10, 0x20, 0x4C, 0x0C, 0x00], // '%'
+    [0x30, 0x48, 0x50, 0x20, 0x54, 0x48, 0x34, 0x00], // '&'
+    [0x30, 0x10, 0x20, 0x00, 0x00, 0x00, 0x00, 0x00], // '\''
+    [0x08, 0x10, 0x20, 0x20, 0x20, 0x10, 0x08, 0x00], // '('
+    [0x20, 0x10, 0x08, 0x08, 0x08, 0x10, 0x20, 0x00], // ')'
+    [0x00, 0x10, 0x54, 0x38, 0x54, 0x10, 0x00, 0x00], // '*'
+    [0x00, 0x10, 0x10, 0x7C, 0x10, 0x10, 0x00, 0x00], // '+'
+    [0x00, 0x00, 0x00, 0x00, 0x30, 0x10, 0x20, 0x00], // ','
+    [0x00, 0x00, 0x00, 0x7C, 0x00, 0x00, 0x00, 0x00], // '-'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x30, 0x30, 0x00], // '.'
+    [0x00, 0x04, 0x08, 0x10, 0x20, 0x40, 0x00, 0x00], // '/'
+    [0x38, 0x44, 0x4C, 0x54, 0x64, 0x44, 0x38, 0x00], // '0'
+    [0x10, 0x30, 0x10, 0x10, 0x10, 0x10, 0x38, 0x00], // '1'
+    [0x38, 0x44, 0x04, 0x08, 0x10, 0x20, 0x7C, 0x00], // '2'
+    [0x7C, 0x08, 0x10, 0x08, 0x04, 0x44, 0x38, 0x00], // '3'
+    [0x08, 0x18, 0x28, 0x48, 0x7C, 0x08, 0x08, 0x00], // '4'
+    [0x7C, 0x40, 0x78, 0x04, 0x04, 0x44, 0x38, 0x00], // '5'
+    [0x18, 0x20, 0x40, 0x78, 0x44, 0x44, 0x38, 0x00], // '6'
+    [0x7C, 0x04, 0x08, 0x10, 0x20, 0x20, 0x20, 0x00], // '7'
+    [0x38, 0x44, 0x44, 0x38, 0x44, 0x44, 0x38, 0x00], // '8'
+    [0x38, 0x44, 0x44, 0x3C, 0x04, 0x08, 0x30, 0x00], // '9'
+    [0x00, 0x30, 0x30, 0x00, 0x30, 0x30, 0x00, 0x00], // ':'
+    [0x00, 0x30, 0x30, 0x00, 0x30, 0x10, 0x20, 0x00], // ';'
+    [0x08, 0x10, 0x20, 0x40, 0x20, 0x10, 0x08, 0x00], // '<'
+    [0x00, 0x00, 0x7C, 0x00, 0x7C, 0x00, 0x00, 0x00], // '='
+    [0x20, 0x10, 0x08, 0x04, 0x08, 0x10, 0x20, 0x00], // '>'
+    [0x38, 0x44, 0x04, 0x08, 0x10, 0x00, 0x10, 0x00], // '?'
+    [0x38, 0x44, 0x04, 0x34, 0x54, 0x54, 0x38, 0x00], // '@'
+    [0x38, 0x44, 0x44, 0x44, 0x7C, 0x44, 0x44, 0x00], // 'A'
+    [0x78, 0x44, 0x44, 0x78, 0x44, 0x44, 0x78, 0x00], // 'B'
+    [0x38, 0x44, 0x40, 0x40, 0x40, 0x44, 0x38, 0x00], // 'C'
+    [0x70, 0x48, 0x44, 0x44, 0x44, 0x48, 0x70, 0x00], // 'D'
+    [0x7C, 0x40, 0x40, 0x78, 0x40, 0x40, 0x7C, 0x00], // 'E'
+    [0x7C, 0x40, 0x40, 0x78, 0x40, 0x40, 0x40, 0x00], // 'F'
+    [0x38, 0x44, 0x40, 0x5C, 0x44, 0x44, 0x3C, 0x00], // 'G'
+    [0x44, 0x44, 0x44, 0x7C, 0x44, 0x44, 0x44, 0x00], // 'H'
+    [0x38, 0x10, 0x10, 0x10, 0x10, 0x10, 0x38, 0x00], // 'I'
+    [0x1C, 0x08, 0x08, 0x08, 0x08, 0x48, 0x30, 0x00], // 'J'
+    [0x44, 0x48, 0x50, 0x60, 0x50, 0x48, 0x44, 0x00], // 'K'
+    [0x40, 0x40, 0x40, 0x40, 0x40, 0x40, 0x7C, 0x00], // 'L'
+    [0x44, 0x6C, 0x54, 0x54, 0x44, 0x44, 0x44, 0x00], // 'M'
+    [0x44, 0x44, 0x64, 0x54, 0x4C, 0x44, 0x44, 0x00], // 'N'
+    [0x38, 0x44, 0x44, 0x44, 0x44, 0x44, 0x38, 0x00], // 'O'
+    [0x78, 0x44, 0x44, 0x78, 0x40, 0x40, 0x40, 0x00], // 'P'
+    [0x38, 0x44, 0x44, 0x44, 0x54, 0x48, 0x34, 0x00], // 'Q'
+    [0x78, 0x44, 0x44, 0x78, 0x50, 0x48, 0x44, 0x00], // 'R'
+    [0x3C, 0x40, 0x40, 0x38, 0x04, 0x04, 0x78, 0x00], // 'S'
+    [0x7C, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x00], // 'T'
+    [0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x38, 0x00], // 'U'
+    [0x44, 0x44, 0x44, 0x44, 0x44, 0x28, 0x10, 0x00], // 'V'
+    [0x44, 0x44, 0x44, 0x54, 0x54, 0x54, 0x28, 0x00], // 'W'
+    [0x44, 0x44, 0x28, 0x10, 0x28, 0x44, 0x44, 0x00], // 'X'
+    [0x44, 0x44, 0x44, 0x28, 0x10, 0x10, 0x10, 0x00], // 'Y'
+    [0x7C, 0x04, 0x08, 0x10, 0x20, 0x40, 0x7C, 0x00], // 'Z'
+    [0x38, 0x20, 0x20, 0x20, 0x20, 0x20, 0x38, 0x00], // '['
+    [0x00, 0x40, 0x20, 0x10, 0x08, 0x04, 0x00, 0x00], // '\\'
+    [0x38, 0x08, 0x08, 0x08, 0x08, 0x08, 0x38, 0x00], // ']'
+    [0x10, 0x28, 0x44, 0x00, 0x00, 0x00, 0x00, 0x00], // '^'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x7C], // '_'
+    [0x20, 0x10, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00], // '`'
+    [0x00, 0x00, 0x38, 0x04, 0x3C, 0x44, 0x3C, 0x00], // 'a'
+    [0x40, 0x40, 0x58, 0x64, 0x44, 0x44, 0x78, 0x00], // 'b'
+    [0x00, 0x00, 0x38, 0x40, 0x40, 0x44, 0x38, 0x00], // 'c'
+    [0x04, 0x04, 0x34, 0x4C, 0x44, 0x44, 0x3C, 0x00], // 'd'
+    [0x00, 0x00, 0x38, 0x44, 0x7C, 0x40, 0x38, 0x00], // 'e'
+    [0x18, 0x24, 0x20, 0x70, 0x20, 0x20, 0x20, 0x00], // 'f'
+    [0x00, 0x00, 0x3C, 0x44, 0x44, 0x3C, 0x04, 0x38], // 'g'
+    [0x40, 0x40, 0x58, 0x64, 0x44, 0x44, 0x44, 0x00], // 'h'
+    [0x10, 0x00, 0x30, 0x10, 0x10, 0x10, 0x38, 0x00], // 'i'
+    [0x08, 0x00, 0x18, 0x08, 0x08, 0x08, 0x48, 0x30], // 'j'
+    [0x40, 0x40, 0x48, 0x50, 0x60, 0x50, 0x48, 0x00], // 'k'
+    [0x30, 0x10, 0x10, 0x10, 0x10, 0x10, 0x38, 0x00], // 'l'
+    [0x00, 0x00, 0x68, 0x54, 0x54, 0x44, 0x44, 0x00], // 'm'
+    [0x00, 0x00, 0x58, 0x64, 0x44, 0x44, 0x44, 0x00], // 'n'
+    [0x00, 0x00, 0x38, 0x44, 0x44, 0x44, 0x38, 0x00], // 'o'
+    [0x00, 0x00, 0x78, 0x44, 0x44, 0x78, 0x40, 0x40], // 'p'
+    [0x00, 0x00, 0x3C, 0x44, 0x44, 0x3C, 0x04, 0x04], // 'q'
+    [0x00, 0x00, 0x58, 0x64, 0x40, 0x40, 0x40, 0x00], // 'r'
+    [0x00, 0x00, 0x38, 0x40, 0x38, 0x04, 0x78, 0x00], // 's'
+    [0x20, 0x20, 0x70, 0x20, 0x20, 0x24, 0x18, 0x00], // 't'
+    [0x00, 0x00, 0x44, 0x44, 0x44, 0x4C, 0x34, 0x00], // 'u'
+    [0x00, 0x00, 0x44, 0x44, 0x44, 0x28, 0x10, 0x00], // 'v'
+    [0x00, 0x00, 0x44, 0x44, 0x54, 0x54, 0x28, 0x00], // 'w'
+    [0x00, 0x00, 0x44, 0x28, 0x10, 0x28, 0x44, 0x00], // 'x'
+    [0x00, 0x00, 0x44, 0x44, 0x44, 0x3C, 0x04, 0x38], // 'y'
+    [0x00, 0x00, 0x7C, 0x08, 0x10, 0x20, 0x7C, 0x00], // 'z'
+    [0x08, 0x10, 0x10, 0x20, 0x10, 0x10, 0x08, 0x00], // '{'
+    [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x00], // '|'
+    [0x20, 0x10, 0x10, 0x08, 0x10, 0x10, 0x20, 0x00], // '}'
+    [0x00, 0x00, 0x20, 0x54, 0x08, 0x00, 0x00, 0x00], // '~'
+];
+
+/// Draw `text` into the provided 2D pixel buffer with its top-left corner at
+/// `(x, y)`. `\n` starts a new line and characters outside printable ASCII
+/// are drawn as `?`.
+pub fn draw_text<P: PixelFormat>(
+    buffer: &mut Grid<P>,
+    x: usize,
+    y: usize,
+    text: &str,
+    color: Pixel,
+) {
+    let (mut cx, mut cy) = (x, y);
+    for ch in text.chars() {
+        if ch == '\n' {
+            cx = x;
+            cy += GLYPH_HEIGHT;
+            continue;
+        }
+        let code = ch as usize;
+        let glyph = if (0x20..0x20 + FONT.len()).contains(&code) {
+            &FONT[code - 0x20]
+        } else {
+            &FONT['?' as usize - 0x20]
+        };
+        for (row, bits) in glyph.iter().enumerate() {
+            for col in 0..8 {
+                if bits & (0x80 >> col) != 0 {
+                    draw_pixel(buffer, cx + col, cy + row, color);
+                }
+            }
+        }
+        cx += GLYPH_WIDTH;
+    }
+}
+
+/// Converts a 2D pixel buffer into a 1D vector of u32 values (0xAARRGGBB).
+pub fn buffer_to_u32(buffer: &Grid<Pixel>) -> Vec<u32> {
+    buffer.as_slice().iter().map(|pixel| pixel.to_u32()).collect()
 }
@@ -0,0 +1,47 @@
+// Rewinding the last few seconds of simulation. Every few ticks the world
+// is written into memory in the save format, which stores the grid as runs
+// and each grain as its position, so a snapshot of a mostly settled world is
+// small. Holding the rewind key loads them back newest first.
+
+use std::collections::VecDeque;
+
+use sandfall::{persist, World};
+
+/// Ticks between snapshots.
+const SNAPSHOT_TICKS: u64 = 4;
+/// Snapshots kept: five seconds at 120 ticks a second.
+const MAX_SNAPSHOTS: usize = 150;
+
+/// Recent snapshots of the world, oldest first.
+#[derive(Default)]
+pub struct Rewind {
+    snapshots: VecDeque<Vec<u8>>,
+}
+
+impl Rewind {
+    /// Take a snapshot of `world` if one is due, after it has been stepped.
+    pub fn record(&mut self, world: &World) {
+        if !world.ticks().is_multiple_of(SNAPSHOT_TICKS) {
+            return;
+        }
+        // Reuse the oldest snapshot's memory once the buffer is full.
+        let mut bytes = if self.snapshots.len() == MAX_SNAPSHOTS {
+            self.snapshots.pop_front().unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        bytes.clear();
+        persist::write_world(world, &mut bytes).expect("writing to memory cannot fail");
+        self.snapshots.push_back(bytes);
+    }
+
+    /// Put `world` back as it was at the newest snapshot, and forget it.
+    /// Returns false if there is nothing left to rewind.
+    pub fn step_back(&mut self, world: &mut World) -> bool {
+        let Some(bytes) = self.snapshots.pop_back() else {
+            return false;
+        };
+        persist::read_world(world, &mut bytes.as_slice()).expect("snapshots are valid saves");
+        true
+    }
+}
@@ -0,0 +1,93 @@
+// Reactions given as data rather than code: a table of rules saying what a
+// pair of touching materials turn into, and how likely that is each tick.
+// Rules are read from TOML, so new reactions can be added without
+// recompiling, and are applied after the built-in reactions each tick.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::material::Material;
+
+/// When a cell of `a` touches a cell of `b`, turn them into `into_a` and
+/// `into_b` with chance `chance` per tick.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rule {
+    pub a: Material,
+    pub b: Material,
+    pub into_a: Material,
+    pub into_b: Material,
+    pub chance: f32,
+}
+
+/// A rule as written in the file, with materials by name.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRule {
+    a: String,
+    b: String,
+    into_a: String,
+    into_b: String,
+    #[serde(default = "always")]
+    chance: f32,
+}
+
+fn always() -> f32 {
+    1.0
+}
+
+/// The file's layout: a list of `[[reaction]]` tables.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Table {
+    #[serde(default)]
+    reaction: Vec<RawRule>,
+}
+
+/// Parse a rule table from TOML text.
+pub fn parse(text: &str) -> Result<Vec<Rule>, String> {
+    let table: Table = toml::from_str(text).map_err(|e| e.to_string())?;
+    table
+        .reaction
+        .iter()
+        .map(|raw| rule(&raw.a, &raw.b, &raw.into_a, &raw.into_b, raw.chance))
+        .collect()
+}
+
+/// The rule turning touching `a` and `b`, by material name, into `into_a`
+/// and `into_b` with chance `chance` per tick, if it is one that can happen.
+pub fn rule(a: &str, b: &str, into_a: &str, into_b: &str, chance: f32) -> Result<Rule, String> {
+    let material = |name: &str| {
+        Material::from_name(name).ok_or_else(|| format!("unknown material `{name}`"))
+    };
+    let rule = Rule {
+        a: material(a)?,
+        b: material(b)?,
+        into_a: material(into_a)?,
+        into_b: material(into_b)?,
+        chance,
+    };
+    if !(0.0..=1.0).contains(&rule.chance) {
+        return Err(format!("{a} + {b}: chance must be from 0 to 1"));
+    }
+    if rule.a == Material::Empty || rule.b == Material::Empty {
+        return Err(format!("{a} + {b}: empty cells cannot react"));
+    }
+    // Only grains are scanned for neighbours, so two static materials would
+    // never be seen touching.
+    if !rule.a.is_movable() && !rule.b.is_movable() {
+        return Err(format!("{a} + {b}: one material must be able to move"));
+    }
+    Ok(rule)
+}
+
+/// Read the rule table at `path`, or no rules if there is no such file.
+pub fn load(path: &Path) -> Result<Vec<Rule>, String> {
+    match fs::read_to_string(path) {
+        Ok(text) => parse(&text).map_err(|e| format!("{}: {e}", path.display())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(format!("{}: {e}", path.display())),
+    }
+}
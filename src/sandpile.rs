@@ -0,0 +1,113 @@
+// The abelian sandpile of Bak, Tang and Wiesenfeld. Every cell holds a count
+// of grains, and a cell holding four or more topples, passing one grain to
+// each of its four neighbours; grains passed off the edge are lost. Dropping
+// grains on one spot and letting the pile settle grows the well-known
+// fractal pattern. Has nothing to do with `World`.
+
+use crate::grid::Grid;
+use crate::render::Pixel;
+
+/// Grains at which a cell topples.
+pub const TOPPLE_AT: u32 = 4;
+
+/// Colours for cells holding 0, 1, 2 and 3 grains, then for cells still
+/// waiting to topple.
+pub const PALETTE: [Pixel; TOPPLE_AT as usize + 1] = [
+    Pixel { r: 0, g: 0, b: 0, a: 255 },
+    Pixel { r: 40, g: 70, b: 160, a: 255 },
+    Pixel { r: 90, g: 170, b: 200, a: 255 },
+    Pixel { r: 240, g: 210, b: 120, a: 255 },
+    Pixel { r: 255, g: 255, b: 255, a: 255 },
+];
+
+/// A grid of grain counts.
+pub struct Sandpile {
+    counts: Grid<u32>,
+    // Cells that may hold `TOPPLE_AT` or more grains.
+    unstable: Vec<(usize, usize)>,
+    topples: u64,
+}
+
+impl Sandpile {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self { counts: Grid::new(width, height, 0), unstable: Vec::new(), topples: 0 }
+    }
+
+    pub fn width(&self) -> usize {
+        self.counts.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.counts.height()
+    }
+
+    /// Grains in each cell.
+    pub fn counts(&self) -> &Grid<u32> {
+        &self.counts
+    }
+
+    /// Topples so far.
+    pub fn topples(&self) -> u64 {
+        self.topples
+    }
+
+    /// True if no cell is waiting to topple.
+    pub fn is_stable(&self) -> bool {
+        self.unstable.is_empty()
+    }
+
+    /// Drop `grains` onto cell `(x, y)`. Nothing topples until `relax`.
+    pub fn add(&mut self, x: usize, y: usize, grains: u32) {
+        let before = self.counts.get(x, y);
+        self.counts.set(x, y, before + grains);
+        if before < TOPPLE_AT && before + grains >= TOPPLE_AT {
+            self.unstable.push((x, y));
+        }
+    }
+
+    /// Empty every cell.
+    pub fn clear(&mut self) {
+        self.counts.fill(0);
+        self.unstable.clear();
+    }
+
+    /// Topple unstable cells until the pile is stable or `budget` cells have
+    /// toppled, and return whether it is stable. The order cells topple in
+    /// does not change where the pile ends up.
+    pub fn relax(&mut self, budget: usize) -> bool {
+        let (w, h) = (self.width(), self.height());
+        for _ in 0..budget {
+            let Some((x, y)) = self.unstable.pop() else {
+                return true;
+            };
+            let count = self.counts.get(x, y);
+            if count < TOPPLE_AT {
+                continue;
+            }
+            // A cell holding several loads topples them all at once.
+            let loads = count / TOPPLE_AT;
+            self.counts.set(x, y, count % TOPPLE_AT);
+            self.topples += 1;
+            for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                let (nx, ny) = (x as isize + dx, y as isize + dy);
+                if nx < 0 || ny < 0 || nx as usize >= w || ny as usize >= h {
+                    continue;
+                }
+                let (nx, ny) = (nx as usize, ny as usize);
+                let before = self.counts.get(nx, ny);
+                self.counts.set(nx, ny, before + loads);
+                // Only queue a neighbour as it crosses the threshold, so no
+                // cell is on the list twice.
+                if before < TOPPLE_AT && before + loads >= TOPPLE_AT {
+                    self.unstable.push((nx, ny));
+                }
+            }
+        }
+        self.is_stable()
+    }
+
+    /// The colour to draw a cell holding `count` grains.
+    pub fn color(count: u32) -> Pixel {
+        PALETTE[count.min(TOPPLE_AT) as usize]
+    }
+}
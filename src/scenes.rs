@@ -0,0 +1,230 @@
+// Built-in starting layouts. Each scene is plain data: wall shapes, areas
+// to fill with grains, emitters and drains, with positions given as fractions of the
+// world size so a scene fits whatever size the world is. A puzzle is a scene
+// with goals as well.
+
+use crate::material::Material;
+use crate::puzzle::Goal;
+use crate::world::{Drain, Emitter, World};
+
+/// A point as fractions of the world's width and height.
+pub type Point = (f32, f32);
+
+/// An area of cells.
+pub enum Shape {
+    /// A thick line between two points; `radius` is a fraction of the
+    /// world's width.
+    Line { from: Point, to: Point, radius: f32 },
+    /// Every cell inside a triangle.
+    Triangle { a: Point, b: Point, c: Point },
+    /// Every cell inside an axis-aligned box.
+    Rect { min: Point, max: Point },
+    /// A staggered board of round pegs filling a box, every other row
+    /// shifted half a gap.
+    Pegs { min: Point, max: Point, cols: usize, rows: usize, radius: f32 },
+}
+
+/// A drain on the row at fraction `y` of the height, the bottom row for 1.0.
+pub struct DrainSpec {
+    pub x: f32,
+    pub y: f32,
+    /// Fraction of the world's width either side of `x`.
+    pub half_width: f32,
+}
+
+/// An emitter at fraction `(x, y)` of the world.
+pub struct EmitterSpec {
+    pub x: f32,
+    pub y: f32,
+    /// Fraction of the world's width.
+    pub radius: f32,
+    /// Grains per tick.
+    pub rate: f32,
+    pub material: Material,
+}
+
+/// A named starting layout.
+pub struct Scene {
+    pub name: &'static str,
+    pub walls: &'static [Shape],
+    /// Areas filled with grains of a material.
+    pub fills: &'static [(Shape, Material)],
+    pub emitters: &'static [EmitterSpec],
+    pub drains: &'static [DrainSpec],
+    /// Drains that must swallow a share of the grains, for a puzzle.
+    pub goals: &'static [Goal],
+}
+
+/// Every built-in scene.
+pub const SCENES: [Scene; 3] = [
+    Scene {
+        name: "hourglass",
+        walls: &[
+            Shape::Line { from: (0.3, 0.05), to: (0.7, 0.05), radius: 0.004 },
+            Shape::Line { from: (0.3, 0.05), to: (0.485, 0.5), radius: 0.004 },
+            Shape::Line { from: (0.7, 0.05), to: (0.515, 0.5), radius: 0.004 },
+            Shape::Line { from: (0.485, 0.5), to: (0.3, 0.95), radius: 0.004 },
+            Shape::Line { from: (0.515, 0.5), to: (0.7, 0.95), radius: 0.004 },
+            Shape::Line { from: (0.3, 0.95), to: (0.7, 0.95), radius: 0.004 },
+        ],
+        fills: &[(
+            Shape::Triangle { a: (0.33, 0.08), b: (0.67, 0.08), c: (0.5, 0.42) },
+            Material::Sand,
+        )],
+        emitters: &[],
+        drains: &[],
+        goals: &[],
+    },
+    Scene {
+        name: "funnel",
+        walls: &[
+            Shape::Line { from: (0.1, 0.25), to: (0.47, 0.65), radius: 0.004 },
+            Shape::Line { from: (0.9, 0.25), to: (0.53, 0.65), radius: 0.004 },
+        ],
+        fills: &[
+            (Shape::Rect { min: (0.25, 0.02), max: (0.75, 0.2) }, Material::Sand),
+            (Shape::Rect { min: (0.35, 0.3), max: (0.65, 0.4) }, Material::Water),
+        ],
+        emitters: &[],
+        drains: &[DrainSpec { x: 0.5, y: 1.0, half_width: 0.05 }],
+        goals: &[],
+    },
+    Scene {
+        name: "pachinko",
+        walls: &[Shape::Pegs {
+            min: (0.08, 0.25),
+            max: (0.92, 0.85),
+            cols: 14,
+            rows: 9,
+            radius: 0.004,
+        }],
+        fills: &[(Shape::Rect { min: (0.3, 0.02), max: (0.7, 0.15) }, Material::Sand)],
+        emitters: &[EmitterSpec {
+            x: 0.5,
+            y: 0.05,
+            radius: 0.01,
+            rate: 3.0,
+            material: Material::Sand,
+        }],
+        drains: &[
+            DrainSpec { x: 0.1, y: 1.0, half_width: 0.04 },
+            DrainSpec { x: 0.5, y: 1.0, half_width: 0.04 },
+            DrainSpec { x: 0.9, y: 1.0, half_width: 0.04 },
+        ],
+        goals: &[],
+    },
+];
+
+/// The built-in scene called `name`.
+pub fn find(name: &str) -> Option<&'static Scene> {
+    SCENES.iter().find(|s| s.name.eq_ignore_ascii_case(name))
+}
+
+impl Shape {
+    /// Fill every cell of `world` inside the shape with `material`.
+    pub fn place(&self, world: &mut World, material: Material) {
+        let (width, height) = (world.width(), world.height());
+        self.for_each_cell(width, height, |x, y| world.place(x, y, material));
+    }
+
+    /// Call `f` for every cell of a `width` x `height` world inside the shape.
+    fn for_each_cell(&self, width: usize, height: usize, mut f: impl FnMut(usize, usize)) {
+        let (w, h) = (width as f32, height as f32);
+        let px = |p: Point| (p.0 * w, p.1 * h);
+        let disc = |cx: f32, cy: f32, r: f32, f: &mut dyn FnMut(usize, usize)| {
+            let r = r.max(0.5);
+            let x0 = (cx - r).floor().max(0.0) as usize;
+            let y0 = (cy - r).floor().max(0.0) as usize;
+            let x1 = ((cx + r).ceil().max(0.0) as usize).min(width);
+            let y1 = ((cy + r).ceil().max(0.0) as usize).min(height);
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+                    if dx * dx + dy * dy <= r * r {
+                        f(x, y);
+                    }
+                }
+            }
+        };
+        match *self {
+            Shape::Line { from, to, radius } => {
+                let ((x0, y0), (x1, y1)) = (px(from), px(to));
+                let r = radius * w;
+                let steps = (x1 - x0).abs().max((y1 - y0).abs()).ceil().max(1.0) as usize;
+                for i in 0..=steps {
+                    let t = i as f32 / steps as f32;
+                    disc(x0 + (x1 - x0) * t, y0 + (y1 - y0) * t, r, &mut f);
+                }
+            }
+            Shape::Triangle { a, b, c } => {
+                let (a, b, c) = (px(a), px(b), px(c));
+                let edge = |p: Point, q: Point, x: f32, y: f32| {
+                    (q.0 - p.0) * (y - p.1) - (q.1 - p.1) * (x - p.0)
+                };
+                let sign = edge(a, b, c.0, c.1).signum();
+                for y in 0..height {
+                    for x in 0..width {
+                        let (cx, cy) = (x as f32 + 0.5, y as f32 + 0.5);
+                        if edge(a, b, cx, cy) * sign >= 0.0
+                            && edge(b, c, cx, cy) * sign >= 0.0
+                            && edge(c, a, cx, cy) * sign >= 0.0
+                        {
+                            f(x, y);
+                        }
+                    }
+                }
+            }
+            Shape::Rect { min, max } => {
+                let ((x0, y0), (x1, y1)) = (px(min), px(max));
+                for y in (y0 as usize)..(y1 as usize).min(height) {
+                    for x in (x0 as usize)..(x1 as usize).min(width) {
+                        f(x, y);
+                    }
+                }
+            }
+            Shape::Pegs { min, max, cols, rows, radius } => {
+                let ((x0, y0), (x1, y1)) = (px(min), px(max));
+                let gap_x = (x1 - x0) / cols.max(1) as f32;
+                let gap_y = (y1 - y0) / rows.max(1) as f32;
+                for row in 0..rows {
+                    let shift = if row % 2 == 0 { 0.0 } else { gap_x / 2.0 };
+                    for col in 0..cols {
+                        let cx = x0 + shift + gap_x * (col as f32 + 0.25);
+                        let cy = y0 + gap_y * (row as f32 + 0.5);
+                        disc(cx, cy, radius * w, &mut f);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Scene {
+    /// Clear `world` and lay this scene out in it.
+    pub fn apply(&self, world: &mut World) {
+        world.clear();
+        let (width, height) = (world.width(), world.height());
+        for shape in self.walls {
+            shape.place(world, Material::Wall);
+        }
+        for (shape, material) in self.fills {
+            shape.place(world, *material);
+        }
+        for e in self.emitters {
+            world.add_emitter(Emitter {
+                x: ((e.x * width as f32) as usize).min(width - 1),
+                y: ((e.y * height as f32) as usize).min(height - 1),
+                radius: (e.radius * width as f32) as usize,
+                rate: e.rate,
+                material: e.material,
+                enabled: true,
+            });
+        }
+        for d in self.drains {
+            let x = ((d.x * width as f32) as usize).min(width - 1);
+            let y = ((d.y * height as f32) as usize).min(height - 1);
+            let half_width = (d.half_width * width as f32) as usize;
+            world.add_drain(Drain { x, y, half_width });
+        }
+    }
+}
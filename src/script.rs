@@ -0,0 +1,225 @@
+// Materials and reactions written as Rhai scripts, with the `rhai` feature.
+// Every `.rhai` file in a directory is run at startup, in name order. The
+// scripts call `material` to change a built-in material or make up a new
+// one in a custom slot, and `reaction` to add a rule to the rule table, so
+// neither needs the crate recompiled:
+//
+//     material("slime", #{ color: [90, 200, 60], movement: "liquid",
+//                          density: 110, viscosity: 0.8 });
+//     reaction("slime", "fire", "smoke", "fire", 0.05);
+
+use std::cell::RefCell;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map};
+
+use crate::material::{self, Material, MaterialProps, Movement};
+use crate::render::Pixel;
+use crate::rules::{self, Rule};
+use crate::world::MAX_SPEED;
+
+/// What the scripts have said so far.
+struct State {
+    props: [MaterialProps; Material::ALL.len()],
+    /// How many custom slots have been filled in.
+    custom: usize,
+    /// The script running, to say where a bad reaction came from.
+    file: String,
+    /// Reactions by material name, from which file, checked once every
+    /// material is known.
+    reactions: Vec<(String, [String; 4], f32)>,
+}
+
+/// Run every `.rhai` file in `dir`, put the materials they make or change
+/// in use, and return the reactions they add. No such directory means no
+/// scripts, and the built-in materials stay as they are.
+pub fn load(dir: &Path) -> Result<Vec<Rule>, String> {
+    let at = |e: io::Error| format!("{}: {e}", dir.display());
+    let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => {
+            entries.map(|e| e.map(|e| e.path())).collect::<Result<_, _>>().map_err(at)?
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(at(e)),
+    };
+    paths.retain(|p| p.extension().is_some_and(|e| e == "rhai"));
+    paths.sort();
+    if paths.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let state = Rc::new(RefCell::new(State {
+        props: material::table(),
+        custom: 0,
+        file: String::new(),
+        reactions: Vec::new(),
+    }));
+    let engine = engine(&state);
+    for path in &paths {
+        let file = path.display().to_string();
+        let text = fs::read_to_string(path).map_err(|e| format!("{file}: {e}"))?;
+        state.borrow_mut().file = file.clone();
+        engine.run(&text).map_err(|e| format!("{file}: {e}"))?;
+    }
+    drop(engine);
+    let state = Rc::into_inner(state).expect("the engine is gone").into_inner();
+
+    material::set_table(state.props, state.custom)?;
+    state
+        .reactions
+        .iter()
+        .map(|(file, [a, b, into_a, into_b], chance)| {
+            rules::rule(a, b, into_a, into_b, *chance).map_err(|e| format!("{file}: {e}"))
+        })
+        .collect()
+}
+
+/// An engine whose `material` and `reaction` functions write to `state`.
+fn engine(state: &Rc<RefCell<State>>) -> Engine {
+    let mut engine = Engine::new();
+    let s = state.clone();
+    engine.register_fn(
+        "material",
+        move |name: &str, fields: Map| -> Result<(), Box<EvalAltResult>> {
+            define(&mut s.borrow_mut(), name, fields).map_err(Into::into)
+        },
+    );
+    let s = state.clone();
+    engine.register_fn("reaction", move |a: &str, b: &str, into_a: &str, into_b: &str| {
+        react(&mut s.borrow_mut(), [a, b, into_a, into_b], 1.0);
+    });
+    let s = state.clone();
+    engine.register_fn(
+        "reaction",
+        move |a: &str, b: &str, into_a: &str, into_b: &str, chance: f64| {
+            react(&mut s.borrow_mut(), [a, b, into_a, into_b], chance as f32);
+        },
+    );
+    engine
+}
+
+fn react(state: &mut State, names: [&str; 4], chance: f32) {
+    let file = state.file.clone();
+    state.reactions.push((file, names.map(str::to_string), chance));
+}
+
+/// Set the fields in `fields` on the material called `name`, taking the
+/// next custom slot for it if there is no such material yet.
+fn define(state: &mut State, name: &str, fields: Map) -> Result<(), String> {
+    let first_custom = Material::CUSTOM[0] as usize;
+    let known = &state.props[..first_custom + state.custom];
+    let index = match known.iter().position(|p| p.name.eq_ignore_ascii_case(name)) {
+        Some(0) => return Err("empty cannot be changed".to_string()),
+        Some(i) => i,
+        None => {
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return Err(format!("`{name}` is not a material name"));
+            }
+            let Some(&slot) = Material::CUSTOM.get(state.custom) else {
+                return Err(format!(
+                    "{name}: there is only room for {} new materials",
+                    Material::CUSTOM.len()
+                ));
+            };
+            state.custom += 1;
+            // The table lasts for the rest of the run, and so do its names.
+            state.props[slot as usize].name = name.to_lowercase().leak();
+            slot as usize
+        }
+    };
+
+    let props = &mut state.props[index];
+    let was_static = props.movement == Movement::Static;
+    for (key, value) in fields {
+        let what = format!("{}.{key}", props.name);
+        match key.as_str() {
+            "color" => props.color = color(&value, &what)?,
+            "shade" => props.shade = fraction(&value, &what)?,
+            "density" => props.density = whole(&value, &what, u8::MAX.into())? as u8,
+            "movement" => props.movement = movement(&value, &what)?,
+            "flammability" => props.flammability = fraction(&value, &what)?,
+            "burn_time" => props.burn_time = whole(&value, &what, u16::MAX.into())? as u16,
+            "lifetime" => props.lifetime = lifetime(&value, &what)?,
+            "viscosity" => props.viscosity = fraction(&value, &what)?,
+            "slide" => props.slide = fraction(&value, &what)?,
+            "slip" => props.slip = fraction(&value, &what)?,
+            "dispersion" => props.dispersion = whole(&value, &what, MAX_SPEED as i64)? as u8,
+            "temperature" => props.temperature = number(&value, &what)?,
+            _ => return Err(format!("{what}: no such property")),
+        }
+    }
+    // Built-in materials are made to move or to stay put by code that
+    // would not expect the other.
+    if index < first_custom && was_static != (props.movement == Movement::Static) {
+        return Err(format!("{}: a built-in material cannot start or stop moving", props.name));
+    }
+    Ok(())
+}
+
+/// A finite number, written with or without a decimal point.
+fn number(value: &Dynamic, what: &str) -> Result<f32, String> {
+    let n = value.as_float().or_else(|_| value.as_int().map(|n| n as f64));
+    match n {
+        Ok(n) if n.is_finite() => Ok(n as f32),
+        _ => Err(format!("{what}: expected a number")),
+    }
+}
+
+/// A number from 0 to 1.
+fn fraction(value: &Dynamic, what: &str) -> Result<f32, String> {
+    let n = number(value, what)?;
+    if !(0.0..=1.0).contains(&n) {
+        return Err(format!("{what}: must be from 0 to 1"));
+    }
+    Ok(n)
+}
+
+/// A whole number from 0 to `max`.
+fn whole(value: &Dynamic, what: &str, max: i64) -> Result<i64, String> {
+    match value.as_int() {
+        Ok(n) if (0..=max).contains(&n) => Ok(n),
+        _ => Err(format!("{what}: expected a whole number from 0 to {max}")),
+    }
+}
+
+/// An `[r, g, b]` colour.
+fn color(value: &Dynamic, what: &str) -> Result<Pixel, String> {
+    let rgb = value.clone().try_cast::<Array>().filter(|a| a.len() == 3);
+    let Some(rgb) = rgb else {
+        return Err(format!("{what}: expected [r, g, b]"));
+    };
+    let c = |i: usize| whole(&rgb[i], what, u8::MAX.into()).map(|n| n as u8);
+    Ok(Pixel::new(c(0)?, c(1)?, c(2)?, 255))
+}
+
+fn movement(value: &Dynamic, what: &str) -> Result<Movement, String> {
+    let name = value.clone().into_string().unwrap_or_default();
+    match name.as_str() {
+        "static" => Ok(Movement::Static),
+        "powder" => Ok(Movement::Powder),
+        "liquid" => Ok(Movement::Liquid),
+        "gas" => Ok(Movement::Gas),
+        _ => Err(format!("{what}: expected \"static\", \"powder\", \"liquid\" or \"gas\"")),
+    }
+}
+
+/// A `[shortest, longest]` range of ticks, or `()` to last forever.
+fn lifetime(value: &Dynamic, what: &str) -> Result<Option<Range<u16>>, String> {
+    if value.is_unit() {
+        return Ok(None);
+    }
+    let range = value.clone().try_cast::<Array>().filter(|a| a.len() == 2);
+    let Some(range) = range else {
+        return Err(format!("{what}: expected [shortest, longest] or ()"));
+    };
+    let tick = |i: usize| whole(&range[i], what, u16::MAX.into()).map(|n| n as u16);
+    let (shortest, longest) = (tick(0)?, tick(1)?);
+    if shortest == 0 || shortest > longest {
+        return Err(format!("{what}: expected 0 < shortest <= longest"));
+    }
+    Ok(Some(shortest..longest))
+}
@@ -0,0 +1,201 @@
+// Remote control over a local socket, built with the `server` feature. A
+// program connects to the port over TCP and sends one JSON request a line,
+// such as `{"cmd": "spawn", "x": 100, "y": 50, "radius": 8}`, and gets one
+// JSON answer a line back, always with an `ok` field and an `error` when it
+// is false. Connections are read on their own threads, but requests are
+// only answered when the frontend calls `Server::poll` between frames, so
+// the world is never touched from two threads at once.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::commands;
+use crate::input::InputState;
+use crate::material::Material;
+use crate::world::World;
+
+/// A request line, with where to send the answer.
+type Pending = (String, Sender<String>);
+
+/// What can be asked, named by the request's `cmd` field.
+#[derive(Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+enum Request {
+    /// Pour, or paint if it does not move, a circle of `material`, or of
+    /// the material last set if none is given.
+    Spawn { x: usize, y: usize, radius: usize, material: Option<String> },
+    Erase { x: usize, y: usize, radius: usize },
+    Clear,
+    /// Set the material `spawn` uses when a request does not name one.
+    SetMaterial { material: String },
+    /// The tick, grain and drained counts, size and gravity of the world,
+    /// and the material last set.
+    Stats,
+    /// Every cell's material, as its index in `Material::ALL`, a row at a
+    /// time from the top.
+    Frame,
+    /// A line for the console's command table, such as `set gravity 0.3`.
+    Command { line: String },
+}
+
+/// A listening socket and the requests waiting on it.
+pub struct Server {
+    addr: SocketAddr,
+    requests: Receiver<Pending>,
+    material: Material,
+}
+
+impl Server {
+    /// Listen on `port` on the loopback address only, so nothing off this
+    /// machine can reach the world.
+    pub fn start(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        let addr = listener.local_addr()?;
+        let (send, requests) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let send = send.clone();
+                thread::spawn(move || serve(stream, send));
+            }
+        });
+        Ok(Self { addr, requests, material: Material::Sand })
+    }
+
+    /// Where the server is listening.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The material `spawn` requests pour when they do not name one.
+    pub fn material(&self) -> Material {
+        self.material
+    }
+
+    /// Answer every request waiting, without waiting for more.
+    pub fn poll(&mut self, world: &mut World) {
+        while let Ok((line, reply)) = self.requests.try_recv() {
+            let answer = match serde_json::from_str(&line) {
+                Ok(request) => self.answer(world, request),
+                Err(e) => Err(e.to_string()),
+            };
+            let answer = match answer {
+                Ok(Value::Object(mut fields)) => {
+                    fields.insert("ok".to_string(), Value::Bool(true));
+                    Value::Object(fields)
+                }
+                Ok(_) => json!({ "ok": true }),
+                Err(e) => json!({ "ok": false, "error": e }),
+            };
+            // The connection may have gone; there is no one else to tell.
+            let _ = reply.send(answer.to_string());
+        }
+    }
+
+    fn answer(&mut self, world: &mut World, request: Request) -> Result<Value, String> {
+        match request {
+            Request::Spawn { x, y, radius, material } => {
+                let material = match material {
+                    Some(name) => material_named(&name)?,
+                    None => self.material,
+                };
+                let mouse = on_world(world, x, y)?;
+                let radius = brush_radius(world, radius)?;
+                let before = world.grains().len();
+                world.apply_input(&InputState {
+                    mouse,
+                    left: true,
+                    material,
+                    radius,
+                    tries: InputState::box_tries(radius),
+                    ..InputState::NONE
+                });
+                Ok(json!({ "spawned": world.grains().len() - before }))
+            }
+            Request::Erase { x, y, radius } => {
+                let mouse = on_world(world, x, y)?;
+                let radius = brush_radius(world, radius)?;
+                world.apply_input(&InputState { mouse, right: true, radius, ..InputState::NONE });
+                Ok(json!({}))
+            }
+            Request::Clear => {
+                world.clear();
+                Ok(json!({}))
+            }
+            Request::SetMaterial { material } => {
+                self.material = material_named(&material)?;
+                Ok(json!({}))
+            }
+            Request::Stats => Ok(json!({
+                "ticks": world.ticks(),
+                "grains": world.grains().len(),
+                "drained": world.drained_total(),
+                "width": world.width(),
+                "height": world.height(),
+                "gravity": format!("{:?}", world.gravity()).to_lowercase(),
+                "material": self.material.props().name,
+            })),
+            Request::Frame => {
+                let cells: Vec<u8> = world.cells().as_slice().iter().map(|&m| m as u8).collect();
+                Ok(json!({ "width": world.width(), "height": world.height(), "cells": cells }))
+            }
+            Request::Command { line } => Ok(json!({ "said": commands::run(world, &line)? })),
+        }
+    }
+}
+
+/// The material called `name`, other than empty.
+fn material_named(name: &str) -> Result<Material, String> {
+    Material::from_name(name)
+        .filter(|&m| m != Material::Empty)
+        .ok_or_else(|| format!("unknown material '{name}'"))
+}
+
+/// Cell `(x, y)` as the pointer, if it is on the world.
+fn on_world(world: &World, x: usize, y: usize) -> Result<Option<(usize, usize)>, String> {
+    if x < world.width() && y < world.height() {
+        Ok(Some((x, y)))
+    } else {
+        Err(format!("{x},{y} is off the world"))
+    }
+}
+
+/// `radius`, if it is no bigger than `world` can use.
+fn brush_radius(world: &World, radius: usize) -> Result<usize, String> {
+    if radius <= world.max_radius() {
+        Ok(radius)
+    } else {
+        Err(format!("radius {radius} is more than {}", world.max_radius()))
+    }
+}
+
+/// Pass each line from `stream` on to be answered, and write the answer
+/// back, until the other end hangs up or the server is dropped.
+fn serve(stream: TcpStream, requests: Sender<Pending>) {
+    let Ok(reader) = stream.try_clone() else {
+        return;
+    };
+    let mut writer = stream;
+    for line in BufReader::new(reader).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (reply, answer) = mpsc::channel();
+        if requests.send((line, reply)).is_err() {
+            return;
+        }
+        let Ok(answer) = answer.recv() else {
+            return;
+        };
+        if writeln!(writer, "{answer}").is_err() {
+            return;
+        }
+    }
+}
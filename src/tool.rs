@@ -0,0 +1,367 @@
+// The tool the left mouse button draws with: which material it places,
+// whether it sprays a brush, draws straight lines or boxes, fills like a
+// paint bucket, selects or stamps, and whether what it draws is mirrored.
+
+use sandfall::brush::Brush;
+use sandfall::{Material, Region, World};
+
+/// Materials that can be picked, in hotkey order: `1` picks the first.
+/// Empty is left out; erasing has its own button.
+const PICKABLE: [Material; 23] = [
+    Material::Sand,
+    Material::Wall,
+    Material::Water,
+    Material::Fire,
+    Material::Smoke,
+    Material::Lava,
+    Material::Stone,
+    Material::Steam,
+    Material::Oil,
+    Material::Wood,
+    Material::Seed,
+    Material::Plant,
+    Material::Gunpowder,
+    Material::Mud,
+    Material::Metal,
+    Material::Battery,
+    Material::Lamp,
+    Material::Clone,
+    Material::Void,
+    Material::ConveyorLeft,
+    Material::ConveyorRight,
+    Material::Snow,
+    Material::Ice,
+];
+
+/// The materials that can be picked: `PICKABLE`, then any made up by
+/// scripts.
+pub fn pickable() -> Vec<Material> {
+    PICKABLE.iter().chain(Material::custom()).copied().collect()
+}
+
+/// Half the thickness of lines drawn with the line tool, in cells.
+const LINE_RADIUS: usize = 1;
+
+/// How the left mouse button draws.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// Draws with the brush under the cursor while held.
+    Brush,
+    /// Draws a straight line from where the button went down to where it
+    /// comes up.
+    Line,
+    /// Fills the box dragged out between the same two points.
+    Rect,
+    /// Draws just the edge of that box.
+    Frame,
+    /// Fills the empty area clicked on, as far as it reaches.
+    Bucket,
+    /// Marks the box dragged out as the selection, to copy, cut and paste,
+    /// or drags the selection somewhere else when the drag starts inside it.
+    Select,
+    /// Places the stamp centred on the cell clicked.
+    Stamp,
+}
+
+impl Kind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Brush => "brush",
+            Kind::Line => "line",
+            Kind::Rect => "rect",
+            Kind::Frame => "frame",
+            Kind::Bucket => "bucket",
+            Kind::Select => "select",
+            Kind::Stamp => "stamp",
+        }
+    }
+}
+
+/// Which axes through the middle of the world drawing is mirrored across.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Symmetry {
+    None,
+    /// Mirrored left to right, across the vertical centre line.
+    Vertical,
+    /// Mirrored top to bottom, across the horizontal centre line.
+    Horizontal,
+    /// Mirrored across both, into all four quarters.
+    FourWay,
+}
+
+impl Symmetry {
+    pub fn name(self) -> &'static str {
+        match self {
+            Symmetry::None => "none",
+            Symmetry::Vertical => "vertical",
+            Symmetry::Horizontal => "horizontal",
+            Symmetry::FourWay => "4-way",
+        }
+    }
+
+    /// The `(flip_x, flip_y)` reflections drawing is repeated under, the
+    /// first being the drawing itself.
+    fn flips(self) -> &'static [(bool, bool)] {
+        match self {
+            Symmetry::None => &[(false, false)],
+            Symmetry::Vertical => &[(false, false), (true, false)],
+            Symmetry::Horizontal => &[(false, false), (false, true)],
+            Symmetry::FourWay => &[(false, false), (true, false), (false, true), (true, true)],
+        }
+    }
+}
+
+/// Reflect cell `(x, y)` of `world` by `flip`.
+fn mirror(world: &World, (flip_x, flip_y): (bool, bool), (x, y): (usize, usize)) -> (usize, usize) {
+    (
+        if flip_x { world.width() - 1 - x } else { x },
+        if flip_y { world.height() - 1 - y } else { y },
+    )
+}
+
+/// What the left mouse button currently places, and how.
+pub struct ActiveTool {
+    material: Material,
+    kind: Kind,
+    symmetry: Symmetry,
+    /// Cell the current drag started on, for tools drawn between two points.
+    anchor: Option<(usize, usize)>,
+    /// Cell the brush was last put down on in the current stroke.
+    last: Option<(usize, usize)>,
+    /// Top-left and bottom-right corners of the selected box.
+    selection: Option<((usize, usize), (usize, usize))>,
+    /// True while the current drag is moving the selection.
+    moving: bool,
+    /// What was last copied or cut.
+    clipboard: Option<Region>,
+    /// What the stamp tool places, if one was loaded.
+    stamp: Option<Region>,
+}
+
+impl ActiveTool {
+    pub fn new(material: Material) -> Self {
+        Self {
+            material,
+            kind: Kind::Brush,
+            symmetry: Symmetry::None,
+            anchor: None,
+            last: None,
+            selection: None,
+            moving: false,
+            clipboard: None,
+            stamp: None,
+        }
+    }
+
+    /// Give the stamp tool `stamp` to place.
+    pub fn set_stamp(&mut self, stamp: Region) {
+        self.stamp = Some(stamp);
+    }
+
+    /// The stamp the stamp tool places, if one was loaded.
+    pub fn stamp(&self) -> Option<&Region> {
+        self.stamp.as_ref()
+    }
+
+    pub fn material(&self) -> Material {
+        self.material
+    }
+
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    /// Switch to the next kind of tool, dropping any drag in progress. The
+    /// stamp tool is skipped if there is no stamp.
+    pub fn cycle_kind(&mut self) {
+        self.kind = match self.kind {
+            Kind::Brush => Kind::Line,
+            Kind::Line => Kind::Rect,
+            Kind::Rect => Kind::Frame,
+            Kind::Frame => Kind::Bucket,
+            Kind::Bucket => Kind::Select,
+            Kind::Select if self.stamp.is_some() => Kind::Stamp,
+            Kind::Select | Kind::Stamp => Kind::Brush,
+        };
+        self.anchor = None;
+        self.last = None;
+        self.selection = None;
+        self.moving = false;
+    }
+
+    pub fn symmetry(&self) -> Symmetry {
+        self.symmetry
+    }
+
+    /// Switch to the next symmetry: none, vertical, horizontal, 4-way.
+    pub fn cycle_symmetry(&mut self) {
+        self.symmetry = match self.symmetry {
+            Symmetry::None => Symmetry::Vertical,
+            Symmetry::Vertical => Symmetry::Horizontal,
+            Symmetry::Horizontal => Symmetry::FourWay,
+            Symmetry::FourWay => Symmetry::None,
+        };
+    }
+
+    /// Cell the current drag started on, if a line or box is being drawn.
+    pub fn anchor(&self) -> Option<(usize, usize)> {
+        self.anchor
+    }
+
+    /// Pick the material on hotkey `slot`, counting from 1. Returns false if
+    /// there is no such slot.
+    pub fn select(&mut self, slot: usize) -> bool {
+        match slot.checked_sub(1).and_then(|i| pickable().get(i).copied()) {
+            Some(m) => {
+                self.material = m;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Pick the next material, wrapping round to the first.
+    pub fn cycle(&mut self) {
+        let pickable = pickable();
+        let next = pickable.iter().position(|&m| m == self.material).map_or(0, |i| i + 1);
+        self.material = pickable[next % pickable.len()];
+    }
+
+    /// Use the tool at cell `(x, y)` while the button is held: `brush`
+    /// draws along the cursor's path since the last call, the bucket fills
+    /// once per click, and the other tools remember where the drag started.
+    /// Drawing is repeated in every mirror image the symmetry asks for.
+    pub fn hold(&mut self, world: &mut World, x: usize, y: usize, brush: &Brush) {
+        match self.kind {
+            Kind::Brush => {
+                let from = self.last.unwrap_or((x, y));
+                for &flip in self.symmetry.flips() {
+                    let (from, to) = (mirror(world, flip, from), mirror(world, flip, (x, y)));
+                    brush.stroke(world, from, to, self.material);
+                }
+                self.last = Some((x, y));
+            }
+            Kind::Bucket if self.anchor.is_none() => {
+                for &flip in self.symmetry.flips() {
+                    let (mx, my) = mirror(world, flip, (x, y));
+                    world.flood(mx, my, self.material);
+                }
+                self.anchor = Some((x, y));
+            }
+            Kind::Bucket => {}
+            Kind::Stamp if self.anchor.is_none() => {
+                if let Some(stamp) = &self.stamp {
+                    for &(flip_x, flip_y) in self.symmetry.flips() {
+                        let (sx, sy) = mirror(world, (flip_x, flip_y), (x, y));
+                        let stamp = stamp.flipped(flip_x, flip_y);
+                        let (w, h) = (stamp.width(), stamp.height());
+                        world.paste(&stamp, sx.saturating_sub(w / 2), sy.saturating_sub(h / 2));
+                    }
+                }
+                self.anchor = Some((x, y));
+            }
+            Kind::Stamp => {}
+            Kind::Select if self.anchor.is_none() => {
+                self.moving = self.selection.is_some_and(|((x0, y0), (x1, y1))| {
+                    (x0..=x1).contains(&x) && (y0..=y1).contains(&y)
+                });
+                self.anchor = Some((x, y));
+            }
+            Kind::Line | Kind::Rect | Kind::Frame | Kind::Select => {
+                self.anchor.get_or_insert((x, y));
+            }
+        }
+    }
+
+    /// Finish a drag at cell `(x, y)`, drawing the line or box if one was
+    /// started.
+    pub fn release(&mut self, world: &mut World, x: usize, y: usize) {
+        let Some(from) = self.anchor.take() else {
+            return;
+        };
+        if self.kind == Kind::Select {
+            if std::mem::take(&mut self.moving) {
+                self.move_selection(world, from, (x, y));
+            } else {
+                let corners = ((from.0.min(x), from.1.min(y)), (from.0.max(x), from.1.max(y)));
+                self.selection = Some(corners);
+            }
+            return;
+        }
+        for &flip in self.symmetry.flips() {
+            let (from, to) = (mirror(world, flip, from), mirror(world, flip, (x, y)));
+            match self.kind {
+                Kind::Brush | Kind::Bucket | Kind::Select | Kind::Stamp => {}
+                Kind::Line => world.line(from, to, LINE_RADIUS, self.material),
+                Kind::Rect => world.rect(from, to, self.material, false),
+                Kind::Frame => world.rect(from, to, self.material, true),
+            }
+        }
+    }
+
+    /// Drop a drag without drawing anything, as when the button is let go
+    /// off the window, and end the brush's stroke.
+    pub fn cancel(&mut self) {
+        self.anchor = None;
+        self.last = None;
+        self.moving = false;
+    }
+
+    /// Top-left and bottom-right corners of the selected box, if any.
+    pub fn selection(&self) -> Option<((usize, usize), (usize, usize))> {
+        self.selection
+    }
+
+    /// True while a drag is moving the selection.
+    pub fn is_moving(&self) -> bool {
+        self.moving
+    }
+
+    /// Cut the selection and paste it back in moved by the drag from `from`
+    /// to `to`, as far as the world allows, and select it where it landed.
+    fn move_selection(&mut self, world: &mut World, from: (usize, usize), to: (usize, usize)) {
+        let Some(((x0, y0), (x1, y1))) = self.selection else {
+            return;
+        };
+        let (w, h) = (x1 - x0, y1 - y0);
+        let shift = |start: usize, size: usize, limit: usize, a: usize, b: usize| {
+            let moved = start as isize + b as isize - a as isize;
+            moved.clamp(0, (limit - 1 - size) as isize) as usize
+        };
+        let nx = shift(x0, w, world.width(), from.0, to.0);
+        let ny = shift(y0, h, world.height(), from.1, to.1);
+        let region = world.cut((x0, y0), (x1, y1));
+        world.paste(&region, nx, ny);
+        self.selection = Some(((nx, ny), (nx + w, ny + h)));
+    }
+
+    /// Copy the selection to the clipboard, if there is one.
+    pub fn copy(&mut self, world: &World) {
+        if let Some((from, to)) = self.selection {
+            self.clipboard = Some(world.copy(from, to));
+        }
+    }
+
+    /// Copy the selection to the clipboard and empty it.
+    pub fn cut(&mut self, world: &mut World) {
+        if let Some((from, to)) = self.selection {
+            self.clipboard = Some(world.cut(from, to));
+        }
+    }
+
+    /// Paste the clipboard centred on cell `(x, y)`, and select it if the
+    /// select tool is in use.
+    pub fn paste(&mut self, world: &mut World, x: usize, y: usize) {
+        let Some(region) = &self.clipboard else {
+            return;
+        };
+        let (w, h) = (region.width(), region.height());
+        let (px, py) = (x.saturating_sub(w / 2), y.saturating_sub(h / 2));
+        world.paste(region, px, py);
+        if self.kind == Kind::Select {
+            let x1 = (px + w).min(world.width()) - 1;
+            let y1 = (py + h).min(world.height()) - 1;
+            self.selection = Some(((px, py), (x1, y1)));
+        }
+    }
+}
@@ -0,0 +1,228 @@
+// The `--tui` frontend: the world drawn in the terminal with half-block
+// characters in 256 colours, two cells to a character, and driven from the
+// keyboard. Works over SSH and needs nothing but a terminal; crossterm puts
+// it in raw mode and restores it on the way out.
+
+use std::io::{self, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Color, Colors, Print, ResetColor, SetAttribute, SetColors};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+
+use sandfall::scenes::Scene;
+use sandfall::{Drain, InputState, Material, World};
+
+use crate::tool;
+
+/// Simulation ticks per second, the same as the window frontend.
+const TICK_RATE: f64 = 120.0;
+/// Frames drawn per second. Terminals cannot keep up with much more.
+const FRAME_RATE: f64 = 30.0;
+/// Grains the brush tries to place per frame while pouring.
+const TRIES_PER_FRAME: usize = 8;
+const MIN_SPAWN_RADIUS: usize = 1;
+const MAX_SPAWN_RADIUS: usize = 16;
+
+/// Puts the terminal in raw mode on the alternate screen, and puts it back
+/// when dropped, even if the simulation panics.
+struct Terminal;
+
+impl Terminal {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let terminal = Self;
+        execute!(io::stdout(), EnterAlternateScreen, Hide)?;
+        Ok(terminal)
+    }
+
+    /// Terminal size as `(columns, rows)`.
+    fn size() -> (usize, usize) {
+        match terminal::size() {
+            Ok((cols, rows)) if cols > 0 && rows > 0 => (cols as usize, rows as usize),
+            _ => (80, 24),
+        }
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), ResetColor, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// The keys pressed since the last call, without waiting for any.
+fn keys() -> Vec<KeyEvent> {
+    let mut keys = Vec::new();
+    while event::poll(Duration::ZERO).unwrap_or(false) {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind != KeyEventKind::Release => keys.push(key),
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+    keys
+}
+
+/// The nearest colour in the xterm 256-colour 6x6x6 cube.
+fn ansi_256(r: u8, g: u8, b: u8) -> u8 {
+    let level = |c: u8| ((c as u16 * 5 + 127) / 255) as u8;
+    16 + 36 * level(r) + 6 * level(g) + level(b)
+}
+
+/// Run the world in the terminal until `q` or Escape is pressed.
+pub fn run(seed: i32, scene: Option<&'static Scene>) {
+    let terminal = match Terminal::enter() {
+        Ok(terminal) => terminal,
+        Err(e) => {
+            eprintln!("sandfall: --tui needs a terminal: {e}");
+            return;
+        }
+    };
+    // Each character shows two cells stacked, and the bottom line is the
+    // status bar.
+    let (cols, rows) = Terminal::size();
+    let (width, height) = (cols, (rows.max(2) - 1) * 2);
+
+    let mut world = World::new(width, height, seed);
+    world.add_drain(Drain { x: width / 2, y: height - 1, half_width: width / 8 });
+    if let Some(scene) = scene {
+        scene.apply(&mut world);
+    }
+    // Every shade of every material as a 256-colour index.
+    let palette: Vec<Vec<u8>> = Material::ALL
+        .iter()
+        .map(|m| m.color_variants().iter().map(|p| ansi_256(p.r, p.g, p.b)).collect())
+        .collect();
+
+    let (mut cx, mut cy) = (width / 2, height / 4);
+    let mut spawn_radius = 2;
+    let mut material = Material::Sand;
+    let mut pouring = false;
+    let mut paused = false;
+
+    let frame_time = Duration::from_secs_f64(1.0 / FRAME_RATE);
+    let ticks_per_frame = (TICK_RATE / FRAME_RATE).round() as usize;
+    let mut stdout = io::stdout();
+    let mut screen = Vec::new();
+
+    'frames: loop {
+        let start = Instant::now();
+
+        // Input: arrows or hjkl move the cursor, space starts and stops
+        // pouring, x erases, d opens the drain, 1-9 pick a material.
+        let (mut erasing, mut draining) = (false, false);
+        for key in keys() {
+            match key.code {
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    break 'frames;
+                }
+                KeyCode::Esc | KeyCode::Char('q') => break 'frames,
+                KeyCode::Up | KeyCode::Char('k') => cy = cy.saturating_sub(2),
+                KeyCode::Down | KeyCode::Char('j') => cy = (cy + 2).min(height - 1),
+                KeyCode::Left | KeyCode::Char('h') => cx = cx.saturating_sub(1),
+                KeyCode::Right | KeyCode::Char('l') => cx = (cx + 1).min(width - 1),
+                KeyCode::Char(' ') => pouring = !pouring,
+                KeyCode::Char('x') => erasing = true,
+                KeyCode::Char('d') => draining = true,
+                KeyCode::Char('p') => paused = !paused,
+                KeyCode::Char('c') => world.clear(),
+                KeyCode::Char('+' | '=') => {
+                    spawn_radius = (spawn_radius + 1).min(MAX_SPAWN_RADIUS);
+                }
+                KeyCode::Char('-') => spawn_radius = (spawn_radius - 1).max(MIN_SPAWN_RADIUS),
+                // The same slots as the number keys in the window.
+                KeyCode::Char(c @ '1'..='9') => {
+                    let slot = c as usize - '1' as usize;
+                    if let Some(&m) = tool::pickable().get(slot) {
+                        material = m;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let mut input = InputState {
+            mouse: Some((cx, cy)),
+            left: pouring && !erasing,
+            right: erasing,
+            material,
+            radius: spawn_radius,
+            tries: TRIES_PER_FRAME,
+            drain: draining,
+        };
+        if paused {
+            world.apply_input(&input);
+        } else {
+            for _ in 0..ticks_per_frame {
+                world.step_parallel(&input);
+                // The brush acts once a frame; the drain stays open for all of it.
+                input = InputState { drain: input.drain, ..InputState::NONE };
+            }
+        }
+
+        let status = format!(
+            " {} r{} {}{} | arrows move, space pour, x erase, d drain, 1-9 material, q quit",
+            material.props().name,
+            spawn_radius,
+            if pouring { "POURING " } else { "" },
+            if paused { "PAUSED" } else { "" },
+        );
+        screen.clear();
+        let drawn = draw(&mut screen, &world, &palette, (cx, cy), &status);
+        if drawn.and_then(|_| stdout.write_all(&screen)).and_then(|_| stdout.flush()).is_err() {
+            break;
+        }
+
+        if let Some(rest) = frame_time.checked_sub(start.elapsed()) {
+            thread::sleep(rest);
+        }
+    }
+    drop(terminal);
+}
+
+/// Queue the world, with the cursor at cell `cursor`, and the status line
+/// below it into `screen`, from the top-left. The colour is only changed
+/// when it differs from the character before.
+fn draw(
+    screen: &mut Vec<u8>,
+    world: &World,
+    palette: &[Vec<u8>],
+    cursor: (usize, usize),
+    status: &str,
+) -> io::Result<()> {
+    queue!(screen, MoveTo(0, 0))?;
+    let cells = world.cells();
+    let (width, height) = (world.width(), world.height());
+    let (cx, cursor_row) = (cursor.0, cursor.1 / 2);
+    for row in 0..height / 2 {
+        let mut last = None;
+        for x in 0..width {
+            if (row, x) == (cursor_row, cx) {
+                queue!(
+                    screen,
+                    SetAttribute(Attribute::Reset),
+                    SetAttribute(Attribute::Reverse),
+                    Print('+'),
+                    SetAttribute(Attribute::Reset),
+                )?;
+                last = None;
+                continue;
+            }
+            let color = |y| palette[cells.get(x, y) as usize][world.shade_at(x, y) as usize];
+            let colors = (color(row * 2), color(row * 2 + 1));
+            if last != Some(colors) {
+                let (fg, bg) = (Color::AnsiValue(colors.0), Color::AnsiValue(colors.1));
+                queue!(screen, SetColors(Colors::new(fg, bg)))?;
+                last = Some(colors);
+            }
+            queue!(screen, Print('\u{2580}'))?;
+        }
+        queue!(screen, SetAttribute(Attribute::Reset), Print("\r\n"))?;
+    }
+    let status = &status[..status.len().min(width)];
+    queue!(screen, ResetColor, Clear(ClearType::UntilNewLine), Print(status))
+}
@@ -0,0 +1,80 @@
+// Weather: rain or snow falling from the edge of the world that gravity
+// pulls away from, at random places along it, blown sideways by the wind.
+// New drops start out with the wind's speed across gravity and keep it
+// until they land, so they fall on a slant and drift further the longer
+// they fall.
+
+use crate::material::Material;
+use crate::world::World;
+
+/// What falls.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Precipitation {
+    Rain,
+    Snow,
+}
+
+impl Precipitation {
+    /// The material each drop is made of.
+    pub fn material(self) -> Material {
+        match self {
+            Precipitation::Rain => Material::Water,
+            Precipitation::Snow => Material::Snow,
+        }
+    }
+
+    /// The precipitation called `name`, ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Precipitation::Rain, Precipitation::Snow]
+            .into_iter()
+            .find(|p| format!("{p:?}").eq_ignore_ascii_case(name))
+    }
+}
+
+/// Rain or snow over the whole width of the world.
+#[derive(Clone, Copy, Debug)]
+pub struct Weather {
+    pub precipitation: Precipitation,
+    /// Drops per tick. A fractional part is a chance of one more.
+    pub rate: f32,
+    /// Sideways speed drops start with, in cells per tick; positive blows
+    /// them the way `Gravity::across` points, to the right when grains fall
+    /// down.
+    pub wind: f32,
+}
+
+/// Drop this tick's rain or snow, if the world has weather.
+pub(crate) fn fall(world: &mut World) {
+    let Some(weather) = world.weather else {
+        return;
+    };
+    let gravity = world.gravity();
+    let (gx, gy) = gravity.vector();
+    let (ax, ay) = gravity.across();
+    let (w, h) = (world.width, world.height);
+    let material = weather.precipitation.material();
+
+    let extra = world.rng.uni() < weather.rate.fract();
+    let drops = weather.rate as usize + extra as usize;
+    for _ in 0..drops {
+        // A random cell along the edge gravity pulls away from.
+        let along = world.rng.uni();
+        let (x, y) = match (gx, gy) {
+            (0, 1) => ((along * w as f32) as usize, 0),
+            (0, _) => ((along * w as f32) as usize, h - 1),
+            (1, _) => (0, (along * h as f32) as usize),
+            _ => (w - 1, (along * h as f32) as usize),
+        };
+        let (x, y) = (x.min(w - 1), y.min(h - 1));
+        if world.grid.get(x, y) != Material::Empty {
+            continue;
+        }
+        // Gusts: each drop gets between half and one and a half times the
+        // wind.
+        let wind = weather.wind * (0.5 + world.rng.uni());
+        let mut g = world.new_grain(x, y, material);
+        g.vx += ax as f32 * wind;
+        g.vy += ay as f32 * wind;
+        world.add_grain(g);
+    }
+}
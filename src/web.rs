@@ -0,0 +1,224 @@
+// Browser frontend, built for wasm32-unknown-unknown with the `web` feature.
+// The simulation is the same `World` the minifb frontend drives. Instead of
+// polling a window for input, the page forwards its pointer and key events
+// to a `WebApp`, and calls `frame` from requestAnimationFrame to step the
+// world and draw the cells that changed into a canvas.
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::Clamped;
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, ImageData};
+
+use crate::material::{Material, SHADES};
+use crate::input::InputState;
+use crate::world::{Drain, World};
+
+/// Simulation ticks per second, whatever the display's refresh rate.
+const TICK_RATE: f64 = 120.0;
+/// Most ticks run in one frame, so a slow tab does not fall further behind.
+const MAX_TICKS_PER_FRAME: u32 = 8;
+/// Starting brush radius in cells.
+const SPAWN_RADIUS: usize = 16;
+/// Grains the brush tries to place per frame.
+const TRIES_PER_FRAME: usize = 25;
+const MIN_SPAWN_RADIUS: usize = 1;
+const MAX_SPAWN_RADIUS: usize = 128;
+
+/// Pointer and key state, as last reported by the page.
+#[derive(Default)]
+struct Input {
+    /// Pointer position in canvas pixels, `None` once it leaves the canvas.
+    pointer: Option<(f64, f64)>,
+    left: bool,
+    right: bool,
+    /// The drains are open while space is held.
+    drain_open: bool,
+}
+
+/// The simulation and the canvas it is drawn into. The world is the size of
+/// the canvas, one cell per pixel.
+#[wasm_bindgen]
+pub struct WebApp {
+    world: World,
+    context: CanvasRenderingContext2d,
+    /// The canvas contents as RGBA bytes, updated where cells changed.
+    rgba: Vec<u8>,
+    /// Every shade of every material as RGBA, indexed like the frontend's.
+    palette: Vec<[u8; 4]>,
+    input: Input,
+    material: Material,
+    spawn_radius: usize,
+    paused: bool,
+    /// Time not yet simulated, in seconds.
+    tick_accumulator: f64,
+}
+
+#[wasm_bindgen]
+impl WebApp {
+    /// Start a world the size of `canvas`, seeded with `seed`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(canvas: HtmlCanvasElement, seed: i32) -> Result<WebApp, JsValue> {
+        console_error_panic_hook::set_once();
+        let (width, height) = (canvas.width() as usize, canvas.height() as usize);
+        if width == 0 || height == 0 {
+            return Err(JsValue::from_str("canvas has no size"));
+        }
+        let context = canvas
+            .get_context("2d")?
+            .ok_or_else(|| JsValue::from_str("no 2d context"))?
+            .dyn_into::<CanvasRenderingContext2d>()?;
+
+        let mut world = World::new(width, height, seed);
+        world.add_drain(Drain { x: width / 2, y: height - 1, half_width: 50 });
+        let palette = Material::ALL
+            .iter()
+            .flat_map(|m| m.color_variants())
+            .map(|p| [p.r, p.g, p.b, 255])
+            .collect();
+        Ok(WebApp {
+            world,
+            context,
+            rgba: vec![0; width * height * 4],
+            palette,
+            input: Input::default(),
+            material: Material::Sand,
+            spawn_radius: SPAWN_RADIUS,
+            paused: false,
+            tick_accumulator: 0.0,
+        })
+    }
+
+    /// The pointer moved to `(x, y)` in canvas pixels.
+    pub fn pointer_move(&mut self, x: f64, y: f64) {
+        self.input.pointer = Some((x, y));
+    }
+
+    /// A pointer button went down: 0 is the left button, 2 the right.
+    pub fn pointer_down(&mut self, button: i16) {
+        match button {
+            0 => self.input.left = true,
+            2 => self.input.right = true,
+            _ => {}
+        }
+    }
+
+    /// A pointer button came up.
+    pub fn pointer_up(&mut self, button: i16) {
+        match button {
+            0 => self.input.left = false,
+            2 => self.input.right = false,
+            _ => {}
+        }
+    }
+
+    /// The pointer left the canvas, which also lets go of its buttons.
+    pub fn pointer_leave(&mut self) {
+        self.input = Input::default();
+    }
+
+    /// The wheel turned; each step grows or shrinks the brush by a cell.
+    pub fn wheel(&mut self, delta_y: f64) {
+        let steps = -delta_y.signum() as isize;
+        self.spawn_radius = (self.spawn_radius as isize + steps)
+            .clamp(MIN_SPAWN_RADIUS as isize, MAX_SPAWN_RADIUS as isize)
+            as usize;
+    }
+
+    /// A key was pressed, named as in `KeyboardEvent.key`. `1` to `9` pick
+    /// a material, `p` pauses, `c` clears and holding space opens the drains.
+    pub fn key_down(&mut self, key: &str) {
+        match key {
+            "p" | "P" => self.paused = !self.paused,
+            "c" | "C" => self.world.clear(),
+            " " => self.input.drain_open = true,
+            _ => {
+                if let Ok(slot) = key.parse::<usize>()
+                    && (1..=9).contains(&slot)
+                {
+                    self.material = Material::ALL[slot];
+                }
+            }
+        }
+    }
+
+    /// A key was let go.
+    pub fn key_up(&mut self, key: &str) {
+        if key == " " {
+            self.input.drain_open = false;
+        }
+    }
+
+    /// Name of the material the left button pours.
+    pub fn material(&self) -> String {
+        self.material.props().name.to_string()
+    }
+
+    /// Advance by `dt` seconds since the last frame and draw the result.
+    pub fn frame(&mut self, dt: f64) -> Result<(), JsValue> {
+        // The canvas is one cell per pixel.
+        let (w, h) = (self.world.width(), self.world.height());
+        let mouse = self.input.pointer.and_then(|(x, y)| {
+            let (x, y) = (x.floor(), y.floor());
+            let inside = x >= 0.0 && y >= 0.0 && (x as usize) < w && (y as usize) < h;
+            inside.then_some((x as usize, y as usize))
+        });
+        let mut input = InputState {
+            mouse,
+            left: self.input.left,
+            right: self.input.right,
+            material: self.material,
+            radius: self.spawn_radius,
+            tries: TRIES_PER_FRAME,
+            drain: self.input.drain_open,
+        };
+
+        let due = if self.paused {
+            0
+        } else {
+            self.tick_accumulator += dt;
+            let due = (self.tick_accumulator * TICK_RATE) as u32;
+            self.tick_accumulator -= due as f64 / TICK_RATE;
+            if due > MAX_TICKS_PER_FRAME {
+                self.tick_accumulator = 0.0;
+            }
+            due.min(MAX_TICKS_PER_FRAME)
+        };
+        if due == 0 {
+            self.world.apply_input(&input);
+        }
+        // No threads in the browser, so ticks run on this one.
+        for _ in 0..due {
+            self.world.step(&input);
+            // The brush acts once a frame; the drains stay open for all of it.
+            input = InputState { drain: input.drain, ..InputState::NONE };
+        }
+
+        self.draw()
+    }
+}
+
+impl WebApp {
+    /// Copy the cells that changed into the RGBA buffer and hand it to the
+    /// canvas.
+    fn draw(&mut self) -> Result<(), JsValue> {
+        let width = self.world.width();
+        let cells = self.world.cells();
+        for (rx, ry, rw, rh) in self.world.chunks().redraw_rects() {
+            for y in ry..ry + rh {
+                for x in rx..rx + rw {
+                    let shade = self.world.shade_at(x, y) as usize;
+                    let color = self.palette[cells.get(x, y) as usize * SHADES + shade];
+                    let i = (y * width + x) * 4;
+                    self.rgba[i..i + 4].copy_from_slice(&color);
+                }
+            }
+        }
+        self.world.clear_redraw();
+
+        let image = ImageData::new_with_u8_clamped_array_and_sh(
+            Clamped(&self.rgba),
+            width as u32,
+            self.world.height() as u32,
+        )?;
+        self.context.put_image_data(&image, 0.0, 0.0)
+    }
+}
@@ -0,0 +1,310 @@
+// A winit window and the input read from it, for the backends that draw
+// with something other than minifb. winit hands out input as events, while
+// the frame loops ask whether a key is down, so the events are pumped once
+// a frame, when the frame is presented, and kept as the state minifb would
+// report, in minifb's `Key` and `MouseButton`.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use winit::application::ApplicationHandler;
+use winit::dpi::PhysicalSize;
+use winit::event::{ElementState, MouseScrollDelta, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, EventLoop};
+use winit::keyboard::{KeyCode, PhysicalKey};
+use winit::platform::pump_events::{EventLoopExtPumpEvents, PumpStatus};
+use winit::window::{Fullscreen, Window, WindowAttributes, WindowId};
+
+use crate::backend::{Key, MouseButton};
+
+/// Pixels of a touchpad's smooth scrolling that count as one notch of a
+/// wheel.
+const PIXELS_PER_NOTCH: f32 = 20.0;
+
+/// A window, with the event loop it belongs to.
+pub struct WinitWindow {
+    event_loop: EventLoop<()>,
+    state: State,
+}
+
+/// The window and what its events have said so far.
+struct State {
+    attributes: WindowAttributes,
+    window: Option<Arc<Window>>,
+    open: bool,
+    keys_down: Vec<Key>,
+    /// Keys that went down since the frame was last presented, with the
+    /// keyboard's repeat.
+    keys_pressed: Vec<Key>,
+    /// Left, middle and right.
+    buttons: [bool; 3],
+    mouse: Option<(f32, f32)>,
+    scroll: Option<(f32, f32)>,
+}
+
+impl WinitWindow {
+    /// Open a `width` x `height` window titled `title`, which the user can
+    /// resize if `resizable`. winit allows one event loop a process, so
+    /// this panics if called twice.
+    pub fn open(title: &str, width: usize, height: usize, resizable: bool) -> Self {
+        let event_loop = EventLoop::new().expect("Unable to start the event loop");
+        let attributes = Window::default_attributes()
+            .with_title(title)
+            .with_inner_size(PhysicalSize::new(width as u32, height as u32))
+            .with_resizable(resizable);
+        let state = State {
+            attributes,
+            window: None,
+            open: true,
+            keys_down: Vec::new(),
+            keys_pressed: Vec::new(),
+            buttons: [false; 3],
+            mouse: None,
+            scroll: None,
+        };
+        let mut window = Self { event_loop, state };
+        // The window is made once the event loop says it may be.
+        while window.state.window.is_none() && window.state.open {
+            window.pump();
+        }
+        window
+    }
+
+    /// The window, for drawing into.
+    pub fn window(&self) -> Arc<Window> {
+        self.state.window.clone().expect("Unable to create window")
+    }
+
+    /// Forget this frame's key presses and scrolling, and take in the
+    /// events that came since.
+    pub fn update(&mut self) {
+        self.state.keys_pressed.clear();
+        self.state.scroll = None;
+        self.pump();
+    }
+
+    fn pump(&mut self) {
+        let status = self.event_loop.pump_app_events(Some(Duration::ZERO), &mut self.state);
+        if let PumpStatus::Exit(_) = status {
+            self.state.open = false;
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.state.open
+    }
+
+    pub fn is_key_down(&self, key: Key) -> bool {
+        self.state.keys_down.contains(&key)
+    }
+
+    pub fn keys_pressed(&self) -> Vec<Key> {
+        self.state.keys_pressed.clone()
+    }
+
+    pub fn is_mouse_down(&self, button: MouseButton) -> bool {
+        match button {
+            MouseButton::Left => self.state.buttons[0],
+            MouseButton::Middle => self.state.buttons[1],
+            MouseButton::Right => self.state.buttons[2],
+        }
+    }
+
+    pub fn mouse_pos(&self) -> Option<(f32, f32)> {
+        self.state.mouse
+    }
+
+    pub fn scroll_wheel(&self) -> Option<(f32, f32)> {
+        self.state.scroll
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        let size = self.window().inner_size();
+        (size.width as usize, size.height as usize)
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        self.window().set_title(title);
+    }
+
+    /// Fill the monitor the window is on, without a frame, or go back to the
+    /// window as it was. winit sizes a borderless window to the monitor
+    /// itself, so unlike minifb it needs no size for it.
+    pub fn set_fullscreen(&mut self, fullscreen: bool) {
+        self.window().set_fullscreen(fullscreen.then_some(Fullscreen::Borderless(None)));
+    }
+}
+
+impl ApplicationHandler for State {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.window.is_none() {
+            match event_loop.create_window(self.attributes.clone()) {
+                Ok(window) => self.window = Some(Arc::new(window)),
+                Err(e) => panic!("Unable to create window: {e}"),
+            }
+        }
+    }
+
+    fn window_event(&mut self, _: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
+        match event {
+            WindowEvent::CloseRequested | WindowEvent::Destroyed => self.open = false,
+            WindowEvent::KeyboardInput { event, .. } => {
+                let PhysicalKey::Code(code) = event.physical_key else {
+                    return;
+                };
+                let Some(key) = key(code) else {
+                    return;
+                };
+                self.keys_down.retain(|&k| k != key);
+                if event.state == ElementState::Pressed {
+                    self.keys_down.push(key);
+                    self.keys_pressed.push(key);
+                }
+            }
+            // Keys let go of while the window was not listening would stay
+            // down for good.
+            WindowEvent::Focused(false) => {
+                self.keys_down.clear();
+                self.buttons = [false; 3];
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.mouse = Some((position.x as f32, position.y as f32));
+            }
+            WindowEvent::CursorLeft { .. } => self.mouse = None,
+            WindowEvent::MouseInput { state, button, .. } => {
+                let i = match button {
+                    winit::event::MouseButton::Left => 0,
+                    winit::event::MouseButton::Middle => 1,
+                    winit::event::MouseButton::Right => 2,
+                    _ => return,
+                };
+                self.buttons[i] = state == ElementState::Pressed;
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (dx, dy) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (x, y),
+                    MouseScrollDelta::PixelDelta(p) => {
+                        (p.x as f32 / PIXELS_PER_NOTCH, p.y as f32 / PIXELS_PER_NOTCH)
+                    }
+                };
+                let (x, y) = self.scroll.unwrap_or((0.0, 0.0));
+                self.scroll = Some((x + dx, y + dy));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The minifb key for the key at `code`, by where it is on the keyboard
+/// rather than what it types, as minifb reports them.
+fn key(code: KeyCode) -> Option<Key> {
+    Some(match code {
+        KeyCode::Digit0 => Key::Key0,
+        KeyCode::Digit1 => Key::Key1,
+        KeyCode::Digit2 => Key::Key2,
+        KeyCode::Digit3 => Key::Key3,
+        KeyCode::Digit4 => Key::Key4,
+        KeyCode::Digit5 => Key::Key5,
+        KeyCode::Digit6 => Key::Key6,
+        KeyCode::Digit7 => Key::Key7,
+        KeyCode::Digit8 => Key::Key8,
+        KeyCode::Digit9 => Key::Key9,
+        KeyCode::KeyA => Key::A,
+        KeyCode::KeyB => Key::B,
+        KeyCode::KeyC => Key::C,
+        KeyCode::KeyD => Key::D,
+        KeyCode::KeyE => Key::E,
+        KeyCode::KeyF => Key::F,
+        KeyCode::KeyG => Key::G,
+        KeyCode::KeyH => Key::H,
+        KeyCode::KeyI => Key::I,
+        KeyCode::KeyJ => Key::J,
+        KeyCode::KeyK => Key::K,
+        KeyCode::KeyL => Key::L,
+        KeyCode::KeyM => Key::M,
+        KeyCode::KeyN => Key::N,
+        KeyCode::KeyO => Key::O,
+        KeyCode::KeyP => Key::P,
+        KeyCode::KeyQ => Key::Q,
+        KeyCode::KeyR => Key::R,
+        KeyCode::KeyS => Key::S,
+        KeyCode::KeyT => Key::T,
+        KeyCode::KeyU => Key::U,
+        KeyCode::KeyV => Key::V,
+        KeyCode::KeyW => Key::W,
+        KeyCode::KeyX => Key::X,
+        KeyCode::KeyY => Key::Y,
+        KeyCode::KeyZ => Key::Z,
+        KeyCode::F1 => Key::F1,
+        KeyCode::F2 => Key::F2,
+        KeyCode::F3 => Key::F3,
+        KeyCode::F4 => Key::F4,
+        KeyCode::F5 => Key::F5,
+        KeyCode::F6 => Key::F6,
+        KeyCode::F7 => Key::F7,
+        KeyCode::F8 => Key::F8,
+        KeyCode::F9 => Key::F9,
+        KeyCode::F10 => Key::F10,
+        KeyCode::F11 => Key::F11,
+        KeyCode::F12 => Key::F12,
+        KeyCode::F13 => Key::F13,
+        KeyCode::F14 => Key::F14,
+        KeyCode::F15 => Key::F15,
+        KeyCode::ArrowDown => Key::Down,
+        KeyCode::ArrowLeft => Key::Left,
+        KeyCode::ArrowRight => Key::Right,
+        KeyCode::ArrowUp => Key::Up,
+        KeyCode::Quote => Key::Apostrophe,
+        KeyCode::Backquote => Key::Backquote,
+        KeyCode::Backslash => Key::Backslash,
+        KeyCode::Comma => Key::Comma,
+        KeyCode::Equal => Key::Equal,
+        KeyCode::BracketLeft => Key::LeftBracket,
+        KeyCode::Minus => Key::Minus,
+        KeyCode::Period => Key::Period,
+        KeyCode::BracketRight => Key::RightBracket,
+        KeyCode::Semicolon => Key::Semicolon,
+        KeyCode::Slash => Key::Slash,
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::Delete => Key::Delete,
+        KeyCode::End => Key::End,
+        KeyCode::Enter => Key::Enter,
+        KeyCode::Escape => Key::Escape,
+        KeyCode::Home => Key::Home,
+        KeyCode::Insert => Key::Insert,
+        KeyCode::ContextMenu => Key::Menu,
+        KeyCode::PageDown => Key::PageDown,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::Pause => Key::Pause,
+        KeyCode::Space => Key::Space,
+        KeyCode::Tab => Key::Tab,
+        KeyCode::NumLock => Key::NumLock,
+        KeyCode::CapsLock => Key::CapsLock,
+        KeyCode::ScrollLock => Key::ScrollLock,
+        KeyCode::ShiftLeft => Key::LeftShift,
+        KeyCode::ShiftRight => Key::RightShift,
+        KeyCode::ControlLeft => Key::LeftCtrl,
+        KeyCode::ControlRight => Key::RightCtrl,
+        KeyCode::Numpad0 => Key::NumPad0,
+        KeyCode::Numpad1 => Key::NumPad1,
+        KeyCode::Numpad2 => Key::NumPad2,
+        KeyCode::Numpad3 => Key::NumPad3,
+        KeyCode::Numpad4 => Key::NumPad4,
+        KeyCode::Numpad5 => Key::NumPad5,
+        KeyCode::Numpad6 => Key::NumPad6,
+        KeyCode::Numpad7 => Key::NumPad7,
+        KeyCode::Numpad8 => Key::NumPad8,
+        KeyCode::Numpad9 => Key::NumPad9,
+        KeyCode::NumpadDecimal => Key::NumPadDot,
+        KeyCode::NumpadDivide => Key::NumPadSlash,
+        KeyCode::NumpadMultiply => Key::NumPadAsterisk,
+        KeyCode::NumpadSubtract => Key::NumPadMinus,
+        KeyCode::NumpadAdd => Key::NumPadPlus,
+        KeyCode::NumpadEnter => Key::NumPadEnter,
+        KeyCode::AltLeft => Key::LeftAlt,
+        KeyCode::AltRight => Key::RightAlt,
+        KeyCode::SuperLeft => Key::LeftSuper,
+        KeyCode::SuperRight => Key::RightSuper,
+        _ => return None,
+    })
+}
@@ -0,0 +1,1376 @@
+// The sand world: the cell grid, the list of moving grains and the physics
+// that moves them. Has no knowledge of windows or input.
+
+use unirand::MarsagliaUniRng;
+
+use crate::chunks::Chunks;
+use crate::climate::{self, DayNight};
+use crate::events::{Event, Handler};
+use crate::grid::Grid;
+use crate::heat::{self, AMBIENT};
+use crate::input::{self, InputState};
+use crate::material::{Material, Movement, SHADES};
+use crate::rules::Rule;
+use crate::weather::{self, Weather};
+use crate::{attractors, conveyors, explosions, fountains, physics, portals, power, reactions};
+
+/// Downward acceleration of a falling grain, in cells per tick per tick,
+/// unless the world is given another.
+pub const GRAVITY: f32 = 0.2;
+/// Fastest a grain can fall, in cells per tick.
+pub const MAX_SPEED: f32 = 8.0;
+/// Speed of a grain that has just started to fall, in cells per tick.
+const START_SPEED: f32 = 1.0;
+
+/// Ticks a grain must go without moving before it falls asleep and is
+/// skipped by the update until something next to it changes.
+pub const SLEEP_TICKS: u8 = 30;
+
+/// Marks a cell with no grain in the grain index.
+pub(crate) const NO_GRAIN: u32 = u32::MAX;
+
+/// A single movable particle and the cell it occupies.
+#[derive(Clone, Copy)]
+pub struct Grain {
+    pub x: usize,
+    pub y: usize,
+    pub material: Material,
+    /// Velocity in cells per tick.
+    pub vx: f32,
+    pub vy: f32,
+    /// Fraction of a cell travelled but not yet committed to the grid.
+    pub fx: f32,
+    pub fy: f32,
+    /// Ticks left before the grain burns out or fades, for materials with
+    /// a lifetime.
+    pub life: u16,
+    /// Which of the material's colour variants the grain is drawn in.
+    pub shade: u8,
+    /// Ticks in a row the grain has not moved, up to `SLEEP_TICKS`.
+    pub idle: u8,
+}
+
+impl Grain {
+    /// A grain at rest at `(x, y)`, given the middle of its material's
+    /// lifetime.
+    pub fn new(x: usize, y: usize, material: Material) -> Self {
+        let life = material.props().lifetime.as_ref().map_or(0, |r| (r.start + r.end) / 2);
+        let shade = position_shade(x, y);
+        Self { x, y, material, vx: 0.0, vy: START_SPEED, fx: 0.0, fy: 0.0, life, shade, idle: 0 }
+    }
+
+    /// True once the grain has stopped long enough to be skipped.
+    pub fn is_asleep(&self) -> bool {
+        self.idle >= SLEEP_TICKS
+    }
+
+    /// True if the grain is moving other than as it would start to fall
+    /// along `gravity`, such as after being thrown by an explosion.
+    pub(crate) fn is_launched(&self, gravity: Gravity) -> bool {
+        let (gx, gy) = gravity.vector();
+        self.vx != gx as f32 * START_SPEED || self.vy != gy as f32 * START_SPEED
+    }
+
+    /// Drop any velocity, as when the grain lands on something, leaving it
+    /// ready to fall along `gravity`.
+    pub(crate) fn land(&mut self, gravity: Gravity) {
+        let (gx, gy) = gravity.vector();
+        self.vx = gx as f32 * START_SPEED;
+        self.vy = gy as f32 * START_SPEED;
+        self.fx = 0.0;
+        self.fy = 0.0;
+    }
+}
+
+/// Which way grains fall. Gases rise the opposite way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Gravity {
+    Down,
+    Left,
+    Up,
+    Right,
+}
+
+impl Gravity {
+    /// Unit step in the direction of the pull.
+    pub fn vector(self) -> (isize, isize) {
+        match self {
+            Gravity::Down => (0, 1),
+            Gravity::Left => (-1, 0),
+            Gravity::Up => (0, -1),
+            Gravity::Right => (1, 0),
+        }
+    }
+
+    /// The pull turned round, as when an hourglass is flipped.
+    pub fn opposite(self) -> Self {
+        match self {
+            Gravity::Down => Gravity::Up,
+            Gravity::Left => Gravity::Right,
+            Gravity::Up => Gravity::Down,
+            Gravity::Right => Gravity::Left,
+        }
+    }
+
+    /// Unit step at right angles to the pull, for sliding and flowing.
+    pub(crate) fn across(self) -> (isize, isize) {
+        let (gx, gy) = self.vector();
+        (gy, gx)
+    }
+}
+
+/// Which edges of the world join up with the opposite one, so grains going
+/// off one side come back on the other.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Wrap {
+    /// The left edge joins the right.
+    pub horizontal: bool,
+    /// The top edge joins the bottom.
+    pub vertical: bool,
+}
+
+impl Wrap {
+    /// Where `(x, y)` really is in a `width` x `height` world: off a joined
+    /// edge it comes back on the other side, and off any other edge it is
+    /// left out of bounds.
+    pub(crate) fn join(self, width: usize, height: usize, x: isize, y: isize) -> (isize, isize) {
+        let (w, h) = (width as isize, height as isize);
+        let x = if self.horizontal { x.rem_euclid(w) } else { x };
+        let y = if self.vertical { y.rem_euclid(h) } else { y };
+        (x, y)
+    }
+}
+
+/// A shade picked by hashing the cell position, so it is stable for cells
+/// that never move and looks random across neighbours.
+fn position_shade(x: usize, y: usize) -> u8 {
+    let mut h = (x as u32).wrapping_mul(0x9e37_79b1) ^ (y as u32).wrapping_mul(0x85eb_ca77);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b_3c6d);
+    h ^= h >> 13;
+    (h % SHADES as u32) as u8
+}
+
+/// A horizontal segment of `2 * half_width + 1` cells centred on `(x, y)`
+/// that swallows grains in it, or resting on top of it, while open.
+#[derive(Clone, Copy)]
+pub struct Drain {
+    pub x: usize,
+    pub y: usize,
+    pub half_width: usize,
+}
+
+impl Drain {
+    /// First and last column covered, clipped to a world `width` wide.
+    pub fn span(&self, width: usize) -> (usize, usize) {
+        (self.x.saturating_sub(self.half_width), (self.x + self.half_width).min(width - 1))
+    }
+}
+
+/// A source that scatters grains of `material` inside the circle of
+/// `radius` around `(x, y)` every tick while enabled.
+#[derive(Clone, Copy)]
+pub struct Emitter {
+    pub x: usize,
+    pub y: usize,
+    pub radius: usize,
+    /// Grains per tick. A fractional part is a chance of one more.
+    pub rate: f32,
+    pub material: Material,
+    pub enabled: bool,
+}
+
+/// A point that pulls grains towards it, with a force falling off with the
+/// square of the distance, or pushes them away if `strength` is negative.
+#[derive(Clone, Copy)]
+pub struct Attractor {
+    pub x: usize,
+    pub y: usize,
+    /// Pull one cell away, in cells per tick per tick.
+    pub strength: f32,
+}
+
+/// A pair of portals: discs of portal cells of `radius` around `a` and `b`.
+/// A grain moving into one end comes out of the other at the same place
+/// relative to its middle, still moving the same way at the same speed.
+#[derive(Clone, Copy)]
+pub struct Portal {
+    pub a: (usize, usize),
+    pub b: (usize, usize),
+    pub radius: usize,
+}
+
+impl Portal {
+    /// True if `(x, y)` lies in the disc around `end`.
+    pub(crate) fn covers(&self, end: (usize, usize), x: usize, y: usize) -> bool {
+        let (dx, dy) = (x.abs_diff(end.0), y.abs_diff(end.1));
+        dx * dx + dy * dy <= self.radius * self.radius
+    }
+}
+
+/// A rectangle of cells copied out of a world, with the grains in it, so it
+/// can be pasted back in elsewhere.
+#[derive(Clone)]
+pub struct Region {
+    cells: Grid<Material>,
+    /// The grains in the region, at positions relative to its top-left.
+    grains: Vec<Grain>,
+}
+
+impl Region {
+    /// A region holding `cells`, with a grain at rest in each cell of a
+    /// movable material.
+    pub fn from_cells(cells: Grid<Material>) -> Self {
+        let mut grains = Vec::new();
+        for y in 0..cells.height() {
+            for x in 0..cells.width() {
+                let material = cells.get(x, y);
+                if material.is_movable() {
+                    grains.push(Grain::new(x, y, material));
+                }
+            }
+        }
+        Self { cells, grains }
+    }
+
+    /// The region mirrored left to right if `flip_x` and top to bottom if
+    /// `flip_y`.
+    pub fn flipped(&self, flip_x: bool, flip_y: bool) -> Self {
+        let (w, h) = (self.width(), self.height());
+        let map = |x: usize, y: usize| {
+            (if flip_x { w - 1 - x } else { x }, if flip_y { h - 1 - y } else { y })
+        };
+        let mut cells = Grid::new(w, h, Material::Empty);
+        for y in 0..h {
+            for x in 0..w {
+                let (fx, fy) = map(x, y);
+                cells.set(fx, fy, self.cells.get(x, y));
+            }
+        }
+        let grains = self
+            .grains
+            .iter()
+            .map(|g| {
+                let (x, y) = map(g.x, g.y);
+                let vx = if flip_x { -g.vx } else { g.vx };
+                let vy = if flip_y { -g.vy } else { g.vy };
+                Grain { x, y, vx, vy, ..*g }
+            })
+            .collect();
+        Self { cells, grains }
+    }
+
+    pub fn width(&self) -> usize {
+        self.cells.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.cells.height()
+    }
+
+    pub fn cells(&self) -> &Grid<Material> {
+        &self.cells
+    }
+}
+
+/// A grid of cells plus the grains moving through it.
+pub struct World {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    pub(crate) grid: Grid<Material>,
+    pub(crate) grains: Vec<Grain>,
+    // Index into `grains` of the grain in each cell, or NO_GRAIN.
+    pub(crate) index: Grid<u32>,
+    // Temperature of each cell.
+    pub(crate) heat: Grid<f32>,
+
+    pub(crate) drains: Vec<Drain>,
+    // Grains each drain has swallowed, in step with `drains`.
+    pub(crate) drained: Vec<u64>,
+    pub(crate) emitters: Vec<Emitter>,
+    pub(crate) portals: Vec<Portal>,
+    pub(crate) attractors: Vec<Attractor>,
+    pub(crate) weather: Option<Weather>,
+    pub(crate) day_night: Option<DayNight>,
+    // Gunpowder cells set off this tick, blown up after the reactions.
+    pub(crate) explosions: Vec<(usize, usize)>,
+    // Reactions from a rule table, applied after the built-in ones.
+    pub(crate) rules: Vec<Rule>,
+    // Metal cells sparking this tick, and those that were last tick.
+    pub(crate) sparks: Vec<(usize, usize)>,
+    pub(crate) tails: Vec<(usize, usize)>,
+    // Which cells are sparking or just have, and cannot be sparked again.
+    pub(crate) charged: Grid<bool>,
+    // What each clone cell copies, or empty until a grain touches it.
+    pub(crate) cloned: Grid<Material>,
+
+    // Only grains in chunks that changed last tick are updated.
+    pub(crate) chunks: Chunks,
+
+    pub(crate) rng: MarsagliaUniRng,
+    gravity: Gravity,
+    // Acceleration of a falling grain, `GRAVITY` unless changed.
+    pub(crate) pull: f32,
+    wrap: Wrap,
+    ticks: u64,
+
+    // Called with every event, in the order they were added.
+    handlers: Vec<Handler>,
+}
+
+impl World {
+    /// Create an empty world. `seed` must be in `0..=900_000_000`.
+    pub fn new(width: usize, height: usize, seed: i32) -> Self {
+        let mut rng = MarsagliaUniRng::new();
+        rng.rinit(seed);
+
+        Self {
+            width,
+            height,
+            grid: Grid::new(width, height, Material::Empty),
+            grains: Vec::new(),
+            index: Grid::new(width, height, NO_GRAIN),
+            heat: Grid::new(width, height, AMBIENT),
+            drains: Vec::new(),
+            drained: Vec::new(),
+            emitters: Vec::new(),
+            portals: Vec::new(),
+            attractors: Vec::new(),
+            weather: None,
+            day_night: None,
+            explosions: Vec::new(),
+            rules: Vec::new(),
+            sparks: Vec::new(),
+            tails: Vec::new(),
+            charged: Grid::new(width, height, false),
+            cloned: Grid::new(width, height, Material::Empty),
+            chunks: Chunks::new(width, height),
+            rng,
+            gravity: Gravity::Down,
+            pull: GRAVITY,
+            wrap: Wrap::default(),
+            ticks: 0,
+            handlers: Vec::new(),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Largest brush radius worth asking for: the longer side of the world.
+    /// Brushes are swept cell by cell, so a bigger one only costs time.
+    pub fn max_radius(&self) -> usize {
+        self.width.max(self.height)
+    }
+
+    /// The cell grid.
+    pub fn cells(&self) -> &Grid<Material> {
+        &self.grid
+    }
+
+    pub fn grains(&self) -> &[Grain] {
+        &self.grains
+    }
+
+    /// The grain in cell `(x, y)`, if it holds one rather than a static
+    /// cell or nothing.
+    pub fn grain_at(&self, x: usize, y: usize) -> Option<&Grain> {
+        let idx = self.index.get(x, y);
+        (idx != NO_GRAIN).then(|| &self.grains[idx as usize])
+    }
+
+    /// The temperature of every cell.
+    pub fn heat(&self) -> &Grid<f32> {
+        &self.heat
+    }
+
+    /// Colour variant to draw cell `(x, y)` in: the grain's own shade, or
+    /// one picked from the position for static cells.
+    #[inline]
+    pub fn shade_at(&self, x: usize, y: usize) -> u8 {
+        match self.index.get(x, y) {
+            NO_GRAIN => position_shade(x, y),
+            idx => self.grains[idx as usize].shade,
+        }
+    }
+
+    /// Number of ticks simulated so far.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    pub fn gravity(&self) -> Gravity {
+        self.gravity
+    }
+
+    /// Point gravity a new way. Every grain is stopped and woken so the
+    /// whole world starts falling the new way at once.
+    pub fn set_gravity(&mut self, gravity: Gravity) {
+        if gravity == self.gravity {
+            return;
+        }
+        self.gravity = gravity;
+        for g in &mut self.grains {
+            g.land(gravity);
+            g.idle = 0;
+        }
+        self.chunks.wake_all();
+    }
+
+    /// How fast falling grains speed up, in cells per tick per tick.
+    pub fn gravity_strength(&self) -> f32 {
+        self.pull
+    }
+
+    /// Make falling grains speed up by `strength` cells per tick every tick,
+    /// from nothing up to `MAX_SPEED`.
+    pub fn set_gravity_strength(&mut self, strength: f32) {
+        debug_assert!(strength.is_finite(), "gravity strength {strength}");
+        if !strength.is_finite() {
+            return;
+        }
+        self.pull = strength.clamp(0.0, MAX_SPEED);
+        self.chunks.wake_all();
+    }
+
+    /// Start the random number generator again from `seed`, as
+    /// `World::new` does.
+    pub fn reseed(&mut self, seed: i32) {
+        self.rng.rinit(seed);
+    }
+
+    /// How many falling grains, powders and liquids, are still in the half
+    /// of the world that gravity pulls away from: the top bulb of an
+    /// hourglass.
+    pub fn grains_uphill(&self) -> usize {
+        let (gx, gy) = self.gravity.vector();
+        let (w, h) = (self.width() as isize, self.height() as isize);
+        self.grains
+            .iter()
+            .filter(|g| matches!(g.material.props().movement, Movement::Powder | Movement::Liquid))
+            .filter(|g| {
+                // Twice the offset from the middle, to stay in whole cells.
+                let (dx, dy) = (2 * g.x as isize - (w - 1), 2 * g.y as isize - (h - 1));
+                dx * gx + dy * gy < 0
+            })
+            .count()
+    }
+
+    pub fn weather(&self) -> Option<Weather> {
+        self.weather
+    }
+
+    /// Start rain or snow falling, or stop it with `None`.
+    pub fn set_weather(&mut self, weather: Option<Weather>) {
+        if let Some(w) = weather {
+            debug_assert!(w.rate.is_finite() && w.wind.is_finite(), "weather {w:?}");
+            if !(w.rate.is_finite() && w.wind.is_finite()) {
+                return;
+            }
+        }
+        self.weather = weather;
+    }
+
+    pub fn day_night(&self) -> Option<DayNight> {
+        self.day_night
+    }
+
+    /// Start the air warming and cooling over a day, or hold it at
+    /// `AMBIENT` with `None`.
+    pub fn set_day_night(&mut self, day_night: Option<DayNight>) {
+        self.day_night = day_night;
+    }
+
+    /// The temperature of the open air now.
+    pub fn ambient(&self) -> f32 {
+        self.day_night.map_or(AMBIENT, |d| d.ambient(self.ticks))
+    }
+
+    pub fn wrap(&self) -> Wrap {
+        self.wrap
+    }
+
+    /// Join the world's edges up as `wrap` says. Grains move across joined
+    /// edges; reactions and heat still stop at them.
+    pub fn set_wrap(&mut self, wrap: Wrap) {
+        self.wrap = wrap;
+        self.chunks.wake_all();
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Replace the table of reactions applied each tick after the built-in
+    /// ones.
+    pub fn set_rules(&mut self, rules: Vec<Rule>) {
+        self.rules = rules;
+    }
+
+    /// Call `handler` with every event from now on, after any handlers
+    /// already added. Handlers see only the event, not the world, and are
+    /// called in the middle of a tick, so should be quick.
+    pub fn on_event(&mut self, handler: impl FnMut(&Event) + Send + Sync + 'static) {
+        self.handlers.push(Box::new(handler));
+    }
+
+    /// Pass `event` to every handler.
+    pub(crate) fn send(&mut self, event: Event) {
+        for handler in &mut self.handlers {
+            handler(&event);
+        }
+    }
+
+    /// Per-chunk activity and redraw flags.
+    pub fn chunks(&self) -> &Chunks {
+        &self.chunks
+    }
+
+    /// Tell the world the frontend has redrawn every flagged chunk.
+    pub fn clear_redraw(&mut self) {
+        self.chunks.clear_redraw();
+    }
+
+    /// Replace the grid and grain list wholesale, e.g. when loading a save.
+    /// The caller guarantees both match this world's size and each other.
+    pub(crate) fn restore(&mut self, mut grid: Grid<Material>, grains: Vec<Grain>) {
+        // Sparks are not saved, so any caught mid-wire are plain metal again.
+        for m in grid.as_mut_slice() {
+            if *m == Material::Spark {
+                *m = Material::Metal;
+            }
+        }
+        self.discharge();
+        // Nor is what clones copy, so they learn it again.
+        self.cloned.fill(Material::Empty);
+        self.heat = heat::initial(&grid);
+        self.grid = grid;
+        self.grains = grains;
+        for g in &mut self.grains {
+            g.land(self.gravity);
+        }
+        self.reindex();
+        self.chunks.wake_all();
+    }
+
+    /// Rebuild the grain index after grains were removed from the list.
+    pub(crate) fn reindex(&mut self) {
+        self.index.fill(NO_GRAIN);
+        for (i, g) in self.grains.iter().enumerate() {
+            self.index.set(g.x, g.y, i as u32);
+        }
+    }
+
+    /// A new grain at `(x, y)` with a lifetime picked from its material's.
+    pub(crate) fn new_grain(&mut self, x: usize, y: usize, material: Material) -> Grain {
+        let mut g = Grain::new(x, y, material);
+        g.land(self.gravity);
+        g.life = self.roll_life(material);
+        g
+    }
+
+    /// A lifetime for a new cell of `material`, anywhere in its range, or 0
+    /// if it lasts forever.
+    pub(crate) fn roll_life(&mut self, material: Material) -> u16 {
+        match &material.props().lifetime {
+            Some(r) => r.start + (self.rng.uni() * (r.end - r.start) as f32) as u16,
+            None => 0,
+        }
+    }
+
+    /// Record that cell `(x, y)` was emptied, or now holds something
+    /// lighter: wake its chunk and any sleeping grains around it, which may
+    /// now be free to move into it.
+    pub(crate) fn free_cell(&mut self, x: usize, y: usize) {
+        self.chunks.wake(x, y);
+        let (x0, y0) = (x.saturating_sub(1), y.saturating_sub(1));
+        let (x1, y1) = ((x + 1).min(self.width - 1), (y + 1).min(self.height - 1));
+        for ny in y0..=y1 {
+            for &idx in &self.index.row(ny)[x0..=x1] {
+                if idx != NO_GRAIN {
+                    self.grains[idx as usize].idle = 0;
+                }
+            }
+        }
+
+        // Cells on a joined edge have neighbours on the far side too.
+        let (w, h) = (self.width, self.height);
+        if (self.wrap.horizontal && (x == 0 || x == w - 1))
+            || (self.wrap.vertical && (y == 0 || y == h - 1))
+        {
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let (nx, ny) = self.wrap.join(w, h, x as isize + dx, y as isize + dy);
+                    if self.in_bounds(nx, ny) {
+                        self.wake_grain(nx as usize, ny as usize);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Wake the grain at `(x, y)`, if there is one, and its chunk.
+    fn wake_grain(&mut self, x: usize, y: usize) {
+        let idx = self.index.get(x, y);
+        if idx != NO_GRAIN {
+            self.grains[idx as usize].idle = 0;
+            self.chunks.wake(x, y);
+        }
+    }
+
+    /// Empty cell `(x, y)`, marking any grain in it for removal, and wake
+    /// everything around it so whatever rested on it falls into the hole
+    /// next tick. Every removal goes through here so none can leave the
+    /// cells above asleep or the cell's old temperature behind. Returns true
+    /// if a grain was marked; the caller drops marked grains and reindexes.
+    pub(crate) fn clear_cell(&mut self, x: usize, y: usize) -> bool {
+        let idx = self.index.get(x, y);
+        if idx != NO_GRAIN {
+            self.grains[idx as usize].material = Material::Empty;
+            self.index.set(x, y, NO_GRAIN);
+        }
+        self.grid.set(x, y, Material::Empty);
+        self.heat.set(x, y, AMBIENT);
+        self.cloned.set(x, y, Material::Empty);
+        self.free_cell(x, y);
+        idx != NO_GRAIN
+    }
+
+    /// Put `grain` into the grid and the grain list. Its cell must be empty.
+    /// Every grain comes into the world through here, which is what sends
+    /// `Event::GrainSpawned`.
+    pub(crate) fn add_grain(&mut self, grain: Grain) {
+        let (x, y, material) = (grain.x, grain.y, grain.material);
+        self.grid.set(x, y, material);
+        self.heat.set(x, y, material.props().temperature);
+        self.index.set(x, y, self.grains.len() as u32);
+        self.grains.push(grain);
+        self.chunks.wake(x, y);
+        self.send(Event::GrainSpawned { x, y, material });
+    }
+
+    fn in_bounds(&self, x: isize, y: isize) -> bool {
+        self.grid.in_bounds(x, y)
+    }
+
+    fn is_empty(&self, x: isize, y: isize) -> bool {
+        self.in_bounds(x, y) && self.grid.get(x as usize, y as usize) == Material::Empty
+    }
+
+    fn is_flammable(&self, x: isize, y: isize) -> bool {
+        self.in_bounds(x, y) && self.grid.get(x as usize, y as usize).props().flammability > 0.0
+    }
+
+    /// Put a cell of `material` at `(x, y)` if it is empty: a grain for a
+    /// movable material, a static cell otherwise.
+    pub fn place(&mut self, x: usize, y: usize, material: Material) {
+        if self.grid.get(x, y) != Material::Empty {
+            return;
+        }
+        if material.is_movable() {
+            let grain = self.new_grain(x, y, material);
+            self.add_grain(grain);
+        } else {
+            self.grid.set(x, y, material);
+            self.heat.set(x, y, material.props().temperature);
+            self.chunks.wake(x, y);
+        }
+    }
+
+    /// Empty the world of every cell, grain, drain and emitter.
+    pub fn clear(&mut self) {
+        self.grid.fill(Material::Empty);
+        self.heat.fill(AMBIENT);
+        self.grains.clear();
+        self.index.fill(NO_GRAIN);
+        self.drains.clear();
+        self.drained.clear();
+        self.emitters.clear();
+        self.portals.clear();
+        self.attractors.clear();
+        self.explosions.clear();
+        self.discharge();
+        self.cloned.fill(Material::Empty);
+        self.chunks.wake_all();
+    }
+
+    /// Stop every spark.
+    fn discharge(&mut self) {
+        self.sparks.clear();
+        self.tails.clear();
+        self.charged.fill(false);
+    }
+
+    /// Throw the loose grains within `radius` cells of `(cx, cy)` away from
+    /// it, at up to `speed` cells per tick near the centre falling off to
+    /// nothing at `radius`. Thrown grains fly until they hit something, then
+    /// fall as usual. Gases are left alone.
+    pub fn shockwave(&mut self, cx: usize, cy: usize, radius: usize, speed: f32) {
+        let r = radius as isize;
+        for dy in -r..=r {
+            for dx in -r..=r {
+                let (x, y) = (cx as isize + dx, cy as isize + dy);
+                let d2 = dx * dx + dy * dy;
+                if d2 == 0 || d2 > r * r || !self.in_bounds(x, y) {
+                    continue;
+                }
+                let (x, y) = (x as usize, y as usize);
+                let idx = self.index.get(x, y);
+                if idx == NO_GRAIN {
+                    continue;
+                }
+                let g = &mut self.grains[idx as usize];
+                if g.material.props().movement == Movement::Gas {
+                    continue;
+                }
+                let d = (d2 as f32).sqrt();
+                let push = speed * (radius as f32 - d) / radius as f32 / d;
+                g.vx = (g.vx + dx as f32 * push).clamp(-MAX_SPEED, MAX_SPEED);
+                g.vy = (g.vy + dy as f32 * push).clamp(-MAX_SPEED, MAX_SPEED);
+                g.idle = 0;
+                self.chunks.wake(x, y);
+            }
+        }
+    }
+
+    /// Jolt every grain sideways, across gravity, at a random speed of up
+    /// to `speed` cells per tick either way, and wake them all, so steep
+    /// piles slump. Gases are left alone.
+    pub fn shake(&mut self, speed: f32) {
+        debug_assert!(speed.is_finite(), "shake speed {speed}");
+        if !speed.is_finite() {
+            return;
+        }
+        let (ax, ay) = self.gravity.across();
+        for idx in 0..self.grains.len() {
+            if self.grains[idx].material.props().movement == Movement::Gas {
+                continue;
+            }
+            let kick = (self.rng.uni() * 2.0 - 1.0) * speed;
+            let g = &mut self.grains[idx];
+            g.vx = (g.vx + ax as f32 * kick).clamp(-MAX_SPEED, MAX_SPEED);
+            g.vy = (g.vy + ay as f32 * kick).clamp(-MAX_SPEED, MAX_SPEED);
+            g.idle = 0;
+        }
+        self.chunks.wake_all();
+    }
+
+    /// Set the cell at `(x, y)` alight. Fuel like oil burns where it is for
+    /// a while, gunpowder is queued to explode, and anything else is
+    /// replaced by fire.
+    pub(crate) fn ignite(&mut self, x: usize, y: usize) {
+        match self.grid.get(x, y) {
+            // A cell may have been caught by two fires.
+            Material::Fire => return,
+            Material::Gunpowder => {
+                self.explosions.push((x, y));
+                return;
+            }
+            _ => {}
+        }
+        let fuel = self.grid.get(x, y).props().burn_time;
+        let idx = self.index.get(x, y);
+        if fuel > 0 && idx != NO_GRAIN {
+            let life = (fuel as f32 * (0.5 + self.rng.uni())) as u16 + 1;
+            let g = &mut self.grains[idx as usize];
+            if g.life == 0 {
+                g.life = life;
+                self.chunks.wake(x, y);
+            }
+            return;
+        }
+        let fire = self.new_grain(x, y, Material::Fire);
+        match self.index.get(x, y) {
+            NO_GRAIN => self.add_grain(fire),
+            idx => {
+                self.grains[idx as usize] = fire;
+                self.grid.set(x, y, Material::Fire);
+                self.heat.set(x, y, Material::Fire.props().temperature);
+                self.free_cell(x, y);
+            }
+        }
+    }
+
+    /// Scatter up to `tries` grains of `material` at random points inside the
+    /// circle of `radius` around `(cx, cy)`. Occupied or off-world points are
+    /// skipped, except that fire sets flammable cells alight.
+    pub fn spawn(&mut self, cx: isize, cy: isize, radius: usize, tries: usize, material: Material) {
+        for _ in 0..tries {
+            loop {
+                let span = (2 * radius + 1) as f32;
+                let dx = (self.rng.uni() * span).floor() as isize - radius as isize;
+                let dy = (self.rng.uni() * span).floor() as isize - radius as isize;
+                if dx * dx + dy * dy > (radius * radius) as isize {
+                    continue;
+                }
+                let (x, y) = (cx + dx, cy + dy);
+                if self.is_empty(x, y) {
+                    let (x, y) = (x as usize, y as usize);
+                    let grain = self.new_grain(x, y, material);
+                    self.add_grain(grain);
+                } else if material == Material::Fire && self.is_flammable(x, y) {
+                    self.ignite(x as usize, y as usize);
+                }
+                break;
+            }
+        }
+    }
+
+    /// Fill every empty cell inside the circle of `radius` around `(cx, cy)`
+    /// with a static `material` such as wall. Static cells are not grains and
+    /// never move.
+    pub fn paint(&mut self, cx: isize, cy: isize, radius: usize, material: Material) {
+        let r = radius as isize;
+        for dy in -r..=r {
+            for dx in -r..=r {
+                if dx * dx + dy * dy > r * r {
+                    continue;
+                }
+                let (x, y) = (cx + dx, cy + dy);
+                if self.is_empty(x, y) {
+                    self.grid.set(x as usize, y as usize, material);
+                    self.heat.set(x as usize, y as usize, material.props().temperature);
+                    self.chunks.wake(x as usize, y as usize);
+                }
+            }
+        }
+    }
+
+    /// Fill the empty area around `(x, y)`, as far as it reaches through
+    /// the four neighbours of each cell, with `material`. Does nothing if
+    /// the cell is not empty.
+    pub fn flood(&mut self, x: usize, y: usize, material: Material) {
+        if self.grid.get(x, y) != Material::Empty || material == Material::Empty {
+            return;
+        }
+        let temperature = material.props().temperature;
+        for (y, x0, x1) in self.grid.flood_fill(x, y, material) {
+            if material.is_movable() {
+                for x in x0..=x1 {
+                    let grain = self.new_grain(x, y, material);
+                    self.add_grain(grain);
+                }
+            }
+            self.heat.row_mut(y)[x0..=x1].fill(temperature);
+            self.chunks.wake_rect(x0, y, x1, y);
+        }
+    }
+
+    /// Fill the empty cells within `radius` of the straight line from
+    /// `from` to `to` with `material`, as grains if it moves.
+    pub fn line(
+        &mut self,
+        from: (usize, usize),
+        to: (usize, usize),
+        radius: usize,
+        material: Material,
+    ) {
+        let (x0, y0) = (from.0 as f32, from.1 as f32);
+        let (x1, y1) = (to.0 as f32, to.1 as f32);
+        let steps = (x1 - x0).abs().max((y1 - y0).abs()).ceil().max(1.0) as usize;
+        let r = radius as isize;
+        for i in 0..=steps {
+            let t = i as f32 / steps as f32;
+            let cx = (x0 + (x1 - x0) * t).round() as isize;
+            let cy = (y0 + (y1 - y0) * t).round() as isize;
+            for dy in -r..=r {
+                for dx in -r..=r {
+                    let (x, y) = (cx + dx, cy + dy);
+                    if dx * dx + dy * dy <= r * r && self.in_bounds(x, y) {
+                        self.place(x as usize, y as usize, material);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fill the empty cells of the box with corners `from` and `to` with
+    /// `material`, or only those on its edge if `outline` is set.
+    pub fn rect(
+        &mut self,
+        from: (usize, usize),
+        to: (usize, usize),
+        material: Material,
+        outline: bool,
+    ) {
+        let (x0, x1) = (from.0.min(to.0), from.0.max(to.0).min(self.width - 1));
+        let (y0, y1) = (from.1.min(to.1), from.1.max(to.1).min(self.height - 1));
+        if outline {
+            self.fill_box(x0, y0, x1, y0, material);
+            self.fill_box(x0, y1, x1, y1, material);
+            self.fill_box(x0, y0, x0, y1, material);
+            self.fill_box(x1, y0, x1, y1, material);
+        } else {
+            self.fill_box(x0, y0, x1, y1, material);
+        }
+    }
+
+    /// Fill the empty cells from `(x0, y0)` to `(x1, y1)` inclusive, waking
+    /// the chunks once for the whole box rather than cell by cell.
+    fn fill_box(&mut self, x0: usize, y0: usize, x1: usize, y1: usize, material: Material) {
+        let temperature = material.props().temperature;
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                if self.grid.get(x, y) != Material::Empty {
+                    continue;
+                }
+                if material.is_movable() {
+                    let grain = self.new_grain(x, y, material);
+                    self.add_grain(grain);
+                } else {
+                    self.grid.set(x, y, material);
+                    self.heat.set(x, y, temperature);
+                }
+            }
+        }
+        self.chunks.wake_rect(x0, y0, x1, y1);
+    }
+
+    /// Copy the box with corners `from` and `to`, clipped to the world, as
+    /// a region that can be pasted with `paste`.
+    pub fn copy(&self, from: (usize, usize), to: (usize, usize)) -> Region {
+        let (x0, x1) = (from.0.min(to.0), from.0.max(to.0).min(self.width - 1));
+        let (y0, y1) = (from.1.min(to.1), from.1.max(to.1).min(self.height - 1));
+        let mut cells = Grid::new(x1 - x0 + 1, y1 - y0 + 1, Material::Empty);
+        let mut grains = Vec::new();
+        for y in y0..=y1 {
+            cells.row_mut(y - y0).copy_from_slice(&self.grid.row(y)[x0..=x1]);
+            for x in x0..=x1 {
+                let idx = self.index.get(x, y);
+                if idx != NO_GRAIN {
+                    grains.push(Grain { x: x - x0, y: y - y0, ..self.grains[idx as usize] });
+                }
+            }
+        }
+        Region { cells, grains }
+    }
+
+    /// Copy the box with corners `from` and `to` like `copy`, and empty it.
+    pub fn cut(&mut self, from: (usize, usize), to: (usize, usize)) -> Region {
+        let region = self.copy(from, to);
+        let (x0, y0) = (from.0.min(to.0), from.1.min(to.1));
+        let mut removed = false;
+        for y in y0..y0 + region.height() {
+            for x in x0..x0 + region.width() {
+                if self.grid.get(x, y) != Material::Empty {
+                    removed |= self.clear_cell(x, y);
+                }
+            }
+        }
+        if removed {
+            self.grains.retain(|g| g.material != Material::Empty);
+            self.reindex();
+        }
+        region
+    }
+
+    /// Paste `region` with its top-left at `(x, y)`. Its non-empty cells
+    /// replace whatever is under them, grains keeping how they were moving;
+    /// its empty cells leave the world as it is. Anything off the world is
+    /// dropped.
+    pub fn paste(&mut self, region: &Region, x: usize, y: usize) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let x1 = (x + region.width()).min(self.width) - 1;
+        let y1 = (y + region.height()).min(self.height) - 1;
+
+        // Clear the cells being pasted over first, so every grain under
+        // them goes before the new ones are indexed.
+        let mut removed = false;
+        for cy in y..=y1 {
+            for cx in x..=x1 {
+                let cell = self.grid.get(cx, cy);
+                if region.cells.get(cx - x, cy - y) != Material::Empty && cell != Material::Empty {
+                    removed |= self.clear_cell(cx, cy);
+                }
+            }
+        }
+        if removed {
+            self.grains.retain(|g| g.material != Material::Empty);
+            self.reindex();
+        }
+
+        for cy in y..=y1 {
+            for cx in x..=x1 {
+                let material = region.cells.get(cx - x, cy - y);
+                if material != Material::Empty && !material.is_movable() {
+                    self.grid.set(cx, cy, material);
+                    self.heat.set(cx, cy, material.props().temperature);
+                }
+            }
+        }
+        for g in &region.grains {
+            let (gx, gy) = (x + g.x, y + g.y);
+            if gx <= x1 && gy <= y1 {
+                self.add_grain(Grain { x: gx, y: gy, idle: 0, ..*g });
+            }
+        }
+        self.chunks.wake_rect(x, y, x1, y1);
+    }
+
+    /// Clear every cell inside the circle of `radius` around `(cx, cy)`,
+    /// grains and static cells alike. The area is woken so whatever was
+    /// resting on it falls into the hole.
+    pub fn erase(&mut self, cx: isize, cy: isize, radius: usize) {
+        let r = radius as isize;
+        let mut removed = false;
+        for dy in -r..=r {
+            for dx in -r..=r {
+                let (x, y) = (cx + dx, cy + dy);
+                if dx * dx + dy * dy > r * r || !self.in_bounds(x, y) {
+                    continue;
+                }
+                let (x, y) = (x as usize, y as usize);
+                if self.grid.get(x, y) == Material::Empty {
+                    continue;
+                }
+                removed |= self.clear_cell(x, y);
+            }
+        }
+        if removed {
+            self.grains.retain(|g| g.material != Material::Empty);
+            self.reindex();
+        }
+    }
+
+    /// Pour, paint or erase at the pointer as `input` says, without running
+    /// a tick, as a paused frontend does.
+    pub fn apply_input(&mut self, input: &InputState) {
+        input::apply(self, input);
+    }
+
+    /// Advance the simulation by one tick, after applying `input`; pass
+    /// `&InputState::NONE` to let the world run by itself.
+    pub fn step(&mut self, input: &InputState) {
+        input::apply(self, input);
+        self.chunks.advance();
+        self.ticks += 1;
+        self.emit();
+        weather::fall(self);
+        attractors::pull(self);
+
+        // Which diagonal is tried first flips every tick, so piles do not
+        // lean towards the side that always gets checked first.
+        let side: isize = if self.ticks.is_multiple_of(2) { -1 } else { 1 };
+
+        self.move_grains(side);
+        conveyors::run(self);
+        portals::run(self);
+
+        reactions::react(self);
+        power::conduct(self);
+        fountains::run(self);
+        climate::run(self);
+        explosions::detonate(self);
+        if input.drain {
+            self.drain();
+        }
+    }
+
+    /// Advance the simulation by one tick like `step`, moving grains on all
+    /// cores. Runs are repeatable for a given seed, but differ from `step`.
+    pub fn step_parallel(&mut self, input: &InputState) {
+        input::apply(self, input);
+        self.chunks.advance();
+        self.ticks += 1;
+        self.emit();
+        weather::fall(self);
+        attractors::pull(self);
+
+        let side: isize = if self.ticks.is_multiple_of(2) { -1 } else { 1 };
+        // Grains crossing a joined edge would reach chunks on the far side
+        // that another task may be updating, so those worlds move serially.
+        if self.wrap == Wrap::default() {
+            physics::step_parallel(self, side);
+        } else {
+            self.move_grains(side);
+        }
+        conveyors::run(self);
+        portals::run(self);
+
+        reactions::react(self);
+        power::conduct(self);
+        fountains::run(self);
+        climate::run(self);
+        explosions::detonate(self);
+        if input.drain {
+            self.drain();
+        }
+    }
+
+    /// Move every grain in an active chunk, one after another.
+    fn move_grains(&mut self, side: isize) {
+        for idx in (0..self.grains.len()).rev() {
+            let g = &self.grains[idx];
+            if self.chunks.is_active(g.x, g.y) && g.material.is_movable() && !g.is_asleep() {
+                physics::update_grain(self, idx, self.gravity, side);
+            }
+        }
+    }
+
+    pub fn drains(&self) -> &[Drain] {
+        &self.drains
+    }
+
+    pub fn add_drain(&mut self, drain: Drain) {
+        self.drains.push(drain);
+        self.drained.push(0);
+    }
+
+    /// How many grains each drain has swallowed, in the same order as
+    /// `drains`.
+    pub fn drained(&self) -> &[u64] {
+        &self.drained
+    }
+
+    /// How many grains all the drains have swallowed between them.
+    pub fn drained_total(&self) -> u64 {
+        self.drained.iter().sum()
+    }
+
+    /// Start every drain's count again from zero.
+    pub fn reset_drained(&mut self) {
+        self.drained.fill(0);
+    }
+
+    /// Remove the drain nearest `(x, y)`, if one lies within `reach` cells.
+    pub fn remove_drain_near(&mut self, x: usize, y: usize, reach: usize) -> Option<Drain> {
+        let distance = |d: &Drain| {
+            let (start, end) = d.span(self.width);
+            let dx = if x < start { start - x } else { x.saturating_sub(end) };
+            dx.max(y.abs_diff(d.y))
+        };
+        let nearest = (0..self.drains.len())
+            .filter(|&i| distance(&self.drains[i]) <= reach)
+            .min_by_key(|&i| distance(&self.drains[i]))?;
+        self.drained.remove(nearest);
+        Some(self.drains.remove(nearest))
+    }
+
+    pub fn emitters(&self) -> &[Emitter] {
+        &self.emitters
+    }
+
+    pub fn emitters_mut(&mut self) -> &mut [Emitter] {
+        &mut self.emitters
+    }
+
+    pub fn add_emitter(&mut self, emitter: Emitter) {
+        self.emitters.push(emitter);
+    }
+
+    /// Remove the emitter nearest `(x, y)`, if its centre lies within
+    /// `reach` cells.
+    pub fn remove_emitter_near(&mut self, x: usize, y: usize, reach: usize) -> Option<Emitter> {
+        let distance = |e: &Emitter| x.abs_diff(e.x).max(y.abs_diff(e.y));
+        let nearest = (0..self.emitters.len())
+            .filter(|&i| distance(&self.emitters[i]) <= reach)
+            .min_by_key(|&i| distance(&self.emitters[i]))?;
+        Some(self.emitters.remove(nearest))
+    }
+
+    pub fn attractors(&self) -> &[Attractor] {
+        &self.attractors
+    }
+
+    pub fn add_attractor(&mut self, attractor: Attractor) {
+        self.attractors.push(attractor);
+    }
+
+    /// Remove the attractor nearest `(x, y)`, if it lies within `reach`
+    /// cells.
+    pub fn remove_attractor_near(&mut self, x: usize, y: usize, reach: usize) -> Option<Attractor> {
+        let distance = |a: &Attractor| x.abs_diff(a.x).max(y.abs_diff(a.y));
+        let nearest = (0..self.attractors.len())
+            .filter(|&i| distance(&self.attractors[i]) <= reach)
+            .min_by_key(|&i| distance(&self.attractors[i]))?;
+        Some(self.attractors.remove(nearest))
+    }
+
+    pub fn portals(&self) -> &[Portal] {
+        &self.portals
+    }
+
+    /// Add a pair of portals, painting portal cells into the empty cells of
+    /// both ends.
+    pub fn add_portal(&mut self, portal: Portal) {
+        for (x, y) in [portal.a, portal.b] {
+            self.paint(x as isize, y as isize, portal.radius, Material::Portal);
+        }
+        self.portals.push(portal);
+    }
+
+    /// Remove the pair of portals with an end nearest `(x, y)`, if one lies
+    /// within `reach` cells, and the portal cells of both ends.
+    pub fn remove_portal_near(&mut self, x: usize, y: usize, reach: usize) -> Option<Portal> {
+        let distance = |p: &Portal| {
+            let to = |(px, py): (usize, usize)| x.abs_diff(px).max(y.abs_diff(py));
+            to(p.a).min(to(p.b))
+        };
+        let nearest = (0..self.portals.len())
+            .filter(|&i| distance(&self.portals[i]) <= reach)
+            .min_by_key(|&i| distance(&self.portals[i]))?;
+        let portal = self.portals.remove(nearest);
+        let r = portal.radius;
+        for end in [portal.a, portal.b] {
+            for y in end.1.saturating_sub(r)..=(end.1 + r).min(self.height - 1) {
+                for x in end.0.saturating_sub(r)..=(end.0 + r).min(self.width - 1) {
+                    if portal.covers(end, x, y) && self.grid.get(x, y) == Material::Portal {
+                        self.clear_cell(x, y);
+                    }
+                }
+            }
+        }
+        Some(portal)
+    }
+
+    /// Run every enabled emitter for one tick.
+    fn emit(&mut self) {
+        for i in 0..self.emitters.len() {
+            let e = self.emitters[i];
+            // Static materials are painted, not spawned, so have no grains
+            // to emit.
+            if !e.enabled || !e.material.is_movable() {
+                continue;
+            }
+            let extra = self.rng.uni() < e.rate.fract();
+            let tries = e.rate as usize + extra as usize;
+            self.spawn(e.x as isize, e.y as isize, e.radius, tries, e.material);
+        }
+    }
+
+    /// The row a drain dropped at `(x, y)` should sit on: the top of the
+    /// first static cell at or below it, or the bottom row.
+    pub fn floor_below(&self, x: usize, y: usize) -> usize {
+        (y..self.height)
+            .find(|&row| {
+                let cell = self.grid.get(x, row);
+                cell != Material::Empty && !cell.is_movable()
+            })
+            .unwrap_or(self.height - 1)
+    }
+
+    /// Open every drain, removing the grains in or resting on top of them
+    /// and counting them against the drain that took them.
+    pub fn drain(&mut self) {
+        let mut removed = false;
+        for i in 0..self.drains.len() {
+            let d = self.drains[i];
+            let (start, end) = d.span(self.width);
+            for y in d.y.saturating_sub(1)..=d.y {
+                for x in start..=end {
+                    let idx = self.index.get(x, y);
+                    if idx == NO_GRAIN {
+                        continue;
+                    }
+                    let material = self.grains[idx as usize].material;
+                    self.send(Event::GrainDrained { x, y, material });
+                    // Only cells whose grain was removed are cleared, so the
+                    // walls a drain sits in survive it being open.
+                    if self.clear_cell(x, y) {
+                        self.drained[i] += 1;
+                        removed = true;
+                    }
+                }
+            }
+        }
+        if removed {
+            self.grains.retain(|g| g.material != Material::Empty);
+            self.reindex();
+        }
+    }
+
+    /// Check that the grid, the grain index and the grain list agree: every
+    /// grain sits in a cell of its material that indexes back to it, and
+    /// every indexed cell or cell of a movable material belongs to a grain.
+    /// On a mismatch, returns a description of the first few problems found.
+    pub fn verify(&self) -> Result<(), String> {
+        const MAX_REPORTED: usize = 16;
+        let mut problems = Vec::new();
+        let mut report = |problem: String| {
+            if problems.len() < MAX_REPORTED {
+                problems.push(problem);
+            }
+        };
+        let mut found = 0;
+
+        for (i, g) in self.grains.iter().enumerate() {
+            if g.x >= self.width || g.y >= self.height {
+                found += 1;
+                report(format!(
+                    "grain {i} ({:?}) is outside the world at ({}, {})",
+                    g.material, g.x, g.y
+                ));
+                continue;
+            }
+            let cell = self.grid.get(g.x, g.y);
+            let idx = self.index.get(g.x, g.y);
+            if g.material == Material::Empty || cell != g.material || idx != i as u32 {
+                found += 1;
+                report(format!(
+                    "grain {i} ({:?}) at ({}, {}): cell holds {:?}, index holds {}",
+                    g.material,
+                    g.x,
+                    g.y,
+                    cell,
+                    if idx == NO_GRAIN { "no grain".to_string() } else { idx.to_string() },
+                ));
+            }
+        }
+
+        let mut indexed = 0;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let idx = self.index.get(x, y);
+                let cell = self.grid.get(x, y);
+                if idx == NO_GRAIN {
+                    if cell.is_movable() {
+                        found += 1;
+                        report(format!("cell ({x}, {y}) holds {cell:?} but no grain"));
+                    }
+                    continue;
+                }
+                indexed += 1;
+                match self.grains.get(idx as usize) {
+                    Some(g) if (g.x, g.y) == (x, y) => {}
+                    Some(g) => {
+                        found += 1;
+                        report(format!(
+                            "cell ({x}, {y}) indexes grain {idx}, which is at ({}, {})",
+                            g.x, g.y
+                        ));
+                    }
+                    None => {
+                        found += 1;
+                        report(format!(
+                            "cell ({x}, {y}) indexes grain {idx} of only {}",
+                            self.grains.len()
+                        ));
+                    }
+                }
+            }
+        }
+        if indexed != self.grains.len() {
+            found += 1;
+            report(format!(
+                "{indexed} cells index a grain but there are {} grains",
+                self.grains.len()
+            ));
+        }
+
+        if found == 0 {
+            return Ok(());
+        }
+        let mut text = problems.join("\n");
+        if found > problems.len() {
+            text.push_str(&format!("\n...and {} more", found - problems.len()));
+        }
+        Err(text)
+    }
+}
@@ -0,0 +1,222 @@
+//! Property tests: random sequences of spawning, painting walls, draining,
+//! turning gravity and stepping, checked after every tick against what must
+//! always hold. A failing case is shrunk to a shorter sequence, and saved
+//! in `invariants.proptest-regressions` to be tried first from then on.
+//!
+//! A few cases run by default, to keep the test quick. `PROPTEST_CASES=<n>`
+//! runs `n`, and `SANDFALL_CASE=<seed>` runs proptest's usual number from a
+//! fixed seed, so a run can be repeated.
+//!
+//! Only materials that never react are used, so grains can only come and
+//! go through spawning and draining.
+//!
+//! The plain tests after them check ways of adding grains in bulk: that
+//! they keep the same invariants, and send an event for every grain.
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use proptest::prelude::*;
+use proptest::sample::select;
+use proptest::test_runner::RngSeed;
+use sandfall::brush::{Brush, Shape};
+use sandfall::{Drain, Event, Gravity, InputState, Material, World};
+
+/// Random cases tried unless asked for more.
+const CASES: u32 = 8;
+/// Most operations per case.
+const OPS: usize = 120;
+
+const GRAINS: [Material; 3] = [Material::Sand, Material::Oil, Material::Gunpowder];
+const GRAVITIES: [Gravity; 4] = [Gravity::Down, Gravity::Left, Gravity::Up, Gravity::Right];
+
+/// One thing done to the world.
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Spawn { x: isize, y: isize, radius: usize, tries: usize, material: Material },
+    Wall { from: (usize, usize), to: (usize, usize) },
+    Gravity(Gravity),
+    Step { ticks: usize, parallel: bool, drain: bool },
+}
+
+fn op(width: usize, height: usize) -> impl Strategy<Value = Op> {
+    let (w, h) = (width as isize, height as isize);
+    let cell = move || (0..width, 0..height);
+    prop_oneof![
+        // Brushes may hang off the edges of the world.
+        4 => (-10..w + 10, -10..h + 10, 0..12usize, 1..80usize, select(&GRAINS[..])).prop_map(
+            |(x, y, radius, tries, material)| Op::Spawn { x, y, radius, tries, material }
+        ),
+        1 => (cell(), cell()).prop_map(|(from, to)| Op::Wall { from, to }),
+        1 => select(&GRAVITIES[..]).prop_map(Op::Gravity),
+        4 => (1..30usize, any::<bool>(), prop::bool::weighted(0.25))
+            .prop_map(|(ticks, parallel, drain)| Op::Step { ticks, parallel, drain }),
+    ]
+}
+
+/// A world's size, seed and drain, and what is done to it.
+#[derive(Clone, Debug)]
+struct Case {
+    width: usize,
+    height: usize,
+    seed: i32,
+    /// Half the width of the drain along the bottom.
+    drain: usize,
+    ops: Vec<Op>,
+}
+
+fn case() -> impl Strategy<Value = Case> {
+    (40..200usize, 40..160usize).prop_flat_map(|(width, height)| {
+        (0..900_000_000, 0..width / 2, prop::collection::vec(op(width, height), 1..=OPS))
+            .prop_map(move |(seed, drain, ops)| Case { width, height, seed, drain, ops })
+    })
+}
+
+/// The world for `case`, with its drain, and counters of the grains
+/// spawned and drained.
+fn world(case: &Case) -> (World, Arc<AtomicUsize>, Arc<AtomicUsize>) {
+    let mut world = World::new(case.width, case.height, case.seed);
+    world.add_drain(Drain { x: case.width / 2, y: case.height - 1, half_width: case.drain });
+
+    let spawned = Arc::new(AtomicUsize::new(0));
+    let drained = Arc::new(AtomicUsize::new(0));
+    let (s, d) = (spawned.clone(), drained.clone());
+    world.on_event(move |event| match event {
+        Event::GrainSpawned { .. } => {
+            s.fetch_add(1, Ordering::Relaxed);
+        }
+        Event::GrainDrained { .. } => {
+            d.fetch_add(1, Ordering::Relaxed);
+        }
+        _ => {}
+    });
+    (world, spawned, drained)
+}
+
+/// Everything that must hold after any tick, or a description of what
+/// does not.
+fn check(world: &World, spawned: usize, drained: usize) -> Result<(), String> {
+    world.verify()?;
+
+    let grains = world.grains();
+    if grains.len() != spawned - drained {
+        return Err(format!(
+            "{} grains, but {spawned} were spawned and {drained} drained",
+            grains.len()
+        ));
+    }
+
+    let (width, height) = (world.width(), world.height());
+    let mut seen = HashSet::new();
+    for g in grains {
+        if g.x >= width || g.y >= height {
+            return Err(format!("grain at ({}, {}) is outside the world", g.x, g.y));
+        }
+        if !seen.insert((g.x, g.y)) {
+            return Err(format!("two grains at ({}, {})", g.x, g.y));
+        }
+    }
+
+    // A grain with nothing under it must still be falling, not asleep.
+    let (gx, gy) = world.gravity().vector();
+    for g in grains {
+        let (bx, by) = (g.x as isize + gx, g.y as isize + gy);
+        let supported = !world.cells().in_bounds(bx, by)
+            || world.cells().get(bx as usize, by as usize) != Material::Empty;
+        if !supported && g.is_asleep() {
+            return Err(format!("{:?} at ({}, {}) is asleep over a hole", g.material, g.x, g.y));
+        }
+    }
+    Ok(())
+}
+
+/// Run `case`, checking after every tick.
+fn run_case(case: &Case) -> Result<(), String> {
+    let (mut world, spawned, drained) = world(case);
+    for (i, &op) in case.ops.iter().enumerate() {
+        match op {
+            Op::Spawn { x, y, radius, tries, material } => {
+                world.spawn(x, y, radius, tries, material);
+            }
+            Op::Wall { from, to } => world.rect(from, to, Material::Wall, true),
+            Op::Gravity(gravity) => world.set_gravity(gravity),
+            Op::Step { ticks, parallel, drain } => {
+                for _ in 0..ticks {
+                    if parallel {
+                        world.step_parallel(&InputState::NONE);
+                    } else {
+                        world.step(&InputState::NONE);
+                    }
+                    if drain {
+                        world.drain();
+                    }
+                    let spawned = spawned.load(Ordering::Relaxed);
+                    let drained = drained.load(Ordering::Relaxed);
+                    check(&world, spawned, drained).map_err(|e| {
+                        format!("after op {i} ({op:?}), tick {}: {e}", world.ticks())
+                    })?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Proptest's settings, read from its environment variables, with fewer
+/// cases unless they or `SANDFALL_CASE` ask for more.
+fn config() -> ProptestConfig {
+    let mut config = ProptestConfig::default();
+    match std::env::var("SANDFALL_CASE") {
+        Ok(seed) => {
+            config.rng_seed = RngSeed::Fixed(seed.parse().expect("SANDFALL_CASE must be a number"))
+        }
+        Err(_) if std::env::var_os("PROPTEST_CASES").is_none() => config.cases = CASES,
+        Err(_) => {}
+    }
+    config
+}
+
+proptest! {
+    #![proptest_config(config())]
+
+    #[test]
+    fn invariants_hold_over_random_sequences(case in case()) {
+        run_case(&case).map_err(TestCaseError::fail)?;
+    }
+}
+
+#[test]
+fn disc_stroke_of_grains_keeps_invariants() {
+    let mut world = World::new(80, 60, 1);
+    let mut brush = Brush::new(6, 0);
+    brush.shape = Shape::Disc;
+    brush.stroke(&mut world, (20, 20), (50, 30), Material::Sand);
+    assert!(!world.grains().is_empty());
+    for _ in 0..30 {
+        world.verify().unwrap();
+        world.step(&InputState::NONE);
+    }
+}
+
+#[test]
+fn boxes_and_pastes_send_a_spawn_event_for_every_grain() {
+    let mut world = World::new(80, 60, 1);
+    let spawned = Arc::new(AtomicUsize::new(0));
+    let s = spawned.clone();
+    world.on_event(move |event| {
+        if let Event::GrainSpawned { .. } = event {
+            s.fetch_add(1, Ordering::Relaxed);
+        }
+    });
+
+    world.rect((10, 10), (19, 14), Material::Sand, false);
+    assert_eq!(world.grains().len(), 50);
+    assert_eq!(spawned.load(Ordering::Relaxed), 50);
+
+    let region = world.copy((10, 10), (19, 14));
+    world.paste(&region, 40, 30);
+    assert_eq!(world.grains().len(), 100);
+    assert_eq!(spawned.load(Ordering::Relaxed), 100);
+    world.verify().unwrap();
+}